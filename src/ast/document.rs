@@ -0,0 +1,9 @@
+use super::Paragraph;
+
+/// A whole litx source file: the blank-line separated paragraphs, in order.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Document {
+    pub paragraphs: Vec<Paragraph>
+}
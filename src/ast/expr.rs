@@ -0,0 +1,92 @@
+use super::Location;
+use super::Math;
+use super::NumberLit;
+use super::StringLit;
+use std::fmt;
+
+/// An expression such as `{h1 "Title"}` or `{litx :title "Example"}`.
+/// The name is the leading identifier, if there is one; keyword properties may appear anywhere in the body.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Expr {
+    pub name: Option<Ident>,
+    pub args: Vec<Value>,
+    pub props: Vec<Prop>,
+    pub location: Location
+}
+
+/// A `:key value` pair inside an expression.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Prop {
+    pub key: Ident,
+    pub value: Value,
+    pub location: Location
+}
+
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Ident {
+    pub name: String,
+    pub location: Location
+}
+
+/// Anything which can be passed to an expression, positionally or as a property.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum Value {
+    Ident(Ident),
+    String(StringLit),
+    Number(NumberLit),
+    Expr(Expr),
+    Math(Math)
+}
+
+impl Value {
+    pub fn location(&self) -> &Location {
+        match *self {
+            Value::Ident(ref ident) => &ident.location,
+            Value::String(ref string) => &string.location,
+            Value::Number(ref number) => &number.location,
+            Value::Expr(ref expr) => &expr.location,
+            Value::Math(ref math) => &math.location
+        }
+    }
+}
+
+/// Prints the expression back out as litx source.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{{")?;
+        let mut first = true;
+        if let Some(ref name) = self.name {
+            write!(f, "{}", name.name)?;
+            first = false;
+        }
+        for arg in &self.args {
+            write!(f, "{}{}", if first { "" } else { " " }, arg)?;
+            first = false;
+        }
+        for prop in &self.props {
+            write!(f, "{}:{} {}", if first { "" } else { " " }, prop.key.name, prop.value)?;
+            first = false;
+        }
+        write!(f, "}}")
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Ident(ref ident) => write!(f, "{}", ident.name),
+            Value::String(ref string) => write!(f, "{}", string),
+            Value::Number(ref number) => write!(f, "{}", number.source),
+            Value::Expr(ref expr) => write!(f, "{}", expr),
+            Value::Math(ref math) => write!(f, "{}", math)
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use super::Location;
+use std::fmt;
+
+/// The contents of a `"..."` string, with escapes resolved.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct StringLit {
+    pub value: String,
+    pub location: Location
+}
+
+/// A number, along with the text it was written as.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct NumberLit {
+    pub value: f64,
+    pub source: String,
+    pub location: Location
+}
+
+/// Prints the string back out as a quoted litx literal.
+impl fmt::Display for StringLit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"")?;
+        for c in self.value.chars() {
+            match c {
+                '"' | '\\' => write!(f, "\\{}", c)?,
+                _ => write!(f, "{}", c)?
+            }
+        }
+        write!(f, "\"")
+    }
+}
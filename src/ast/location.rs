@@ -0,0 +1,44 @@
+use ::lex::Token;
+use ::source::Span;
+use std::fmt;
+
+/// Where a node sits in its source: `index` and `end` are the byte offsets bounding it,
+/// and `line` and `column` are where it starts. Lines and columns are zero-based, like those of `Span`.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(PartialEq)]
+pub struct Location {
+    pub file: Option<String>,
+    pub index: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize
+}
+
+impl Location {
+    pub fn from_token(token: &Token) -> Self {
+        Location::from_span(&token.get_span())
+    }
+
+    pub fn from_span(span: &Span) -> Self {
+        Location {
+            file: span.file.map(str::to_string),
+            index: span.start,
+            end: span.end,
+            line: span.start_line_col.0,
+            column: span.start_line_col.1
+        }
+    }
+
+    /// Whether `other` lies within this node, in the same file.
+    pub fn contains(&self, other: &Location) -> bool {
+        self.file == other.file && self.index <= other.index && other.end <= self.end
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file.as_deref().unwrap_or("<input>"), self.line + 1, self.column + 1)
+    }
+}
@@ -0,0 +1,370 @@
+use ::lex::Token;
+use ::lex::TokenType;
+use ::parse::Branch;
+use ::parse::Production;
+use ::parse::ProductionType;
+use super::Document;
+use super::Expr;
+use super::Ident;
+use super::Inline;
+use super::Location;
+use super::Math;
+use super::MathTerm;
+use super::NumberLit;
+use super::Paragraph;
+use super::Prop;
+use super::StringLit;
+use super::Text;
+use super::Value;
+
+pub fn lower_document(s: &Production) -> Document {
+    let mut paragraphs = Vec::new();
+    for blocks in nonterminals(s, ProductionType::Blocks) {
+        for block in nonterminals(blocks, ProductionType::Block) {
+            let paragraph = lower_paragraph(block);
+            // A block made up only of comments has nothing left to show.
+            if !paragraph.items.is_empty() {
+                paragraphs.push(paragraph);
+            }
+        }
+    }
+    Document { paragraphs }
+}
+
+fn lower_paragraph(block: &Production) -> Paragraph {
+    let mut items = Vec::new();
+    for child in block.get_children() {
+        if let Branch::Nonterminal(ref prod) = *child {
+            match prod.get_type() {
+                ProductionType::Text => lower_text(prod, &mut items),
+                ProductionType::Expr => items.push(Inline::Expr(lower_expr(prod))),
+                ProductionType::Math | ProductionType::DisplayMath => items.push(Inline::Math(lower_math(prod))),
+                ProductionType::WhiteSpace => push_space(&mut items),
+                _ => {}
+            }
+        }
+    }
+    while let Some(&Inline::Space) = items.last() {
+        items.pop();
+    }
+    Paragraph {
+        items,
+        location: location_of(block)
+    }
+}
+
+fn lower_text(text: &Production, items: &mut Vec<Inline>) {
+    for child in text.get_children() {
+        match *child {
+            Branch::Terminal(ref token) => {
+                if token.get_type() == TokenType::Escaped && token.get_content() == "\\n" {
+                    items.push(Inline::LineBreak);
+                    continue;
+                }
+                let value = match token.get_type() {
+                    TokenType::Escaped => unescape(token),
+                    _ => token.get_content()
+                };
+                // Whitespace always produces a `Space`, so a preceding `Text` must be directly adjacent.
+                if let Some(&mut Inline::Text(ref mut last)) = items.last_mut() {
+                    last.value.push_str(value);
+                    continue;
+                }
+                items.push(Inline::Text(Text {
+                    value: value.to_string(),
+                    location: Location::from_token(token)
+                }));
+            },
+            Branch::Nonterminal(_) => push_space(items)
+        }
+    }
+}
+
+fn push_space(items: &mut Vec<Inline>) {
+    match items.last() {
+        None
+        | Some(&Inline::Space)
+        | Some(&Inline::LineBreak) => {},
+        _ => items.push(Inline::Space)
+    }
+}
+
+fn lower_expr(expr: &Production) -> Expr {
+    let mut args = Vec::new();
+    let mut props = Vec::new();
+    for body in nonterminals(expr, ProductionType::ExprBody) {
+        for child in body.get_children() {
+            match *child {
+                Branch::Nonterminal(ref prod) if prod.get_type() == ProductionType::ExprProp => {
+                    props.push(lower_prop(prod));
+                },
+                _ => args.extend(lower_value(child))
+            }
+        }
+    }
+    let name = match args.first() {
+        Some(&Value::Ident(_)) => match args.remove(0) {
+            Value::Ident(ident) => Some(ident),
+            _ => unreachable!()
+        },
+        _ => None
+    };
+    Expr {
+        name,
+        args,
+        props,
+        location: location_of(expr)
+    }
+}
+
+fn lower_prop(prop: &Production) -> Prop {
+    let mut children = prop.get_children().iter().skip(1);
+    let key = match *children.next().unwrap() {
+        Branch::Nonterminal(ref ident) => lower_ident(ident),
+        _ => unreachable!()
+    };
+    Prop {
+        key,
+        value: children.filter_map(lower_value).next().expect("the parser reports a property without a value"),
+        location: location_of(prop)
+    }
+}
+
+fn lower_value(branch: &Branch) -> Option<Value> {
+    match *branch {
+        Branch::Terminal(ref token) if token.get_type() == TokenType::Number => {
+            Some(Value::Number(NumberLit {
+                value: token.get_content().parse().unwrap(),
+                source: token.get_content().to_string(),
+                location: Location::from_token(token)
+            }))
+        },
+        Branch::Terminal(_) => None,
+        Branch::Nonterminal(ref prod) => match prod.get_type() {
+            ProductionType::ExprIdent => Some(Value::Ident(lower_ident(prod))),
+            ProductionType::String => Some(Value::String(lower_string(prod))),
+            ProductionType::Expr => Some(Value::Expr(lower_expr(prod))),
+            ProductionType::Math | ProductionType::DisplayMath => Some(Value::Math(lower_math(prod))),
+            _ => None
+        }
+    }
+}
+
+fn lower_ident(ident: &Production) -> Ident {
+    Ident {
+        name: terminals(ident).iter().map(|tok| tok.get_content()).collect(),
+        location: location_of(ident)
+    }
+}
+
+fn lower_string(string: &Production) -> StringLit {
+    let mut value = String::new();
+    for body in nonterminals(string, ProductionType::StringBody) {
+        for token in terminals(body) {
+            match token.get_type() {
+                TokenType::Escaped if token.get_content() == "\\n" => value.push('\n'),
+                TokenType::Escaped => value.push_str(unescape(token)),
+                _ => value.push_str(token.get_content())
+            }
+        }
+    }
+    StringLit {
+        value,
+        location: location_of(string)
+    }
+}
+
+fn lower_math(math: &Production) -> Math {
+    let mut terms = Vec::new();
+    let mut source = String::new();
+    for body in nonterminals(math, ProductionType::MathBody) {
+        lower_math_body(body, &mut terms, &mut source);
+    }
+    while let Some(&MathTerm::Space) = terms.last() {
+        terms.pop();
+    }
+    Math {
+        terms,
+        source: source.trim().to_string(),
+        display: math.get_type() == ProductionType::DisplayMath,
+        location: location_of(math)
+    }
+}
+
+fn lower_math_body(body: &Production, terms: &mut Vec<MathTerm>, source: &mut String) {
+    for child in body.get_children() {
+        match *child {
+            Branch::Terminal(ref token) => {
+                source.push_str(token.get_content());
+                if token.get_type() == TokenType::Escaped {
+                    terms.push(MathTerm::Escaped(unescape(token).to_string()));
+                } else if let Some(&mut MathTerm::Text(ref mut last)) = terms.last_mut() {
+                    last.push_str(token.get_content());
+                } else {
+                    terms.push(MathTerm::Text(token.get_content().to_string()));
+                }
+            },
+            Branch::Nonterminal(ref prod) => {
+                match prod.get_type() {
+                    ProductionType::MathBody => {
+                        lower_math_body(prod, terms, source);
+                        continue;
+                    },
+                    ProductionType::Comment => continue,
+                    ProductionType::WhiteSpace => match terms.last() {
+                        None | Some(&MathTerm::Space) => {},
+                        _ => terms.push(MathTerm::Space)
+                    },
+                    ProductionType::Expr => terms.push(MathTerm::Expr(lower_expr(prod))),
+                    ProductionType::Math => terms.push(MathTerm::Math(lower_math(prod))),
+                    _ => {}
+                }
+                for token in terminals(prod) {
+                    source.push_str(token.get_content());
+                }
+            }
+        }
+    }
+}
+
+fn unescape<'a>(token: &Token<'a, '_>) -> &'a str {
+    &token.get_content()[1..]
+}
+
+fn location_of(prod: &Production) -> Location {
+    let tokens = terminals(prod);
+    match (tokens.first(), tokens.last()) {
+        (Some(first), Some(last)) => Location::from_span(&first.get_span().to(&last.get_span())),
+        _ => Location::default()
+    }
+}
+
+fn nonterminals<'p, 'a, 'b>(prod: &'p Production<'a, 'b>, ty: ProductionType) -> Vec<&'p Production<'a, 'b>> {
+    prod.get_children().iter()
+        .filter_map(|child| match *child {
+            Branch::Nonterminal(ref child) if child.get_type() == ty => Some(child),
+            _ => None
+        })
+        .collect()
+}
+
+/// Every token below `prod` in source order, skipping over comments.
+fn terminals<'p, 'a, 'b>(prod: &'p Production<'a, 'b>) -> Vec<&'p Token<'a, 'b>> {
+    let mut tokens = Vec::new();
+    for child in prod.get_children() {
+        match *child {
+            Branch::Terminal(ref token) => tokens.push(token),
+            Branch::Nonterminal(ref child) if child.get_type() != ProductionType::Comment => {
+                tokens.extend(terminals(child));
+            },
+            Branch::Nonterminal(_) => {}
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod test {
+    use ::ast::*;
+    use ::lex::Lexer;
+    use ::parse::parse;
+
+    fn lower(source: &str) -> Document {
+        ::ast::lower(&parse(Lexer::new(source)).unwrap())
+    }
+
+    fn words(paragraph: &Paragraph) -> Vec<String> {
+        paragraph.items.iter()
+            .map(|item| match *item {
+                Inline::Text(ref text) => text.value.clone(),
+                Inline::Space => " ".to_string(),
+                Inline::LineBreak => "\n".to_string(),
+                Inline::Expr(_) => "{}".to_string(),
+                Inline::Math(_) => "{$$}".to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn paragraphs_and_text() {
+        let doc = lower("{! lead !}\n  One,\ttwo {emph x}.\nFoo\\nbar \\{ok{!c!}ay \n\n\nSecond.");
+        assert_eq!(doc.paragraphs.len(), 2);
+        assert_eq!(words(&doc.paragraphs[0]), vec![
+            "One,", " ", "two", " ", "{}", ".", " ", "Foo", "\n", "bar", " ", "{okay"
+        ]);
+        assert_eq!(words(&doc.paragraphs[1]), vec!["Second."]);
+        assert_eq!(doc.paragraphs[1].location.line, 5);
+    }
+
+    #[test]
+    fn expressions() {
+        let doc = lower("{litx\n    :title \"An \\\"Example\\\"\"\n    :authors {list \"A\" b 2.5}}");
+        let expr = match doc.paragraphs[0].items[0] {
+            Inline::Expr(ref expr) => expr,
+            _ => panic!()
+        };
+        assert_eq!(expr.name.as_ref().unwrap().name, "litx");
+        assert!(expr.args.is_empty());
+        assert_eq!(expr.props.len(), 2);
+        assert_eq!(expr.props[0].key.name, "title");
+        match expr.props[0].value {
+            Value::String(ref string) => assert_eq!(string.value, "An \"Example\""),
+            _ => panic!()
+        }
+        let list = match expr.props[1].value {
+            Value::Expr(ref list) => list,
+            _ => panic!()
+        };
+        assert_eq!(list.name.as_ref().unwrap().name, "list");
+        assert_eq!(list.args.len(), 3);
+        assert_eq!(list.location.line, 2);
+        match list.args[1] {
+            Value::Ident(ref ident) => assert_eq!(ident.name, "b"),
+            _ => panic!()
+        }
+        match list.args[2] {
+            Value::Number(ref number) => assert_eq!(number.value, 2.5),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn comments_are_not_property_values() {
+        let doc = lower("{foo :bar {! c !} 5}");
+        let expr = match doc.paragraphs[0].items[0] {
+            Inline::Expr(ref expr) => expr,
+            _ => panic!()
+        };
+        match expr.props[0].value {
+            Value::Number(ref number) => assert_eq!(number.value, 5.0),
+            _ => panic!()
+        }
+        let errors = parse(Lexer::new("{foo :bar {! c !}}")).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].get_code(), ::diagnostics::Code::UnexpectedToken);
+        assert_eq!(errors[0].get_message(), "expected a property value, found `}`");
+    }
+
+    #[test]
+    fn math() {
+        let doc = lower("{$ 1+2 \\a lpha {! c !} {$x$} $}");
+        let math = match doc.paragraphs[0].items[0] {
+            Inline::Math(ref math) => math,
+            _ => panic!()
+        };
+        assert_eq!(math.source, "1+2 \\a lpha  {$x$}");
+        assert_eq!(math.terms.len(), 7);
+        assert_eq!(math.terms[0], MathTerm::Text("1+2".to_string()));
+        assert_eq!(math.terms[2], MathTerm::Escaped("a".to_string()));
+        assert!(!math.display);
+
+        let doc = lower("{$$ x {$y$} $$}");
+        match doc.paragraphs[0].items[0] {
+            Inline::Math(ref math) => {
+                assert!(math.display);
+                assert_eq!(math.to_string(), "{$$ x {$y$} $$}");
+            },
+            _ => panic!()
+        }
+    }
+}
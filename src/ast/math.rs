@@ -0,0 +1,59 @@
+use super::Expr;
+use super::Location;
+use std::fmt;
+
+/// A `{$ ... $}` context, or a `{$$ ... $$}` one for math displayed on a line of its own. Its body is kept as loosely
+/// grouped terms for later interpretation, along with the source text (minus comments) for backends which only want
+/// it verbatim.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Math {
+    pub terms: Vec<MathTerm>,
+    pub source: String,
+    pub display: bool,
+    pub location: Location
+}
+
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum MathTerm {
+    /// Adjacent word, number and symbol tokens, e.g. `1+2` or `x^2`.
+    Text(String),
+    /// The character following a backslash, e.g. `a` for `\a`.
+    Escaped(String),
+    Space,
+    Expr(Expr),
+    Math(Math)
+}
+
+impl Math {
+    /// Rebuilds `source` after the terms have been changed.
+    pub fn update_source(&mut self) {
+        self.source = self.terms.iter().map(MathTerm::to_string).collect();
+    }
+}
+
+/// Prints the math back out as litx source.
+impl fmt::Display for Math {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.display {
+            write!(f, "{{$$ {} $$}}", self.source)
+        } else {
+            write!(f, "{{$ {} $}}", self.source)
+        }
+    }
+}
+
+impl fmt::Display for MathTerm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MathTerm::Text(ref text) => write!(f, "{}", text),
+            MathTerm::Escaped(ref escaped) => write!(f, "\\{}", escaped),
+            MathTerm::Space => write!(f, " "),
+            MathTerm::Expr(ref expr) => write!(f, "{}", expr),
+            MathTerm::Math(ref math) => write!(f, "{}", math)
+        }
+    }
+}
@@ -0,0 +1,28 @@
+mod document;
+mod expr;
+mod literal;
+mod location;
+mod lower;
+mod math;
+mod paragraph;
+mod text;
+
+pub use self::document::Document;
+pub use self::expr::Expr;
+pub use self::expr::Ident;
+pub use self::expr::Prop;
+pub use self::expr::Value;
+pub use self::literal::NumberLit;
+pub use self::literal::StringLit;
+pub use self::location::Location;
+pub use self::math::Math;
+pub use self::math::MathTerm;
+pub use self::paragraph::Inline;
+pub use self::paragraph::Paragraph;
+pub use self::text::Text;
+
+/// Lowers a successfully parsed `S` production into a typed document.
+/// Whitespace is collapsed, escapes are resolved, and comments are dropped.
+pub fn lower(tree: &::parse::Production) -> Document {
+    lower::lower_document(tree)
+}
@@ -0,0 +1,26 @@
+use super::Expr;
+use super::Location;
+use super::Math;
+use super::Text;
+
+/// A run of content between blank lines, lowered from a `Block` production.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Paragraph {
+    pub items: Vec<Inline>,
+    pub location: Location
+}
+
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum Inline {
+    Text(Text),
+    /// Any amount of whitespace between two items, including single new lines.
+    Space,
+    /// An explicit `\n` escape.
+    LineBreak,
+    Expr(Expr),
+    Math(Math)
+}
@@ -0,0 +1,10 @@
+use super::Location;
+
+/// A word of prose: adjacent text tokens joined together, with escapes resolved.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Text {
+    pub value: String,
+    pub location: Location
+}
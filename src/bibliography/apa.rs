@@ -0,0 +1,143 @@
+use ::eval::Node;
+use super::CitationStyle;
+use super::Entry;
+use super::Name;
+use super::style;
+use super::style::contributors;
+use super::style::emph_title;
+use super::style::is_part;
+use super::style::join_names;
+use super::style::parenthesise;
+use super::style::sentence;
+use super::style::title;
+use super::style::year;
+
+/// The American Psychological Association's style, as in `(Knuth, 1984, p. 12)` and
+/// `Knuth, D. E. (1984). The TeXbook. Addison-Wesley.`
+pub struct Apa;
+
+impl CitationStyle for Apa {
+    fn references_title(&self) -> &str {
+        "References"
+    }
+
+    fn tell_apart(&self, entries: &mut [Entry]) {
+        style::add_year_suffixes(entries);
+    }
+
+    fn format_citation(&self, works: &[(&Entry, Option<&str>)]) -> Vec<Node> {
+        parenthesise(works.iter().map(|&(entry, page)| {
+            let families: Vec<String> = contributors(entry).iter().map(|name| name.family.clone()).collect();
+            let mut nodes = match families.len() {
+                0 => title(entry, ""),
+                1 | 2 => Node::text(&join_names(&families, " & ", "")),
+                _ => Node::text(&format!("{} et al.", families[0]))
+            };
+            nodes.push(Node::Text(",".to_string()));
+            nodes.push(Node::Space);
+            nodes.extend(Node::text(&year(entry)));
+            if let Some(page) = page {
+                nodes.push(Node::Text(",".to_string()));
+                nodes.push(Node::Space);
+                nodes.extend(Node::text(&pages(page)));
+            }
+            nodes
+        }).collect())
+    }
+
+    fn format_reference(&self, entry: &Entry) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        let names = contributors(entry);
+        let date = Node::text(&format!("({})", year(entry)));
+        if names.is_empty() {
+            sentence(&mut nodes, reference_title(entry));
+            sentence(&mut nodes, date);
+        } else {
+            let inverted: Vec<String> = names.iter().map(inverted_initials).collect();
+            let mut text = join_names(&inverted, ", & ", ", & ");
+            if entry.authors.is_empty() {
+                text.push_str(if names.len() == 1 { " (Ed.)" } else { " (Eds.)" });
+            }
+            sentence(&mut nodes, Node::text(&text));
+            sentence(&mut nodes, date);
+            sentence(&mut nodes, reference_title(entry));
+        }
+
+        match entry.kind.as_str() {
+            "article" => if let Some(journal) = entry.get("journal") {
+                let mut container = vec![Node::Emph(Node::text(journal))];
+                if let Some(volume) = entry.get("volume") {
+                    container.push(Node::Text(",".to_string()));
+                    container.push(Node::Space);
+                    container.push(Node::Emph(Node::text(volume)));
+                }
+                if let Some(number) = entry.get("number") {
+                    container.push(Node::Text(format!("({})", number)));
+                }
+                if let Some(pages) = entry.get("pages") {
+                    container.push(Node::Text(",".to_string()));
+                    container.push(Node::Space);
+                    container.extend(Node::text(pages));
+                }
+                sentence(&mut nodes, container);
+            },
+            "inproceedings" | "incollection" => if let Some(book) = entry.get("booktitle") {
+                let mut container = Node::text("In ");
+                container.push(Node::Emph(Node::text(book)));
+                if let Some(range) = entry.get("pages") {
+                    container.push(Node::Space);
+                    container.extend(Node::text(&format!("({})", pages(range))));
+                }
+                sentence(&mut nodes, container);
+            },
+            _ => ()
+        }
+
+        if let Some(publisher) = entry.get("publisher").or_else(|| entry.get("school")).or_else(|| entry.get("institution")) {
+            sentence(&mut nodes, Node::text(publisher));
+        }
+        // Links end a reference without a full stop, so they can be followed as written.
+        let link = entry.get("doi").map(|doi| format!("https://doi.org/{}", doi)).or_else(|| entry.get("url").map(str::to_string));
+        if let Some(link) = link {
+            nodes.push(Node::Space);
+            nodes.extend(Node::text(&link));
+        }
+        nodes
+    }
+}
+
+/// `Family, G. N.`, with each given name cut to its initial.
+fn inverted_initials(name: &Name) -> String {
+    if name.given.is_empty() {
+        return name.family.clone();
+    }
+    let initials: Vec<String> = name.given.split_whitespace()
+        .map(|given| {
+            let parts: Vec<String> = given.split('-')
+                .filter_map(|part| part.chars().next())
+                .map(|initial| format!("{}.", initial))
+                .collect();
+            parts.join("-")
+        })
+        .collect();
+    format!("{}, {}", name.family, initials.join(" "))
+}
+
+/// Whole works are titled in italics, and parts of them plainly.
+fn reference_title(entry: &Entry) -> Vec<Node> {
+    let title = entry.get("title").unwrap_or("Untitled");
+    if is_part(entry) {
+        Node::text(title)
+    } else {
+        emph_title(title, "")
+    }
+}
+
+/// `p. 12` for a single page, and `pp. 12–14` for a range or list of them.
+fn pages(pages: &str) -> String {
+    if pages.contains(['-', '\u{2013}', ',']) {
+        format!("pp. {}", pages)
+    } else {
+        format!("p. {}", pages)
+    }
+}
@@ -0,0 +1,17 @@
+use ::ast::Location;
+use std::fmt;
+
+/// A problem in a bibliography database, such as an unclosed entry or a field of the wrong type.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct BibError {
+    pub message: String,
+    pub location: Location
+}
+
+impl fmt::Display for BibError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
@@ -0,0 +1,366 @@
+use ::ast::Location;
+use ::source::SourceFile;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use super::BibError;
+use super::Entry;
+use super::Name;
+use super::entry::strip_braces;
+
+const MONTHS: [(&str, &str); 12] = [
+    ("jan", "January"), ("feb", "February"), ("mar", "March"), ("apr", "April"), ("may", "May"), ("jun", "June"),
+    ("jul", "July"), ("aug", "August"), ("sep", "September"), ("oct", "October"), ("nov", "November"), ("dec", "December")
+];
+
+/// Accents as LaTeX writes them, such as `\"o`, with the letters they go on and the accented letters they make.
+const ACCENTS: [(char, &str, &str); 5] = [
+    ('\'', "aeiouycnAEIOUYCN", "áéíóúýćńÁÉÍÓÚÝĆŃ"),
+    ('`', "aeiouAEIOU", "àèìòùÀÈÌÒÙ"),
+    ('^', "aeiouAEIOU", "âêîôûÂÊÎÔÛ"),
+    ('"', "aeiouyAEIOUY", "äëïöüÿÄËÏÖÜŸ"),
+    ('~', "anoANO", "ãñõÃÑÕ")
+];
+
+/// Commands which print their own names.
+const LOGOS: [&str; 3] = ["TeX", "LaTeX", "BibTeX"];
+
+/// Reads every entry in a BibTeX database, with `@string` abbreviations expanded and LaTeX escapes such as `\"o` and
+/// `--` turned into the characters they stand for. Text outside entries is a comment, as it is to BibTeX.
+///
+/// An entry with an error in it is skipped, and reading carries on with the next, so all the errors are found at once.
+pub fn read_bibtex(source: &str, filename: &str) -> (Vec<Entry>, Vec<BibError>) {
+    let mut parser = Parser {
+        source,
+        file: SourceFile::new(filename, source),
+        position: 0,
+        strings: MONTHS.iter().map(|&(abbreviation, month)| (abbreviation.to_string(), month.to_string())).collect()
+    };
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    while let Some(at) = source[parser.position..].find('@') {
+        let start = parser.position + at;
+        parser.position = start;
+        match parser.entry() {
+            Ok(Some(entry)) => entries.push(entry),
+            Ok(None) => (),
+            Err(err) => {
+                errors.push(err);
+                // Carry on from the next line which could start an entry.
+                parser.position = source[start..].find("\n@")
+                    .map(|next| start + next + 1)
+                    .unwrap_or(source.len());
+            }
+        }
+    }
+    (entries, errors)
+}
+
+struct Parser<'s> {
+    source: &'s str,
+    file: SourceFile,
+    position: usize,
+    /// `@string` abbreviations, which start out as the months.
+    strings: HashMap<String, String>
+}
+
+impl <'s> Parser<'s> {
+    /// Reads the entry at the `@` at the current position, or `None` for a comment, preamble or abbreviation.
+    fn entry(&mut self) -> Result<Option<Entry>, BibError> {
+        let start = self.position;
+        self.position += 1;
+        let kind = self.identifier("an entry type after `@`")?.to_lowercase();
+        self.skip_space();
+        let close = match self.next_char() {
+            Some('{') => '}',
+            Some('(') => ')',
+            _ => return Err(self.error_at(start, format!("expected `{{` after `@{}`", kind)))
+        };
+        match kind.as_str() {
+            "comment" | "preamble" => {
+                self.position -= 1;
+                self.balanced(start)?;
+                return Ok(None);
+            },
+            "string" => {
+                self.skip_space();
+                let (name, value) = self.field()?;
+                self.expect(close)?;
+                self.strings.insert(name, value);
+                return Ok(None);
+            },
+            _ => ()
+        }
+
+        self.skip_space();
+        let key_start = self.position;
+        let key_end = self.source[key_start..].find(|c: char| c == ',' || c == close || c.is_whitespace())
+            .map(|end| key_start + end)
+            .unwrap_or(self.source.len());
+        let key = self.source[key_start..key_end].to_string();
+        if key.is_empty() {
+            return Err(self.error_at(key_start, format!("expected a key for the `@{}` entry", kind)));
+        }
+        self.position = key_end;
+        let mut entry = Entry {
+            key,
+            kind,
+            authors: Vec::new(),
+            editors: Vec::new(),
+            fields: BTreeMap::new(),
+        year_suffix: String::new(),
+        shares_authors: false,
+            location: self.location(start, key_end)
+        };
+        loop {
+            self.skip_space();
+            match self.next_char() {
+                Some(',') => (),
+                Some(c) if c == close => break,
+                _ => return Err(self.error_at(self.position, format!("expected `,` or `{}` in the entry `{}`", close, entry.key)))
+            }
+            self.skip_space();
+            if self.source[self.position..].starts_with(close) {
+                self.position += 1;
+                break;
+            }
+            let (name, value) = self.field()?;
+            match name.as_str() {
+                "author" => entry.authors = Name::parse_list(&value),
+                "editor" => entry.editors = Name::parse_list(&value),
+                _ => {
+                    entry.fields.insert(name, strip_braces(&value));
+                }
+            }
+        }
+        Ok(Some(entry))
+    }
+
+    /// Reads `name = value`, where the value is any number of pieces joined with `#`. Braces are kept in the value,
+    /// for names to be split by.
+    fn field(&mut self) -> Result<(String, String), BibError> {
+        let name = self.identifier("a field name")?.to_lowercase();
+        self.skip_space();
+        self.expect('=')?;
+        let mut value = String::new();
+        loop {
+            self.skip_space();
+            let start = self.position;
+            match self.source[start..].chars().next() {
+                Some('{') => {
+                    self.balanced(start)?;
+                    value.push_str(&decode(&self.source[start + 1..self.position - 1]));
+                },
+                Some('"') => {
+                    self.position += 1;
+                    let mut depth = 0;
+                    loop {
+                        match self.next_char() {
+                            Some('{') => depth += 1,
+                            Some('}') => depth -= 1,
+                            Some('"') if depth == 0 => break,
+                            Some(_) => (),
+                            None => return Err(self.error_at(start, "this quoted value is never closed"))
+                        }
+                    }
+                    value.push_str(&decode(&self.source[start + 1..self.position - 1]));
+                },
+                Some(c) if c.is_ascii_digit() => {
+                    let end = self.source[start..].find(|c: char| !c.is_ascii_digit()).map(|end| start + end).unwrap_or(self.source.len());
+                    value.push_str(&self.source[start..end]);
+                    self.position = end;
+                },
+                _ => {
+                    let abbreviation = self.identifier("a value")?.to_lowercase();
+                    match self.strings.get(&abbreviation) {
+                        Some(expansion) => value.push_str(expansion),
+                        None => return Err(self.error_at(start, format!("`{}` is not a defined `@string`", abbreviation)))
+                    }
+                }
+            }
+            self.skip_space();
+            if !self.source[self.position..].starts_with('#') {
+                break;
+            }
+            self.position += 1;
+        }
+        Ok((name, value.split_ascii_whitespace().collect::<Vec<_>>().join(" ")))
+    }
+
+    /// Skips past the braces or parentheses opening at the current position and everything between them. Only the
+    /// kind which opened counts, so a value such as `{Part 1) Intro}` holds any number of the other.
+    fn balanced(&mut self, start: usize) -> Result<(), BibError> {
+        let open = self.next_char();
+        let close = if open == Some('(') { ')' } else { '}' };
+        let mut depth = 1;
+        while let Some(c) = self.next_char() {
+            if Some(c) == open {
+                depth += 1;
+            } else if c == close {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(());
+                }
+            }
+        }
+        Err(self.error_at(start, "this is never closed"))
+    }
+
+    fn identifier(&mut self, expected: &str) -> Result<String, BibError> {
+        let start = self.position;
+        let end = self.source[start..]
+            .find(|c: char| !(c.is_alphanumeric() || "_-:.+/'".contains(c)))
+            .map(|end| start + end)
+            .unwrap_or(self.source.len());
+        if end == start {
+            return Err(self.error_at(start, format!("expected {}", expected)));
+        }
+        self.position = end;
+        Ok(self.source[start..end].to_string())
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), BibError> {
+        if self.source[self.position..].starts_with(expected) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.error_at(self.position, format!("expected `{}`", expected)))
+        }
+    }
+
+    fn skip_space(&mut self) {
+        let rest = &self.source[self.position..];
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.source[self.position..].chars().next()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn location(&self, start: usize, end: usize) -> Location {
+        let (line, column) = self.file.get_line_col(start);
+        Location { file: Some(self.file.get_name().to_string()), index: start, end, line, column }
+    }
+
+    fn error_at<S: Into<String>>(&self, at: usize, message: S) -> BibError {
+        let end = self.source[at..].chars().next().map(|c| at + c.len_utf8()).unwrap_or(at);
+        BibError { message: message.into(), location: self.location(at, end) }
+    }
+}
+
+/// Turns LaTeX's escapes and dashes into the characters they stand for. Braces which only group are left in place.
+fn decode(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(accent) if ACCENTS.iter().any(|&(a, _, _)| a == accent) => {
+                    let braced = chars.peek() == Some(&'{');
+                    if braced {
+                        chars.next();
+                    }
+                    if let Some(letter) = chars.next() {
+                        decoded.push(accented(accent, letter));
+                    }
+                    if braced && chars.peek() == Some(&'}') {
+                        chars.next();
+                    }
+                },
+                Some(escaped) if "&%$#_{}".contains(escaped) => decoded.push(escaped),
+                // Other commands, such as `\emph`, are dropped and their arguments kept, except for logos like `\TeX`.
+                Some(letter) if letter.is_alphabetic() => {
+                    let mut command = letter.to_string();
+                    while let Some(letter) = chars.next_if(|c| c.is_alphabetic()) {
+                        command.push(letter);
+                    }
+                    if LOGOS.contains(&command.as_str()) {
+                        decoded.push_str(&command);
+                    }
+                },
+                Some(other) => decoded.push(other),
+                None => ()
+            },
+            '~' => decoded.push('\u{a0}'),
+            '-' if chars.peek() == Some(&'-') => {
+                chars.next();
+                if chars.peek() == Some(&'-') {
+                    chars.next();
+                    decoded.push('\u{2014}');
+                } else {
+                    decoded.push('\u{2013}');
+                }
+            },
+            _ => decoded.push(c)
+        }
+    }
+    decoded
+}
+
+fn accented(accent: char, letter: char) -> char {
+    ACCENTS.iter()
+        .find(|&&(a, _, _)| a == accent)
+        .and_then(|&(_, plain, marked)| plain.chars().position(|c| c == letter).and_then(|i| marked.chars().nth(i)))
+        .unwrap_or(letter)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reads_entries() {
+        let source = "Comments go anywhere.\n\
+            @string{tug = \"TeX Users Group\"}\n\
+            @Book{knuth84,\n  author = {Donald E. Knuth},\n  title = \"The {\\TeX}book\",\n  year = 1984,\n  month = feb,\n\
+              publisher = tug # {, Reading}}\n\
+            @article(goedel31, author = {G{\\\"o}del, Kurt}, pages = {173--198}, note = {50\\% off~now},)\n";
+        let (entries, errors) = read_bibtex(source, "refs.bib");
+        assert_eq!(errors, vec![]);
+        assert_eq!(entries.len(), 2);
+        let knuth = &entries[0];
+        assert_eq!((knuth.key.as_str(), knuth.kind.as_str()), ("knuth84", "book"));
+        assert_eq!(knuth.authors, vec![Name { given: "Donald E.".to_string(), family: "Knuth".to_string() }]);
+        assert_eq!(knuth.get("title"), Some("The TeXbook"));
+        assert_eq!(knuth.get("year"), Some("1984"));
+        assert_eq!(knuth.get("month"), Some("February"));
+        assert_eq!(knuth.get("publisher"), Some("TeX Users Group, Reading"));
+        assert_eq!((knuth.location.line, knuth.location.column), (2, 0));
+        let goedel = &entries[1];
+        assert_eq!(goedel.authors, vec![Name { given: "Kurt".to_string(), family: "G\u{f6}del".to_string() }]);
+        assert_eq!(goedel.get("pages"), Some("173\u{2013}198"));
+        assert_eq!(goedel.get("note"), Some("50% off\u{a0}now"));
+    }
+
+    #[test]
+    fn recovers_from_errors() {
+        let source = "@book{a, title = {Unclosed\n@book{b, title = undefined}\n@book{c, year = 2000}\n@book{, year = 1}";
+        let (entries, errors) = read_bibtex(source, "refs.bib");
+        assert_eq!(entries.iter().map(|entry| entry.key.as_str()).collect::<Vec<_>>(), vec!["c"]);
+        let errors: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+        assert_eq!(errors, vec![
+            "refs.bib:1:18: this is never closed",
+            "refs.bib:2:18: `undefined` is not a defined `@string`",
+            "refs.bib:4:7: expected a key for the `@book` entry"
+        ]);
+    }
+
+    #[test]
+    fn counts_only_the_opening_delimiter() {
+        let source = "@comment(a {b)\n@book{a, title = {Part 1) Intro}, note = {(}}\n@book(b, title = {Part 1) Intro})";
+        let (entries, errors) = read_bibtex(source, "refs.bib");
+        assert_eq!(errors, vec![]);
+        let titles: Vec<_> = entries.iter().map(|entry| (entry.key.as_str(), entry.get("title"))).collect();
+        assert_eq!(titles, vec![("a", Some("Part 1) Intro")), ("b", Some("Part 1) Intro"))]);
+        assert_eq!(entries[0].get("note"), Some("("));
+    }
+
+    #[test]
+    fn strings_may_be_spaced() {
+        let source = "@string{ aw = \"Addison-Wesley\" }\n@string( tug = {TeX Users Group} )\n@book{a, publisher = aw # { and } # tug}";
+        let (entries, errors) = read_bibtex(source, "refs.bib");
+        assert_eq!(errors, vec![]);
+        assert_eq!(entries[0].get("publisher"), Some("Addison-Wesley and TeX Users Group"));
+    }
+}
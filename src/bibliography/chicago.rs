@@ -0,0 +1,132 @@
+use ::eval::Node;
+use super::CitationStyle;
+use super::Entry;
+use super::style;
+use super::style::contributors;
+use super::style::join_names;
+use super::style::parenthesise;
+use super::style::sentence;
+use super::style::title;
+use super::style::year;
+
+/// The Chicago Manual of Style's author–date system, as in `(Knuth 1984, 12)` and
+/// `Knuth, Donald E. 1984. The TeXbook. Reading, MA: Addison-Wesley.`
+pub struct Chicago;
+
+impl CitationStyle for Chicago {
+    fn references_title(&self) -> &str {
+        "References"
+    }
+
+    fn tell_apart(&self, entries: &mut [Entry]) {
+        style::add_year_suffixes(entries);
+    }
+
+    fn format_citation(&self, works: &[(&Entry, Option<&str>)]) -> Vec<Node> {
+        parenthesise(works.iter().map(|&(entry, page)| {
+            let mut nodes = match short_names(entry) {
+                Some(names) => Node::text(&names),
+                None => title(entry, "")
+            };
+            nodes.push(Node::Space);
+            nodes.extend(Node::text(&year(entry)));
+            if let Some(page) = page {
+                nodes.push(Node::Text(",".to_string()));
+                nodes.push(Node::Space);
+                nodes.extend(Node::text(page));
+            }
+            nodes
+        }).collect())
+    }
+
+    fn format_reference(&self, entry: &Entry) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        let names = long_names(entry);
+        if let Some(ref names) = names {
+            sentence(&mut nodes, Node::text(names));
+        } else {
+            sentence(&mut nodes, title(entry, "."));
+        }
+        sentence(&mut nodes, Node::text(&year(entry)));
+        if names.is_some() {
+            sentence(&mut nodes, title(entry, "."));
+        }
+
+        match entry.kind.as_str() {
+            "article" => if let Some(journal) = entry.get("journal") {
+                let mut container = vec![Node::Emph(Node::text(journal))];
+                if let Some(volume) = entry.get("volume") {
+                    container.push(Node::Space);
+                    container.extend(Node::text(volume));
+                }
+                if let Some(number) = entry.get("number") {
+                    container.push(Node::Space);
+                    container.extend(Node::text(&format!("({})", number)));
+                }
+                if let Some(pages) = entry.get("pages") {
+                    container.push(Node::Text(":".to_string()));
+                    container.push(Node::Space);
+                    container.extend(Node::text(pages));
+                }
+                sentence(&mut nodes, container);
+            },
+            "inproceedings" | "incollection" => if let Some(book) = entry.get("booktitle") {
+                let mut container = Node::text("In ");
+                container.push(Node::Emph(Node::text(book)));
+                if let Some(pages) = entry.get("pages") {
+                    container.push(Node::Text(",".to_string()));
+                    container.push(Node::Space);
+                    container.extend(Node::text(pages));
+                }
+                sentence(&mut nodes, container);
+            },
+            _ => ()
+        }
+
+        let publisher = entry.get("publisher")
+            .or_else(|| entry.get("school"))
+            .or_else(|| entry.get("institution"));
+        match (entry.get("address"), publisher) {
+            (Some(address), Some(publisher)) => sentence(&mut nodes, Node::text(&format!("{}: {}", address, publisher))),
+            (None, Some(publisher)) => sentence(&mut nodes, Node::text(publisher)),
+            _ => ()
+        }
+        if let Some(doi) = entry.get("doi") {
+            sentence(&mut nodes, Node::text(&format!("https://doi.org/{}", doi)));
+        } else if let Some(url) = entry.get("url") {
+            sentence(&mut nodes, Node::text(url));
+        }
+        nodes
+    }
+}
+
+/// Family names as a citation gives them: `A`, `A and B`, `A, B, and C`, or `A et al.` for four or more.
+fn short_names(entry: &Entry) -> Option<String> {
+    let families: Vec<String> = contributors(entry).iter().map(|name| name.family.clone()).collect();
+    match families.len() {
+        0 => None,
+        1..=3 => Some(join_names(&families, " and ", ", and ")),
+        _ => Some(format!("{} et al.", families[0]))
+    }
+}
+
+/// Full names as a list of references gives them, with the first inverted so the list can be sorted by it.
+fn long_names(entry: &Entry) -> Option<String> {
+    let names = contributors(entry);
+    if names.is_empty() {
+        return None;
+    }
+    let full: Vec<String> = names.iter()
+        .enumerate()
+        .map(|(i, name)| match (i, name.given.is_empty()) {
+            (_, true) => name.family.clone(),
+            (0, false) => format!("{}, {}", name.family, name.given),
+            (_, false) => format!("{} {}", name.given, name.family)
+        })
+        .collect();
+    let mut text = join_names(&full, ", and ", ", and ");
+    if entry.authors.is_empty() {
+        text.push_str(if names.len() == 1 { ", ed" } else { ", eds" });
+    }
+    Some(text)
+}
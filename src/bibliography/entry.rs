@@ -0,0 +1,137 @@
+use ::ast::Location;
+use std::collections::BTreeMap;
+
+/// A person's name, split into the part a bibliography sorts by and the rest.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub struct Name {
+    pub given: String,
+    pub family: String
+}
+
+impl Name {
+    /// Reads a name written as BibTeX writes them: `Family, Given` or `Given Family`, where a lower case word such as
+    /// `van` starts the family name, and a name in braces, such as `{World Health Organization}`, is kept whole.
+    /// Braces elsewhere are removed.
+    pub fn parse(text: &str) -> Self {
+        let text = text.trim();
+        if text.starts_with('{') && matching_brace(text, 0) == Some(text.len() - 1) {
+            return Name { given: String::new(), family: text[1..text.len() - 1].trim().to_string() };
+        }
+        let parts = split_top_level(text, |c| c == ',');
+        if parts.len() > 1 {
+            return Name { given: strip_braces(parts.last().unwrap()), family: strip_braces(parts[0]) };
+        }
+        let words = split_top_level(text, char::is_whitespace);
+        if words.is_empty() {
+            return Name { given: String::new(), family: String::new() };
+        }
+        let last = words.len() - 1;
+        let family_start = words[..last].iter()
+            .position(|word| word.starts_with(char::is_lowercase))
+            .unwrap_or(last);
+        Name {
+            given: strip_braces(&words[..family_start].join(" ")),
+            family: strip_braces(&words[family_start..].join(" "))
+        }
+    }
+
+    /// Reads a list of names separated by `and`, as in a BibTeX `author` field.
+    pub fn parse_list(text: &str) -> Vec<Self> {
+        let words = split_top_level(text, char::is_whitespace);
+        words.split(|word| word.eq_ignore_ascii_case("and"))
+            .filter(|name| !name.is_empty())
+            .map(|name| Name::parse(&name.join(" ")))
+            .collect()
+    }
+}
+
+/// One work in a bibliography database.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Entry {
+    pub key: String,
+    /// The type of work, as BibTeX names it, such as `article` or `book`, in lower case.
+    pub kind: String,
+    pub authors: Vec<Name>,
+    pub editors: Vec<Name>,
+    /// Every other field, by its name in lower case, as plain text.
+    pub fields: BTreeMap<String, String>,
+    /// The letter telling this work from others by the same authors in the same year, such as the `a` of `2020a`.
+    /// Empty until the citation style gives it one.
+    pub year_suffix: String,
+    /// Whether another work cited is by the same authors, for styles which then cite it by title as well.
+    pub shares_authors: bool,
+    pub location: Location
+}
+
+impl Entry {
+    pub fn get(&self, field: &str) -> Option<&str> {
+        self.fields.get(field).map(String::as_str)
+    }
+}
+
+/// Where the `}` closing the `{` at `open` in `text` is.
+fn matching_brace(text: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 1 => return Some(open + i),
+            '}' => depth -= 1,
+            _ => ()
+        }
+    }
+    None
+}
+
+/// Splits `text` at every character matching `separator` outside braces, dropping empty pieces and trimming the rest.
+fn split_top_level<F: Fn(char) -> bool>(text: &str, separator: F) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            c if depth == 0 && separator(c) => {
+                pieces.push(text[start..i].trim());
+                start = i + c.len_utf8();
+            },
+            _ => ()
+        }
+    }
+    pieces.push(text[start..].trim());
+    pieces.retain(|piece| !piece.is_empty());
+    pieces
+}
+
+/// Removes grouping braces, which BibTeX uses to protect text from case changes.
+pub fn strip_braces(text: &str) -> String {
+    text.chars().filter(|&c| c != '{' && c != '}').collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn name(given: &str, family: &str) -> Name {
+        Name { given: given.to_string(), family: family.to_string() }
+    }
+
+    #[test]
+    fn parses_names() {
+        assert_eq!(Name::parse("Donald E. Knuth"), name("Donald E.", "Knuth"));
+        assert_eq!(Name::parse("Knuth, Donald E."), name("Donald E.", "Knuth"));
+        assert_eq!(Name::parse("Ludwig van Beethoven"), name("Ludwig", "van Beethoven"));
+        assert_eq!(Name::parse("{World Health Organization}"), name("", "World Health Organization"));
+        assert_eq!(Name::parse("Plato"), name("", "Plato"));
+        assert_eq!(Name::parse_list("Alfred V. Aho and {Sethi and Sons} AND Ullman, Jeffrey"), vec![
+            name("Alfred V.", "Aho"),
+            name("", "Sethi and Sons"),
+            name("Jeffrey", "Ullman")
+        ]);
+    }
+}
@@ -0,0 +1,120 @@
+use ::eval::Node;
+use super::CitationStyle;
+use super::Entry;
+use super::style;
+use super::style::contributors;
+use super::style::emph_title;
+use super::style::is_part;
+use super::style::parenthesise;
+use super::style::sentence;
+use super::style::title;
+
+/// The Modern Language Association's style, as in `(Knuth 12)` and
+/// `Knuth, Donald E. The TeXbook. Addison-Wesley, 1984.` Works by authors with others cited add a short title, as in
+/// `(Knuth, TeXbook 12)`.
+pub struct Mla;
+
+impl CitationStyle for Mla {
+    fn references_title(&self) -> &str {
+        "Works Cited"
+    }
+
+    fn tell_apart(&self, entries: &mut [Entry]) {
+        style::mark_shared_authors(entries);
+    }
+
+    fn format_citation(&self, works: &[(&Entry, Option<&str>)]) -> Vec<Node> {
+        parenthesise(works.iter().map(|&(entry, page)| {
+            let families: Vec<&str> = contributors(entry).iter().map(|name| name.family.as_str()).collect();
+            let mut nodes = match families.len() {
+                0 => title(entry, ""),
+                1 => Node::text(families[0]),
+                2 => Node::text(&format!("{} and {}", families[0], families[1])),
+                _ => Node::text(&format!("{} et al.", families[0]))
+            };
+            if entry.shares_authors {
+                nodes.push(Node::Text(",".to_string()));
+                nodes.push(Node::Space);
+                nodes.extend(short_title(entry));
+            }
+            if let Some(page) = page {
+                nodes.push(Node::Space);
+                nodes.extend(Node::text(page));
+            }
+            nodes
+        }).collect())
+    }
+
+    fn format_reference(&self, entry: &Entry) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        let names = contributors(entry);
+        if let Some(first) = names.first() {
+            let first = if first.given.is_empty() { first.family.clone() } else { format!("{}, {}", first.family, first.given) };
+            let mut text = match names.len() {
+                1 => first,
+                2 if names[1].given.is_empty() => format!("{}, and {}", first, names[1].family),
+                2 => format!("{}, and {} {}", first, names[1].given, names[1].family),
+                _ => format!("{}, et al.", first)
+            };
+            if entry.authors.is_empty() {
+                text.push_str(if names.len() == 1 { ", editor" } else { ", editors" });
+            }
+            sentence(&mut nodes, Node::text(&text));
+        }
+        sentence(&mut nodes, title(entry, "."));
+
+        // The container the work was published in, then its publication details, all in one sentence.
+        let mut details: Vec<Vec<Node>> = Vec::new();
+        match entry.kind.as_str() {
+            "article" => {
+                details.extend(entry.get("journal").map(|journal| vec![Node::Emph(Node::text(journal))]));
+                details.extend(entry.get("volume").map(|volume| Node::text(&format!("vol. {}", volume))));
+                details.extend(entry.get("number").map(|number| Node::text(&format!("no. {}", number))));
+            },
+            "inproceedings" | "incollection" => {
+                details.extend(entry.get("booktitle").map(|book| vec![Node::Emph(Node::text(book))]));
+            },
+            _ => ()
+        }
+        let publisher = entry.get("publisher").or_else(|| entry.get("school")).or_else(|| entry.get("institution"));
+        details.extend(publisher.map(Node::text));
+        details.extend(entry.get("year").map(Node::text));
+        if let Some(pages) = entry.get("pages") {
+            let prefix = if pages.contains(['-', '\u{2013}', ',']) { "pp." } else { "p." };
+            details.push(Node::text(&format!("{} {}", prefix, pages)));
+        }
+        let mut container = Vec::new();
+        for (i, detail) in details.into_iter().enumerate() {
+            if i > 0 {
+                container.push(Node::Text(",".to_string()));
+                container.push(Node::Space);
+            }
+            container.extend(detail);
+        }
+        sentence(&mut nodes, container);
+
+        if let Some(doi) = entry.get("doi") {
+            sentence(&mut nodes, Node::text(&format!("https://doi.org/{}", doi)));
+        } else if let Some(url) = entry.get("url") {
+            sentence(&mut nodes, Node::text(url));
+        }
+        nodes
+    }
+}
+
+/// The title cut to the words a citation needs: without a subtitle or a leading article, and at most four words long.
+fn short_title(entry: &Entry) -> Vec<Node> {
+    let title = entry.get("title").unwrap_or("Untitled");
+    let main = title.split(':').next().unwrap_or(title);
+    let mut words: Vec<&str> = main.split_whitespace().collect();
+    if words.len() > 1 && ["a", "an", "the"].contains(&words[0].to_lowercase().as_str()) {
+        words.remove(0);
+    }
+    words.truncate(4);
+    let short = words.join(" ");
+    if is_part(entry) {
+        Node::text(&format!("\u{201c}{}\u{201d}", short))
+    } else {
+        emph_title(&short, "")
+    }
+}
@@ -0,0 +1,26 @@
+//! Bibliography databases, read from BibTeX or litx files, and the citation styles which format their entries.
+//!
+//! The styles are written in Rust rather than read from CSL XML files. Reading CSL would need an XML parser, which
+//! litx does without, and the three styles its doctypes name are covered by `Chicago`, `Apa` and `Mla`. A style from a
+//! CSL file would be one more `CitationStyle`.
+
+mod apa;
+mod bib_error;
+mod bibtex;
+mod chicago;
+mod entry;
+mod mla;
+mod native;
+mod style;
+
+pub use self::apa::Apa;
+pub use self::bib_error::BibError;
+pub use self::bibtex::read_bibtex;
+pub use self::chicago::Chicago;
+pub use self::entry::Entry;
+pub use self::entry::Name;
+pub use self::mla::Mla;
+pub use self::native::read_native;
+pub use self::style::citation_style;
+pub use self::style::CitationStyle;
+pub use self::style::CITATION_STYLES;
@@ -0,0 +1,132 @@
+use ::ast::Document;
+use ::ast::Expr;
+use ::ast::Inline;
+use ::ast::Location;
+use ::ast::Prop;
+use ::ast::Value;
+use std::collections::BTreeMap;
+use super::BibError;
+use super::Entry;
+use super::Name;
+
+/// Reads a bibliography written in litx, as a series of entries such as
+/// `{entry knuth84 :type book :author "Donald E. Knuth" :title "The TeXbook" :year 1984}`.
+///
+/// `:author` and `:editor` take a string of names separated by `and`, as BibTeX does, or a `{list ...}` of names.
+/// An entry without a `:type` is `misc`. Nothing but entries may appear in the file.
+pub fn read_native(doc: &Document) -> (Vec<Entry>, Vec<BibError>) {
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    for paragraph in &doc.paragraphs {
+        for item in &paragraph.items {
+            let result = match *item {
+                Inline::Space | Inline::LineBreak => continue,
+                Inline::Expr(ref expr) => entry(expr),
+                Inline::Text(ref text) => Err(outside_entry(&text.location)),
+                Inline::Math(ref math) => Err(outside_entry(&math.location))
+            };
+            match result {
+                Ok(entry) => entries.push(entry),
+                Err(err) => errors.push(err)
+            }
+        }
+    }
+    (entries, errors)
+}
+
+fn entry(expr: &Expr) -> Result<Entry, BibError> {
+    match expr.name {
+        Some(ref name) if name.name == "entry" => (),
+        _ => return Err(error(&expr.location, "expected an `{entry ...}`"))
+    }
+    let key = match expr.args.first().and_then(as_text) {
+        Some(key) if expr.args.len() == 1 => key,
+        _ => return Err(error(&expr.location, "an entry takes exactly one key, as in `{entry knuth84 ...}`"))
+    };
+    let mut entry = Entry {
+        key,
+        kind: "misc".to_string(),
+        authors: Vec::new(),
+        editors: Vec::new(),
+        fields: BTreeMap::new(),
+        year_suffix: String::new(),
+        shares_authors: false,
+        location: expr.location.clone()
+    };
+    for prop in &expr.props {
+        match prop.key.name.as_str() {
+            "type" => entry.kind = text(prop)?.to_lowercase(),
+            "author" => entry.authors = names(prop)?,
+            "editor" => entry.editors = names(prop)?,
+            field => {
+                let value = text(prop)?;
+                if entry.fields.insert(field.to_string(), value).is_some() {
+                    return Err(error(&prop.location, format!("`{}` is set more than once", field)));
+                }
+            }
+        }
+    }
+    Ok(entry)
+}
+
+fn as_text(value: &Value) -> Option<String> {
+    match *value {
+        Value::Ident(ref ident) => Some(ident.name.clone()),
+        Value::String(ref string) => Some(string.value.clone()),
+        Value::Number(ref number) => Some(number.source.clone()),
+        _ => None
+    }
+}
+
+fn text(prop: &Prop) -> Result<String, BibError> {
+    as_text(&prop.value).ok_or_else(|| error(&prop.location, format!("`{}` expects a string", prop.key.name)))
+}
+
+/// A string of names separated by `and`, or a `{list ...}` of names.
+fn names(prop: &Prop) -> Result<Vec<Name>, BibError> {
+    match prop.value {
+        Value::Expr(ref list) if list.name.as_ref().map(|name| name.name == "list").unwrap_or(false) => {
+            list.args.iter()
+                .map(|item| as_text(item)
+                    .map(|name| Name::parse(&name))
+                    .ok_or_else(|| error(item.location(), format!("`{}` expects a list of names", prop.key.name))))
+                .collect()
+        },
+        _ => text(prop).map(|names| Name::parse_list(&names))
+    }
+}
+
+fn outside_entry(location: &Location) -> BibError {
+    error(location, "only `{entry ...}` expressions may appear in a bibliography")
+}
+
+fn error<S: Into<String>>(location: &Location, message: S) -> BibError {
+    BibError { message: message.into(), location: location.clone() }
+}
+
+#[cfg(test)]
+mod test {
+    use ::lex::Lexer;
+    use ::parse::parse;
+    use super::*;
+
+    #[test]
+    fn reads_entries() {
+        let source = "{entry knuth84 :type Book :author \"Donald E. Knuth\" :title \"The TeXbook\" :year 1984}\n\
+            {entry \"aho86\" :author {list \"Alfred V. Aho\" \"Ravi Sethi\"}}\n\n\
+            Stray text {entry} {entry a :title {emph \"x\"}}";
+        let doc = ::ast::lower(&parse(Lexer::new_with_filename(source, "refs.litx")).unwrap());
+        let (entries, errors) = read_native(&doc);
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].kind.as_str(), entries[0].get("year")), ("book", Some("1984")));
+        assert_eq!(entries[0].authors, vec![Name { given: "Donald E.".to_string(), family: "Knuth".to_string() }]);
+        assert_eq!((entries[1].key.as_str(), entries[1].kind.as_str(), entries[1].authors.len()), ("aho86", "misc", 2));
+        let errors: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+        assert_eq!(errors, vec![
+            "refs.litx:4:1: only `{entry ...}` expressions may appear in a bibliography",
+            "refs.litx:4:7: only `{entry ...}` expressions may appear in a bibliography",
+            "refs.litx:4:12: an entry takes exactly one key, as in `{entry knuth84 ...}`",
+            "refs.litx:4:29: `title` expects a string"
+        ]);
+    }
+}
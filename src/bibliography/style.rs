@@ -0,0 +1,243 @@
+use ::eval::Node;
+use super::Apa;
+use super::Chicago;
+use super::Entry;
+use super::Mla;
+use super::Name;
+
+/// The names `citation_style` knows, in the order error messages list them.
+pub const CITATION_STYLES: [&str; 3] = ["chicago", "apa", "mla"];
+
+/// A way of formatting citations and lists of references, such as MLA's.
+///
+/// Styles are chosen by the `{litx ...}` header's `:citation-style`, or by its `:doctype` when that names a style.
+/// Only the built-in styles exist; CSL style files are not read.
+pub trait CitationStyle {
+    /// The heading `{bibliography}` gives the list of references, such as `Works Cited`.
+    fn references_title(&self) -> &str;
+
+    /// Formats an in-text citation of one or more works, each with the page cited, if there is one.
+    fn format_citation(&self, works: &[(&Entry, Option<&str>)]) -> Vec<Node>;
+
+    /// Formats an entry for the list of references.
+    fn format_reference(&self, entry: &Entry) -> Vec<Node>;
+
+    /// Puts entries in the order the list of references gives them: by their authors' family names, then year,
+    /// then title.
+    fn sort_entries(&self, entries: &mut [Entry]) {
+        entries.sort_by_cached_key(|entry| {
+            let names = contributors(entry).iter()
+                .map(|name| format!("{} {}", name.family, name.given))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let title = entry.get("title").unwrap_or("").to_lowercase();
+            let first = if names.is_empty() { title.clone() } else { names.to_lowercase() };
+            (first, year(entry), title)
+        });
+    }
+
+    /// Marks works by the same authors, which `sort_entries` has put together, so that their citations can be told
+    /// apart, as by `add_year_suffixes` or `mark_shared_authors`.
+    fn tell_apart(&self, _entries: &mut [Entry]) {}
+}
+
+/// The built-in style called `name`. `chicago-author-date` is another name for `chicago`.
+pub fn citation_style(name: &str) -> Option<Box<dyn CitationStyle>> {
+    match name.to_lowercase().as_str() {
+        "chicago" | "chicago-author-date" => Some(Box::new(Chicago)),
+        "apa" => Some(Box::new(Apa)),
+        "mla" => Some(Box::new(Mla)),
+        _ => None
+    }
+}
+
+/// The authors of an entry, or its editors if it has no authors.
+pub fn contributors(entry: &Entry) -> &[Name] {
+    if entry.authors.is_empty() {
+        &entry.editors
+    } else {
+        &entry.authors
+    }
+}
+
+/// The year of publication, or `n.d.` for no date, with the entry's suffix, as in `2020a` or `n.d.-a`.
+pub fn year(entry: &Entry) -> String {
+    match entry.get("year") {
+        Some(year) => format!("{}{}", year, entry.year_suffix),
+        None if entry.year_suffix.is_empty() => "n.d.".to_string(),
+        None => format!("n.d.-{}", entry.year_suffix)
+    }
+}
+
+/// Suffixes the years of sorted entries whose authors and year are the same as a neighbour's with `a`, `b` and so on,
+/// in the order they are in.
+pub fn add_year_suffixes(entries: &mut [Entry]) {
+    for_each_run(entries, |a, b| a.get("year") == b.get("year"), |run| {
+        for (entry, letter) in run.iter_mut().zip(('a'..='z').cycle()) {
+            entry.year_suffix = letter.to_string();
+        }
+    });
+}
+
+/// Marks each of the sorted entries whose authors are the same as a neighbour's.
+pub fn mark_shared_authors(entries: &mut [Entry]) {
+    for_each_run(entries, |_, _| true, |run| {
+        for entry in run {
+            entry.shares_authors = true;
+        }
+    });
+}
+
+/// Calls `mark` with each run of two or more neighbouring entries by the same authors which are `alike`. Works without
+/// authors are cited by title, which tells them apart already.
+fn for_each_run<A: Fn(&Entry, &Entry) -> bool, M: FnMut(&mut [Entry])>(entries: &mut [Entry], alike: A, mut mark: M) {
+    let same = |a: &Entry, b: &Entry| !contributors(a).is_empty() && contributors(a) == contributors(b) && alike(a, b);
+    let mut start = 0;
+    while start < entries.len() {
+        let end = (start + 1..entries.len())
+            .find(|&i| !same(&entries[start], &entries[i]))
+            .unwrap_or(entries.len());
+        if end - start > 1 {
+            mark(&mut entries[start..end]);
+        }
+        start = end;
+    }
+}
+
+/// Joins two names with `two`, as in `A and B`, and more with commas and then `many`, as in `A, B, and C`.
+pub fn join_names(names: &[String], two: &str, many: &str) -> String {
+    match names.split_last() {
+        Some((only, [])) => only.clone(),
+        Some((last, [first])) => format!("{}{}{}", first, two, last),
+        Some((last, rest)) => format!("{}{}{}", rest.join(", "), many, last),
+        None => String::new()
+    }
+}
+
+/// Whether the entry is part of a larger work, such as an article in a journal, whose title goes in quotation marks.
+pub fn is_part(entry: &Entry) -> bool {
+    matches!(entry.kind.as_str(), "article" | "inproceedings" | "incollection" | "inbook")
+}
+
+/// The title, in quotation marks for a part of a larger work and in italics otherwise, ending with `end` unless it
+/// already ends with a full stop or other mark.
+pub fn title(entry: &Entry, end: &str) -> Vec<Node> {
+    let title = entry.get("title").unwrap_or("Untitled");
+    let end = if title.ends_with(['.', '?', '!']) { "" } else { end };
+    if is_part(entry) {
+        Node::text(&format!("\u{201c}{}{}\u{201d}", title, end))
+    } else {
+        emph_title(title, end)
+    }
+}
+
+/// `title` in italics followed by `end`, which stays upright.
+pub fn emph_title(title: &str, end: &str) -> Vec<Node> {
+    let mut nodes = vec![Node::Emph(Node::text(title))];
+    if !end.is_empty() {
+        nodes.push(Node::Text(end.to_string()));
+    }
+    nodes
+}
+
+/// Appends `content` to a reference as a sentence of its own, adding a full stop if it has none.
+pub fn sentence(nodes: &mut Vec<Node>, mut content: Vec<Node>) {
+    let text = Node::plain_text(&content);
+    if text.is_empty() {
+        return;
+    }
+    if !nodes.is_empty() {
+        nodes.push(Node::Space);
+    }
+    if !text.ends_with(['.', '?', '!', '\u{201d}']) {
+        content.push(Node::Text(".".to_string()));
+    }
+    nodes.extend(content);
+}
+
+/// Wraps the citations of several works in parentheses, separated by semicolons.
+pub fn parenthesise(works: Vec<Vec<Node>>) -> Vec<Node> {
+    let mut nodes = vec![Node::Text("(".to_string())];
+    for (i, work) in works.into_iter().enumerate() {
+        if i > 0 {
+            nodes.push(Node::Text(";".to_string()));
+            nodes.push(Node::Space);
+        }
+        nodes.extend(work);
+    }
+    nodes.push(Node::Text(")".to_string()));
+    nodes
+}
+
+#[cfg(test)]
+mod test {
+    use ::ast::Location;
+    use std::collections::BTreeMap;
+    use super::*;
+
+    fn entry(key: &str, kind: &str, authors: &[&str], fields: &[(&str, &str)]) -> Entry {
+        Entry {
+            key: key.to_string(),
+            kind: kind.to_string(),
+            authors: authors.iter().map(|author| Name::parse(author)).collect(),
+            editors: Vec::new(),
+            fields: fields.iter().map(|&(field, value)| (field.to_string(), value.to_string())).collect::<BTreeMap<_, _>>(),
+            year_suffix: String::new(),
+            shares_authors: false,
+            location: Location { file: None, index: 0, end: 0, line: 0, column: 0 }
+        }
+    }
+
+    /// The citation of every entry, with a page for `knuth84`, and the reference to each of them, in the style's order.
+    fn format(style: &str) -> Vec<String> {
+        let knuth = entry("knuth84", "book", &["Donald E. Knuth"], &[
+            ("title", "The TeXbook"), ("year", "1984"), ("address", "Reading, MA"), ("publisher", "Addison-Wesley")
+        ]);
+        let goedel = entry("goedel31", "article", &["Kurt G\u{f6}del", "Jean-Paul Sartre"], &[
+            ("title", "On Formally Undecidable Propositions"), ("journal", "Monatshefte"), ("volume", "38"), ("number", "1"),
+            ("pages", "173\u{2013}198"), ("year", "1931")
+        ]);
+        let many = entry("many", "book", &["A Z", "B Y", "C X", "D W"], &[("title", "Why?")]);
+        let metafont = entry("metafont", "book", &["Donald E. Knuth"], &[("title", "The METAFONTbook"), ("year", "1986")]);
+        let typefaces = entry("typefaces", "book", &["Donald E. Knuth"], &[("title", "Computer Modern Typefaces"), ("year", "1986")]);
+        let style = citation_style(style).unwrap();
+        let mut entries = vec![knuth, goedel, many, metafont, typefaces];
+        style.sort_entries(&mut entries);
+        style.tell_apart(&mut entries);
+        let works: Vec<_> = entries.iter()
+            .map(|entry| (entry, if entry.key == "knuth84" { Some("12") } else { None }))
+            .collect();
+        let mut formatted = vec![Node::plain_text(&style.format_citation(&works))];
+        formatted.extend(entries.iter().map(|entry| Node::plain_text(&style.format_reference(entry))));
+        formatted
+    }
+
+    #[test]
+    fn formats_each_style() {
+        assert_eq!(format("chicago-author-date"), vec![
+            "(G\u{f6}del and Sartre 1931; Knuth 1984, 12; Knuth 1986a; Knuth 1986b; Z et al. n.d.)",
+            "G\u{f6}del, Kurt, and Jean-Paul Sartre. 1931. \u{201c}On Formally Undecidable Propositions.\u{201d} Monatshefte 38 (1): 173\u{2013}198.",
+            "Knuth, Donald E. 1984. The TeXbook. Reading, MA: Addison-Wesley.",
+            "Knuth, Donald E. 1986a. Computer Modern Typefaces.",
+            "Knuth, Donald E. 1986b. The METAFONTbook.",
+            "Z, A, B Y, C X, and D W. n.d. Why?"
+        ]);
+        assert_eq!(format("APA"), vec![
+            "(G\u{f6}del & Sartre, 1931; Knuth, 1984, p. 12; Knuth, 1986a; Knuth, 1986b; Z et al., n.d.)",
+            "G\u{f6}del, K., & Sartre, J.-P. (1931). On Formally Undecidable Propositions. Monatshefte, 38(1), 173\u{2013}198.",
+            "Knuth, D. E. (1984). The TeXbook. Addison-Wesley.",
+            "Knuth, D. E. (1986a). Computer Modern Typefaces.",
+            "Knuth, D. E. (1986b). The METAFONTbook.",
+            "Z, A., Y, B., X, C., & W, D. (n.d.). Why?"
+        ]);
+        assert_eq!(format("mla"), vec![
+            "(G\u{f6}del and Sartre; Knuth, TeXbook 12; Knuth, Computer Modern Typefaces; Knuth, METAFONTbook; Z et al.)",
+            "G\u{f6}del, Kurt, and Jean-Paul Sartre. \u{201c}On Formally Undecidable Propositions.\u{201d} Monatshefte, vol. 38, no. 1, 1931, pp. 173\u{2013}198.",
+            "Knuth, Donald E. The TeXbook. Addison-Wesley, 1984.",
+            "Knuth, Donald E. Computer Modern Typefaces. 1986.",
+            "Knuth, Donald E. The METAFONTbook. 1986.",
+            "Z, A, et al. Why?"
+        ]);
+        assert!(citation_style("harvard").is_none());
+    }
+}
@@ -1,50 +1,516 @@
 extern crate litx;
 
-const _MINIMAL_TEST: &'static str = r#"{}"#;
+use litx::diagnostics::Diagnostic;
+use litx::diagnostics::Emitter;
+use litx::diagnostics::JsonEmitter;
+use litx::diagnostics::Severity;
+use litx::eval::CommandRegistry;
+use litx::graphviz::Graphviz;
+use litx::hyphenation::Patterns;
+use litx::lex::LexError;
+use litx::lex::Lexer;
+use litx::meta::DocumentMeta;
+use litx::parse::Branch;
+use litx::parse::Production;
+use litx::render::pdf::parse_length;
+use litx::render::pdf::PageSetup;
+use litx::source::SourceMap;
+use std::env;
+use std::fmt::Display;
+use std::fs::File;
+use std::io;
+use std::io::IsTerminal;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::process;
 
-const _LITTLE_TEST: &'static str =
-r#"
-{litx :author "Cedrick Cooke"}
+const USAGE: &str =
+"usage: litx build [INPUT] [-o OUTPUT] [--format FORMAT]
+       litx import [INPUT] [-o OUTPUT] [--from FORMAT]
+       litx tokens [INPUT]
+       litx tree [INPUT] [--graphviz]
 
-{frobnicate :foo "bar"}
-Same paragraph: {$t$}.
+INPUT defaults to standard input, and may also be given as `-`.
+OUTPUT defaults to standard output.
 
-New paragraph.
-"#;
+options:
+    -o, --output OUTPUT    where `build` writes the document
+    -f, --format FORMAT    the output format for `build`: html (default), text, latex, markdown or pdf
+    -w, --width COLUMNS    the line width for text output (default 72)
+        --page-size SIZE   the paper for pdf output: a4 (default), a5, b5, letter, legal, or WIDTHxHEIGHT
+        --margin LENGTH    the margin on every side of pdf pages, e.g. 20mm or 1in (default 1in)
+        --hyphenation DIR  hyphenate pdf output with TeX patterns from DIR, such as hyph-en-us.tex for `:lang en-us`
+        --from FORMAT      the input format for `import`: markdown (default)
+        --graphviz         print the tree as a Graphviz digraph
+        --color WHEN       colour errors: auto (default), always or never
+        --message-format FORMAT
+                           how errors are written: human (default), or json for one object per line
+    -q, --quiet            print nothing on standard error
+    -v, --verbose          also report each step as it happens
+    -h, --help             print this message";
 
-const _BIG_TEST: &'static str =
-r#"{!
-    This is a simple, example litx document.
-    Hopefully, this doesn't trip up the close comment: \!}
-!}
-{litx
-    :doctype mla
-    :title "Example Document"
-    :author "Cedrick Cooke"}
+/// The document had errors; they have already been reported.
+const EXIT_DIAGNOSTICS: i32 = 1;
+/// The command line was wrong, or a file could not be read or written.
+const EXIT_FAILURE: i32 = 2;
 
-{litx.meta :table-of-contents none}
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+enum Subcommand {
+    Build,
+    Import,
+    Tokens,
+    Tree,
+    Help
+}
+
+/// A format `import` can convert from.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+enum ImportFormat {
+    Markdown
+}
+
+impl ImportFormat {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "markdown" | "md" | "commonmark" => Some(ImportFormat::Markdown),
+            _ => None
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+enum Format {
+    Html,
+    Text,
+    Latex,
+    Markdown,
+    Pdf
+}
+
+impl Format {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "html" => Some(Format::Html),
+            "text" => Some(Format::Text),
+            "latex" => Some(Format::Latex),
+            "markdown" | "md" => Some(Format::Markdown),
+            "pdf" => Some(Format::Pdf),
+            _ => None
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(PartialEq, PartialOrd)]
+enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose
+}
+
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+enum Colour {
+    Auto,
+    Always,
+    Never
+}
+
+impl Colour {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(Colour::Auto),
+            "always" => Some(Colour::Always),
+            "never" => Some(Colour::Never),
+            _ => None
+        }
+    }
+
+    /// Automatic colour follows the `NO_COLOR` convention and is only used when errors go to a terminal.
+    fn is_enabled(self) -> bool {
+        match self {
+            Colour::Auto => env::var_os("NO_COLOR").is_none() && io::stderr().is_terminal(),
+            Colour::Always => true,
+            Colour::Never => false
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+enum MessageFormat {
+    Human,
+    Json
+}
 
-{h1 "Introduction"}
-This is a plaintext paragraph.
-These lines have not been separated by a blank line.
-Lorem ipsum dolor sit amet.
+impl MessageFormat {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "human" => Some(MessageFormat::Human),
+            "json" => Some(MessageFormat::Json),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+struct Options {
+    subcommand: Subcommand,
+    input: Option<String>,
+    output: Option<String>,
+    format: Format,
+    from: ImportFormat,
+    width: usize,
+    page: PageSetup,
+    hyphenation: Option<String>,
+    graphviz: bool,
+    colour: Colour,
+    message_format: MessageFormat,
+    verbosity: Verbosity
+}
 
-This line is separated by a blank line on either side.
+impl Options {
+    fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let subcommand = match args.next().as_deref() {
+            Some("build") => Subcommand::Build,
+            Some("import") => Subcommand::Import,
+            Some("tokens") => Subcommand::Tokens,
+            Some("tree") => Subcommand::Tree,
+            Some("help") | Some("-h") | Some("--help") | None => Subcommand::Help,
+            Some(other) => return Err(format!("unknown command `{}`", other))
+        };
+        let mut options = Options {
+            subcommand,
+            input: None,
+            output: None,
+            format: Format::Html,
+            from: ImportFormat::Markdown,
+            width: litx::render::text::DEFAULT_WIDTH,
+            page: PageSetup::default(),
+            hyphenation: None,
+            graphviz: false,
+            colour: Colour::Auto,
+            message_format: MessageFormat::Human,
+            verbosity: Verbosity::Normal
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-o" | "--output" if subcommand == Subcommand::Build || subcommand == Subcommand::Import => {
+                    options.output = Some(args.next().ok_or_else(|| format!("`{}` needs a file name", arg))?);
+                },
+                "-f" | "--format" if subcommand == Subcommand::Build => {
+                    let name = args.next().ok_or_else(|| format!("`{}` needs a format name", arg))?;
+                    options.format = Format::from_name(&name).ok_or_else(|| format!("unknown format `{}`", name))?;
+                },
+                "--from" if subcommand == Subcommand::Import => {
+                    let name = args.next().ok_or_else(|| format!("`{}` needs a format name", arg))?;
+                    options.from = ImportFormat::from_name(&name).ok_or_else(|| format!("unknown import format `{}`", name))?;
+                },
+                "-w" | "--width" if subcommand == Subcommand::Build => {
+                    let columns = args.next().ok_or_else(|| format!("`{}` needs a number of columns", arg))?;
+                    options.width = columns.parse().map_err(|_| format!("`{}` is not a number of columns", columns))?;
+                },
+                "--page-size" if subcommand == Subcommand::Build => {
+                    let name = args.next().ok_or_else(|| format!("`{}` needs a paper size", arg))?;
+                    let (width, height) = PageSetup::size_from_name(&name).ok_or_else(|| format!("unknown paper size `{}`", name))?;
+                    options.page = options.page.with_size(width, height);
+                },
+                "--margin" if subcommand == Subcommand::Build => {
+                    let length = args.next().ok_or_else(|| format!("`{}` needs a length", arg))?;
+                    let margin = parse_length(&length).ok_or_else(|| format!("`{}` is not a length", length))?;
+                    options.page = options.page.with_margins(margin);
+                },
+                "--hyphenation" if subcommand == Subcommand::Build => {
+                    options.hyphenation = Some(args.next().ok_or_else(|| format!("`{}` needs a directory", arg))?);
+                },
+                "--graphviz" if subcommand == Subcommand::Tree => options.graphviz = true,
+                "--color" => {
+                    let name = args.next().ok_or_else(|| format!("`{}` needs `auto`, `always` or `never`", arg))?;
+                    options.colour = Colour::from_name(&name).ok_or_else(|| format!("unknown colour setting `{}`", name))?;
+                },
+                "--message-format" => {
+                    let name = args.next().ok_or_else(|| format!("`{}` needs `human` or `json`", arg))?;
+                    options.message_format = MessageFormat::from_name(&name).ok_or_else(|| format!("unknown message format `{}`", name))?;
+                },
+                "-q" | "--quiet" => options.verbosity = Verbosity::Quiet,
+                "-v" | "--verbose" => options.verbosity = Verbosity::Verbose,
+                "-h" | "--help" => options.subcommand = Subcommand::Help,
+                "-" => options.input = None,
+                _ if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+                _ if options.input.is_some() => return Err(format!("unexpected argument `{}`", arg)),
+                _ => options.input = Some(arg)
+            }
+        }
+        // Checked once both are known, since either may be given first.
+        if options.page.get_text_width() <= 0.0 || options.page.get_text_height() <= 0.0 {
+            return Err("the margins leave no room for text on the page".to_string());
+        }
+        Ok(options)
+    }
+}
 
-{h2 "Sub-header"}
-On this line we have some funky styling, like inline math: {$1+2$}, and {emph "italics"}.
-There is also an escaped newline: \n, outside of an expression.
-Even worse, what if we have a newline in a word? Foo\nbar.
+/// Writes messages to standard error, as far as the chosen verbosity allows.
+struct Reporter {
+    verbosity: Verbosity,
+    message_format: MessageFormat,
+    emitter: Emitter
+}
 
-{h2 "Danger zone"}
-I think this might actually break things. Is this fine!? What about this: $25.
+impl Reporter {
+    fn error<D: Display>(&self, message: D) {
+        self.diagnostic(&Diagnostic::error(message.to_string()), &SourceMap::new());
+    }
 
-{ignore "This is a string which contains {! a comment !}"}
+    fn diagnostic(&self, diagnostic: &Diagnostic, sources: &SourceMap) {
+        if self.verbosity >= Verbosity::Normal {
+            let stderr = io::stderr();
+            let mut stderr = stderr.lock();
+            // There is nowhere left to report a failure to write to standard error.
+            let _ = match self.message_format {
+                MessageFormat::Human => self.emitter.emit(diagnostic, sources, &mut stderr),
+                MessageFormat::Json => JsonEmitter::new().emit(diagnostic, sources, &mut stderr)
+            };
+        }
+    }
 
-{! This is another comment at the end. It contains "a string" !}"#;
+    /// Progress messages would only get in the way of a program reading JSON, so they are only written for people.
+    fn info<D: Display>(&self, message: D) {
+        if self.verbosity >= Verbosity::Verbose && self.message_format == MessageFormat::Human {
+            eprintln!("litx: {}", message);
+        }
+    }
+}
 
 fn main() {
-    let tokens = litx::lex::Lexer::new(_LITTLE_TEST);
-    let tree = litx::parse::parse(tokens);
-    litx::graphviz::Graphviz::write_graph(&tree, &mut std::io::stdout()).unwrap();
+    let options = match Options::from_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            process::exit(EXIT_FAILURE);
+        }
+    };
+    let reporter = Reporter {
+        verbosity: options.verbosity,
+        message_format: options.message_format,
+        emitter: Emitter::new(options.colour.is_enabled())
+    };
+    if let Err(code) = run(&options, &reporter) {
+        process::exit(code);
+    }
+}
+
+fn run(options: &Options, reporter: &Reporter) -> Result<(), i32> {
+    if options.subcommand == Subcommand::Help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+
+    let source = read_input(options.input.as_deref()).map_err(|err| {
+        reporter.error(format!("cannot read {}: {}", options.input.as_deref().unwrap_or("standard input"), err));
+        EXIT_FAILURE
+    })?;
+    let filename = options.input.as_deref().unwrap_or("<stdin>");
+    let mut sources = SourceMap::new();
+    sources.add_file(filename, source.as_str());
+    if options.subcommand == Subcommand::Import {
+        let file = sources.get_file(filename).unwrap();
+        let conversion = match options.from {
+            ImportFormat::Markdown => litx::import::markdown::convert(file)
+        };
+        for warning in &conversion.warnings {
+            reporter.diagnostic(warning, &sources);
+        }
+        let mut output = create_output(options, reporter)?;
+        output.write_all(conversion.source.as_bytes())
+            .and_then(|_| output.flush())
+            .map_err(|err| io_failure(reporter, err))?;
+        reporter.info(format!("imported {} with {} warning{}",
+            filename,
+            conversion.warnings.len(),
+            if conversion.warnings.len() == 1 { "" } else { "s" }));
+        return Ok(());
+    }
+
+    let lexer = Lexer::new_with_filename(&source, filename);
+    if options.subcommand == Subcommand::Tokens {
+        let stdout = io::stdout();
+        let errors = write_tokens(lexer, &mut stdout.lock()).map_err(|err| io_failure(reporter, err))?;
+        return if errors.is_empty() { Ok(()) } else { Err(report_all(reporter, errors, &sources)) };
+    }
+
+    let tree = litx::parse::parse(lexer).map_err(|errors| report_all(reporter, errors, &sources))?;
+    reporter.info(format!("parsed {}", filename));
+    if options.subcommand == Subcommand::Tree {
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        let written = if options.graphviz {
+            tree.write_graph(&mut stdout)
+        } else {
+            write_tree(&tree, 0, &mut stdout)
+        };
+        return written.map_err(|err| io_failure(reporter, err));
+    }
+
+    let registry = CommandRegistry::with_builtins();
+    let doc = litx::eval::evaluate_with_sources(&litx::ast::lower(&tree), &registry, &mut sources)
+        .map_err(|errors| report_all(reporter, errors, &sources))?;
+    reporter.info(format!("evaluated {} blocks", doc.blocks.len()));
+    let patterns = if options.format == Format::Pdf {
+        load_patterns(options, &doc.meta, reporter)?
+    } else {
+        Patterns::default()
+    };
+    let mut output = create_output(options, reporter)?;
+    let written = match options.format {
+        Format::Html => litx::render::html::render(&doc, &mut output),
+        Format::Text => litx::render::text::render(&doc, options.width, &mut output),
+        Format::Latex => litx::render::latex::render(&doc, &mut output),
+        Format::Markdown => litx::render::markdown::render(&doc, &mut output),
+        Format::Pdf => litx::render::pdf::render(&doc, &options.page, &patterns, &mut output)
+    };
+    written.and_then(|_| output.flush()).map_err(|err| io_failure(reporter, err))?;
+    reporter.info(format!("wrote {}", options.output.as_deref().unwrap_or("standard output")));
+    Ok(())
+}
+
+/// The hyphenation patterns for the document's language from the `--hyphenation` directory. Without either, only the
+/// document's own `{hyphenate ...}` words are hyphenated.
+fn load_patterns(options: &Options, meta: &DocumentMeta, reporter: &Reporter) -> Result<Patterns, i32> {
+    let (dir, language) = match (options.hyphenation.as_ref(), meta.language.as_ref()) {
+        (Some(dir), Some(language)) => (dir, language),
+        _ => return Ok(Patterns::default())
+    };
+    match litx::hyphenation::find_patterns(Path::new(dir), language) {
+        Some(path) => {
+            let patterns = Patterns::load(&path).map_err(|err| {
+                reporter.error(format!("cannot read {}: {}", path.display(), err));
+                EXIT_FAILURE
+            })?;
+            reporter.info(format!("hyphenating with {}", path.display()));
+            Ok(patterns)
+        },
+        None => {
+            let warning = Diagnostic::new(Severity::Warning, format!("no hyphenation patterns for `{}` in {}", language, dir));
+            reporter.diagnostic(&warning, &SourceMap::new());
+            Ok(Patterns::default())
+        }
+    }
+}
+
+fn create_output(options: &Options, reporter: &Reporter) -> Result<Box<dyn Write>, i32> {
+    Ok(match options.output {
+        Some(ref path) => Box::new(File::create(path).map_err(|err| {
+            reporter.error(format!("cannot write {}: {}", path, err));
+            EXIT_FAILURE
+        })?),
+        None => Box::new(io::stdout())
+    })
+}
+
+fn read_input(path: Option<&str>) -> io::Result<String> {
+    let mut source = String::new();
+    match path {
+        Some(path) => File::open(path)?.read_to_string(&mut source)?,
+        None => io::stdin().read_to_string(&mut source)?
+    };
+    Ok(source)
+}
+
+fn report_all<D: Into<Diagnostic>>(reporter: &Reporter, errors: Vec<D>, sources: &SourceMap) -> i32 {
+    let count = errors.len();
+    for error in errors {
+        reporter.diagnostic(&error.into(), sources);
+    }
+    let plural = if count == 1 { "" } else { "s" };
+    reporter.info(format!("stopped after {} error{}", count, plural));
+    EXIT_DIAGNOSTICS
+}
+
+fn io_failure(reporter: &Reporter, err: io::Error) -> i32 {
+    reporter.error(err);
+    EXIT_FAILURE
+}
+
+/// Prints one token per line, returning any which the lexer could not recognise.
+fn write_tokens<'a, 'b, W: Write>(lexer: Lexer<'a, 'b>, writer: &mut W) -> io::Result<Vec<LexError<'a, 'b>>> {
+    let mut errors = Vec::new();
+    for token in lexer {
+        let (line, column) = token.get_span().start_line_col;
+        writeln!(writer, "{}:{}\t{:?}\t{:?}",
+            line + 1,
+            column + 1,
+            token.get_type(),
+            token.get_content())?;
+        errors.extend(LexError::from_token(&token));
+    }
+    Ok(errors)
+}
+
+/// Prints one production per line, indented by depth, with tokens shown as their type and content.
+fn write_tree<W: Write>(production: &Production, depth: usize, writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "{:indent$}{:?}", "", production.get_type(), indent = depth * 2)?;
+    for child in production.get_children() {
+        match *child {
+            Branch::Terminal(ref token) => {
+                writeln!(writer, "{:indent$}{:?} {:?}", "", token.get_type(), token.get_content(), indent = depth * 2 + 2)?;
+            },
+            Branch::Nonterminal(ref child) => write_tree(child, depth + 1, writer)?
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn options(args: &[&str]) -> Result<Options, String> {
+        Options::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn arguments() {
+        assert_eq!(options(&["build", "book.litx", "-o", "book.html", "--format", "html", "-q"]), Ok(Options {
+            subcommand: Subcommand::Build,
+            input: Some("book.litx".to_string()),
+            output: Some("book.html".to_string()),
+            format: Format::Html,
+            from: ImportFormat::Markdown,
+            width: 72,
+            page: PageSetup::default(),
+            hyphenation: None,
+            graphviz: false,
+            colour: Colour::Auto,
+            message_format: MessageFormat::Human,
+            verbosity: Verbosity::Quiet
+        }));
+        assert_eq!(options(&["build", "--message-format", "json"]).unwrap().message_format, MessageFormat::Json);
+        assert_eq!(options(&["build", "--color", "never"]).unwrap().colour, Colour::Never);
+        assert!(options(&["tree", "-", "--graphviz"]).unwrap().graphviz);
+        assert_eq!(options(&[]).unwrap().subcommand, Subcommand::Help);
+        assert_eq!(options(&["tokens", "--graphviz"]), Err("unknown option `--graphviz`".to_string()));
+        assert_eq!(options(&["build", "-f", "text", "-w", "60"]).unwrap().width, 60);
+        assert_eq!(options(&["build", "-f", "docx"]), Err("unknown format `docx`".to_string()));
+        assert_eq!(options(&["import", "notes.md", "--from", "markdown", "-o", "notes.litx"]).unwrap().output, Some("notes.litx".to_string()));
+        assert_eq!(options(&["build", "-f", "pdf", "--margin", "1in", "--page-size", "letter"]).unwrap().page,
+            PageSetup::new(612.0, 792.0).with_margins(72.0));
+        assert_eq!(options(&["build", "--hyphenation", "patterns"]).unwrap().hyphenation, Some("patterns".to_string()));
+        assert_eq!(options(&["build", "--margin", "wide"]), Err("`wide` is not a length".to_string()));
+        assert_eq!(options(&["build", "--margin", "400mm"]), Err("the margins leave no room for text on the page".to_string()));
+        assert_eq!(options(&["build", "--margin", "10cm", "--page-size", "a5"]),
+            Err("the margins leave no room for text on the page".to_string()));
+        assert_eq!(options(&["import", "--from", "rst"]), Err("unknown import format `rst`".to_string()));
+        assert_eq!(options(&["build", "a", "b"]), Err("unexpected argument `b`".to_string()));
+    }
 }
@@ -0,0 +1,145 @@
+use std::fmt;
+
+/// The stable catalogue of diagnostic codes. Codes are never reused or renumbered, so tools may match on them.
+///
+/// `L00xx` codes are syntax errors found while lexing and parsing; `L01xx` codes are found during evaluation;
+/// `L02xx` codes are found while importing documents written in other formats.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub enum Code {
+    /// An expression `{` without its `}`.
+    UnclosedExpression,
+    /// A math `{$` without its `$}`.
+    UnclosedMath,
+    /// A comment `{!` without its `!}`.
+    UnclosedComment,
+    /// A string `"` without its closing `"`.
+    UnclosedString,
+    /// A `}`, `$}` or `!}` which closes nothing.
+    UnmatchedCloser,
+    /// A token the grammar does not allow where it appears.
+    UnexpectedToken,
+    /// Input which is not part of any token, such as a control character.
+    UnrecognisedInput,
+    /// A command failed for a reason of its own.
+    CommandFailed,
+    /// An expression names a command or macro which does not exist.
+    UnknownCommand,
+    /// A command or macro was given the wrong arguments or properties.
+    BadArguments,
+    /// The `{litx ...}` header is misplaced or has a bad property.
+    BadHeader,
+    /// A macro definition is invalid, or its expansion never ends.
+    BadMacro,
+    /// A file could not be included.
+    BadInclude,
+    /// The contents of `{$ ... $}` cannot be understood as math.
+    BadMath,
+    /// A label is defined twice, or has no heading, figure or equation to attach to.
+    BadLabel,
+    /// A reference names a label which is never defined.
+    UndefinedLabel,
+    /// A citation names a key which is in none of the document's bibliographies.
+    UnknownCitation,
+    /// A bibliography could not be read, or has an error in it.
+    BadBibliography,
+    /// Part of an imported document has no litx equivalent, so it was changed or dropped.
+    NotImported
+}
+
+pub const CODES: [Code; 19] = [
+    Code::UnclosedExpression,
+    Code::UnclosedMath,
+    Code::UnclosedComment,
+    Code::UnclosedString,
+    Code::UnmatchedCloser,
+    Code::UnexpectedToken,
+    Code::UnrecognisedInput,
+    Code::CommandFailed,
+    Code::UnknownCommand,
+    Code::BadArguments,
+    Code::BadHeader,
+    Code::BadMacro,
+    Code::BadInclude,
+    Code::BadMath,
+    Code::BadLabel,
+    Code::UndefinedLabel,
+    Code::UnknownCitation,
+    Code::BadBibliography,
+    Code::NotImported
+];
+
+impl Code {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Code::UnclosedExpression => "L0001",
+            Code::UnclosedMath => "L0002",
+            Code::UnclosedComment => "L0003",
+            Code::UnclosedString => "L0004",
+            Code::UnmatchedCloser => "L0005",
+            Code::UnexpectedToken => "L0006",
+            Code::UnrecognisedInput => "L0007",
+            Code::CommandFailed => "L0100",
+            Code::UnknownCommand => "L0101",
+            Code::BadArguments => "L0102",
+            Code::BadHeader => "L0103",
+            Code::BadMacro => "L0104",
+            Code::BadInclude => "L0105",
+            Code::BadMath => "L0106",
+            Code::BadLabel => "L0107",
+            Code::UndefinedLabel => "L0108",
+            Code::UnknownCitation => "L0109",
+            Code::BadBibliography => "L0110",
+            Code::NotImported => "L0200"
+        }
+    }
+
+    pub fn get_summary(self) -> &'static str {
+        match self {
+            Code::UnclosedExpression => "unclosed expression",
+            Code::UnclosedMath => "unclosed math",
+            Code::UnclosedComment => "unclosed comment",
+            Code::UnclosedString => "unclosed string",
+            Code::UnmatchedCloser => "unmatched closer",
+            Code::UnexpectedToken => "unexpected token",
+            Code::UnrecognisedInput => "unrecognised input",
+            Code::CommandFailed => "command failed",
+            Code::UnknownCommand => "unknown command",
+            Code::BadArguments => "bad arguments",
+            Code::BadHeader => "bad header",
+            Code::BadMacro => "bad macro",
+            Code::BadInclude => "bad include",
+            Code::BadMath => "bad math",
+            Code::BadLabel => "bad label",
+            Code::UndefinedLabel => "undefined label",
+            Code::UnknownCitation => "unknown citation",
+            Code::BadBibliography => "bad bibliography",
+            Code::NotImported => "not imported"
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        CODES.iter().copied().find(|candidate| candidate.as_str() == code)
+    }
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn codes_are_unique() {
+        for (i, code) in CODES.iter().enumerate() {
+            assert_eq!(Code::from_code(code.as_str()), Some(*code));
+            assert!(CODES[i + 1..].iter().all(|other| other.as_str() != code.as_str()));
+        }
+        assert_eq!(Code::from_code("L0001").map(Code::get_summary), Some("unclosed expression"));
+    }
+}
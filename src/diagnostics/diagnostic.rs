@@ -0,0 +1,59 @@
+use super::Code;
+use super::Label;
+use super::Severity;
+
+/// A problem with a document, ready to be shown to its author by an `Emitter`.
+/// Each stage's error type converts into one of these with `From`.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<Code>,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>
+}
+
+impl Diagnostic {
+    pub fn new<S: Into<String>>(severity: Severity, message: S) -> Self {
+        Diagnostic {
+            severity,
+            code: None,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: None
+        }
+    }
+
+    pub fn error<S: Into<String>>(message: S) -> Self {
+        Diagnostic::new(Severity::Error, message)
+    }
+
+    pub fn with_code(mut self, code: Code) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+
+    pub fn with_note<S: Into<String>>(mut self, note: S) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help<S: Into<String>>(mut self, help: S) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// The label marking where the problem is, which is the first primary label.
+    pub fn get_primary(&self) -> Option<&Label> {
+        self.labels.iter().find(|label| label.primary)
+    }
+}
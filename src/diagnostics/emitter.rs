@@ -0,0 +1,284 @@
+use ::source::SourceFile;
+use ::source::SourceMap;
+use std::cmp;
+use std::collections::BTreeMap;
+use std::io;
+use super::Diagnostic;
+use super::Label;
+use super::Severity;
+
+/// How many columns a tab takes up in a source snippet.
+const TAB_WIDTH: usize = 4;
+
+/// Writes diagnostics in the style of rustc: the message, then each labelled line of source with its underlines,
+/// then any notes and help. Lines are only shown for files in the `SourceMap`; otherwise just the position is given.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub struct Emitter {
+    colour: bool
+}
+
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+enum Style {
+    Plain,
+    Bold,
+    Severity(Severity),
+    Secondary,
+    Gutter
+}
+
+impl Style {
+    fn ansi(self) -> &'static str {
+        match self {
+            Style::Plain => "",
+            Style::Bold => "\x1b[1m",
+            Style::Severity(Severity::Error) => "\x1b[1;31m",
+            Style::Severity(Severity::Warning) => "\x1b[1;33m",
+            Style::Secondary | Style::Gutter => "\x1b[1;34m"
+        }
+    }
+}
+
+impl Emitter {
+    /// `colour` turns on ANSI escapes; leave it off for files and CI logs.
+    pub fn new(colour: bool) -> Self {
+        Emitter { colour }
+    }
+
+    pub fn emit<W: io::Write>(&self, diagnostic: &Diagnostic, sources: &SourceMap, writer: &mut W) -> io::Result<()> {
+        let heading = match diagnostic.code {
+            Some(code) => format!("{}[{}]", diagnostic.severity, code),
+            None => diagnostic.severity.to_string()
+        };
+        writeln!(writer, "{}{}",
+            self.paint(&heading, Style::Severity(diagnostic.severity)),
+            self.paint(&format!(": {}", diagnostic.message), Style::Bold))?;
+
+        let width = diagnostic.labels.iter()
+            .map(|label| (label.location.line + 1).to_string().len())
+            .max()
+            .unwrap_or(0);
+        let pad = " ".repeat(width);
+        for (i, labels) in group_by_file(diagnostic).iter().enumerate() {
+            let arrow = if i == 0 { "-->" } else { ":::" };
+            let first = labels.iter().find(|label| label.primary).unwrap_or(&labels[0]);
+            writeln!(writer, "{}{} {}", pad, self.paint(arrow, Style::Gutter), first.location)?;
+            if let Some(source) = first.location.file.as_ref().and_then(|file| sources.get_file(file)) {
+                self.emit_snippet(source, labels, diagnostic.severity, width, writer)?;
+            }
+        }
+
+        let mut footer = diagnostic.notes.iter()
+            .map(|note| ("note", note))
+            .chain(diagnostic.help.iter().map(|help| ("help", help)))
+            .peekable();
+        if footer.peek().is_some() && !diagnostic.labels.is_empty() {
+            writeln!(writer, "{} {}", pad, self.paint("|", Style::Gutter))?;
+        }
+        for (kind, text) in footer {
+            writeln!(writer, "{} {} {}: {}", pad, self.paint("=", Style::Gutter), self.paint(kind, Style::Bold), text)?;
+        }
+        writeln!(writer)
+    }
+
+    fn emit_snippet<W: io::Write>(&self, source: &SourceFile, labels: &[&Label], severity: Severity, width: usize, writer: &mut W) -> io::Result<()> {
+        let mut lines: BTreeMap<usize, Vec<&Label>> = BTreeMap::new();
+        for &label in labels {
+            lines.entry(label.location.line).or_default().push(label);
+        }
+        let pad = " ".repeat(width);
+        let bar = self.paint("|", Style::Gutter);
+        writeln!(writer, "{} {}", pad, bar)?;
+        let mut previous = None;
+        for (&line, labels) in &lines {
+            if previous.map(|previous| line > previous + 1).unwrap_or(false) {
+                writeln!(writer, "{}", self.paint("...", Style::Gutter))?;
+            }
+            previous = Some(line);
+            let text = source.get_line(line).unwrap_or("");
+            let number = format!("{:>width$}", line + 1, width = width);
+            writeln!(writer, "{} {} {}", self.paint(&number, Style::Gutter), bar, text.replace('\t', &" ".repeat(TAB_WIDTH)).trim_end())?;
+            for row in annotate(text, labels, severity) {
+                writeln!(writer, "{} {} {}", pad, bar, self.render_row(&row))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn paint(&self, text: &str, style: Style) -> String {
+        if self.colour && style != Style::Plain {
+            format!("{}{}\x1b[0m", style.ansi(), text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn render_row(&self, row: &[(char, Style)]) -> String {
+        let mut out = String::new();
+        let mut start = 0;
+        while start < row.len() {
+            let style = row[start].1;
+            let run = row[start..].iter().take_while(|cell| cell.1 == style).count();
+            let text: String = row[start..start + run].iter().map(|cell| cell.0).collect();
+            out.push_str(&self.paint(&text, style));
+            start += run;
+        }
+        out
+    }
+}
+
+/// Splits the labels of `diagnostic` by file, starting with the file of the primary label.
+fn group_by_file(diagnostic: &Diagnostic) -> Vec<Vec<&Label>> {
+    let mut groups: Vec<Vec<&Label>> = Vec::new();
+    let ordered = diagnostic.get_primary().into_iter().chain(diagnostic.labels.iter().filter(|label| !label.primary));
+    for label in ordered {
+        match groups.iter_mut().find(|group| group[0].location.file == label.location.file) {
+            Some(group) => group.push(label),
+            None => groups.push(vec![label])
+        }
+    }
+    groups
+}
+
+/// The rows of underlines and messages to draw beneath `text` for `labels`, all of which start on that line.
+///
+/// Every label is underlined in the first row, and the rightmost label's message follows it.
+/// The other messages hang below their underlines on lines of their own, right to left.
+fn annotate(text: &str, labels: &[&Label], severity: Severity) -> Vec<Vec<(char, Style)>> {
+    let mut marks: Vec<(usize, usize, &Label)> = labels.iter()
+        .map(|&label| {
+            let location = &label.location;
+            let start = cmp::min(location.column, text.len());
+            let end = cmp::min(location.column + (location.end - location.index), text.len());
+            let before = display_width(text.get(..start).unwrap_or(text));
+            let width = display_width(text.get(start..end).unwrap_or(""));
+            (before, cmp::max(width, 1), label)
+        })
+        .collect();
+    marks.sort_by_key(|&(start, _, _)| start);
+
+    let mut first = Vec::new();
+    for &(start, width, label) in &marks {
+        let marker = if label.primary { "^" } else { "-" };
+        put(&mut first, start, &marker.repeat(width), style_of(label, severity));
+    }
+    let (&(_, _, last), rest) = marks.split_last().unwrap();
+    if !last.message.is_empty() {
+        let after = first.len() + 1;
+        put(&mut first, after, &last.message, style_of(last, severity));
+    }
+
+    let mut rows = vec![first];
+    let pending: Vec<_> = rest.iter().filter(|mark| !mark.2.message.is_empty()).collect();
+    if !pending.is_empty() {
+        let mut connector = Vec::new();
+        for &&(start, _, label) in &pending {
+            put(&mut connector, start, "|", style_of(label, severity));
+        }
+        rows.push(connector);
+    }
+    for i in (0..pending.len()).rev() {
+        let mut row = Vec::new();
+        for &&(start, _, label) in &pending[..i] {
+            put(&mut row, start, "|", style_of(label, severity));
+        }
+        let (start, _, label) = *pending[i];
+        put(&mut row, start, &label.message, style_of(label, severity));
+        rows.push(row);
+    }
+    rows
+}
+
+fn style_of(label: &Label, severity: Severity) -> Style {
+    if label.primary {
+        Style::Severity(severity)
+    } else {
+        Style::Secondary
+    }
+}
+
+fn put(row: &mut Vec<(char, Style)>, column: usize, text: &str, style: Style) {
+    for (i, c) in text.chars().enumerate() {
+        if row.len() <= column + i {
+            row.resize(column + i + 1, (' ', Style::Plain));
+        }
+        row[column + i] = (c, style);
+    }
+}
+
+fn display_width(text: &str) -> usize {
+    text.chars().map(|c| if c == '\t' { TAB_WIDTH } else { 1 }).sum()
+}
+
+#[cfg(test)]
+mod test {
+    use ::ast::Location;
+    use ::diagnostics::*;
+    use ::source::SourceMap;
+
+    fn location(index: usize, end: usize, line: usize, column: usize) -> Location {
+        Location {
+            file: Some("test.litx".to_string()),
+            index,
+            end,
+            line,
+            column
+        }
+    }
+
+    fn emit(diagnostic: &Diagnostic, colour: bool) -> String {
+        let mut sources = SourceMap::new();
+        sources.add_file("test.litx", "Some text.\n{h1 \"Intro\"\n\n\tNext {frob} {emph x}.");
+        let mut out = Vec::new();
+        Emitter::new(colour).emit(diagnostic, &sources, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn labels_on_one_line() {
+        let diagnostic = Diagnostic::error("expected `}` to close expression opened at line 2, found a blank line")
+            .with_code(Code::UnclosedExpression)
+            .with_label(Label::primary(&location(22, 23, 1, 11), ""))
+            .with_label(Label::secondary(&location(11, 12, 1, 0), "unclosed expression opened here"))
+            .with_help("add `}` to close the expression");
+        assert_eq!(emit(&diagnostic, false), "\
+error[L0001]: expected `}` to close expression opened at line 2, found a blank line
+ --> test.litx:2:12
+  |
+2 | {h1 \"Intro\"
+  | -          ^
+  | |
+  | unclosed expression opened here
+  |
+  = help: add `}` to close the expression
+
+");
+    }
+
+    #[test]
+    fn tabs_and_distant_lines() {
+        let diagnostic = Diagnostic::error("unknown command `frob`")
+            .with_label(Label::primary(&location(30, 36, 3, 6), "not defined anywhere"))
+            .with_label(Label::secondary(&location(0, 4, 0, 0), "see here"))
+            .with_note("commands are case-sensitive");
+        let expected = "\
+error: unknown command `frob`
+ --> test.litx:4:7
+  |
+1 | Some text.
+  | ---- see here
+...
+4 |     Next {frob} {emph x}.
+  |          ^^^^^^ not defined anywhere
+  |
+  = note: commands are case-sensitive
+
+";
+        assert_eq!(emit(&diagnostic, false), expected);
+        let coloured = emit(&diagnostic, true);
+        assert!(coloured.starts_with("\x1b[1;31merror\x1b[0m\x1b[1m: unknown command `frob`\x1b[0m\n"));
+        assert!(coloured.contains("\x1b[1;31m^^^^^^\x1b[0m \x1b[1;31mnot defined anywhere\x1b[0m"));
+    }
+}
@@ -0,0 +1,112 @@
+use ::ast::Location;
+use ::source::SourceMap;
+use std::cmp;
+use std::io;
+use super::Diagnostic;
+use super::Label;
+
+/// Writes each diagnostic as one line of JSON, for editors and CI annotations.
+///
+/// Every position is given as a byte range and as a line and column range. Lines and columns are one-based,
+/// columns count bytes, and the ends of ranges point just past their last byte.
+/// The position of the diagnostic itself is that of its primary label; every label, primary or not, is listed in `labels`.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Default)]
+pub struct JsonEmitter;
+
+impl JsonEmitter {
+    pub fn new() -> Self {
+        JsonEmitter
+    }
+
+    pub fn emit<W: io::Write>(&self, diagnostic: &Diagnostic, sources: &SourceMap, writer: &mut W) -> io::Result<()> {
+        let position = match diagnostic.get_primary() {
+            Some(label) => position(&label.location, sources),
+            None => "\"file\":null".to_string()
+        };
+        let labels: Vec<String> = diagnostic.labels.iter()
+            .map(|label| label_object(label, sources))
+            .collect();
+        let notes: Vec<String> = diagnostic.notes.iter()
+            .map(|note| string(note))
+            .collect();
+        writeln!(writer, "{{\"severity\":{},\"code\":{},\"message\":{},{},\"labels\":[{}],\"notes\":[{}],\"help\":{}}}",
+            string(&diagnostic.severity.to_string()),
+            diagnostic.code.map(|code| string(code.as_str())).unwrap_or_else(|| "null".to_string()),
+            string(&diagnostic.message),
+            position,
+            labels.join(","),
+            notes.join(","),
+            diagnostic.help.as_ref().map(|help| string(help)).unwrap_or_else(|| "null".to_string()))
+    }
+}
+
+fn label_object(label: &Label, sources: &SourceMap) -> String {
+    format!("{{\"primary\":{},\"message\":{},{}}}", label.primary, string(&label.message), position(&label.location, sources))
+}
+
+/// The fields locating `location`. Its end is found through `sources` when possible,
+/// and otherwise is assumed to be on the line where it starts.
+fn position(location: &Location, sources: &SourceMap) -> String {
+    let file = match location.file {
+        Some(ref file) => file,
+        None => return "\"file\":null".to_string()
+    };
+    let (end_line, end_column) = sources.get_file(file)
+        .map(|source| source.get_line_col(cmp::min(location.end, source.get_source().len())))
+        .unwrap_or((location.line, location.column + (location.end - location.index)));
+    format!("\"file\":{},\"byte_start\":{},\"byte_end\":{},\"line_start\":{},\"column_start\":{},\"line_end\":{},\"column_end\":{}",
+        string(file),
+        location.index,
+        location.end,
+        location.line + 1,
+        location.column + 1,
+        end_line + 1,
+        end_column + 1)
+}
+
+/// `text` as a quoted JSON string.
+fn string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c)
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use ::diagnostics::*;
+    use ::lex::Lexer;
+    use ::parse::parse;
+    use ::source::SourceMap;
+
+    #[test]
+    fn one_object_per_line() {
+        let source = "{h1 \"In\\\"tro\"\n\nText";
+        let mut sources = SourceMap::new();
+        sources.add_file("test.litx", source);
+        let mut out = Vec::new();
+        for err in parse(Lexer::new_with_filename(source, "test.litx")).unwrap_err() {
+            JsonEmitter::new().emit(&Diagnostic::from(err), &sources, &mut out).unwrap();
+        }
+        assert_eq!(String::from_utf8(out).unwrap(), concat!(
+            r#"{"severity":"error","code":"L0001","message":"expected `}` to close expression opened at line 1, found a blank line","#,
+            r#""file":"test.litx","byte_start":13,"byte_end":15,"line_start":1,"column_start":14,"line_end":3,"column_end":1,"#,
+            r#""labels":[{"primary":true,"message":"","file":"test.litx","byte_start":13,"byte_end":15,"line_start":1,"column_start":14,"line_end":3,"column_end":1},"#,
+            r#"{"primary":false,"message":"unclosed expression opened here","file":"test.litx","byte_start":0,"byte_end":1,"line_start":1,"column_start":1,"line_end":1,"column_end":2}],"#,
+            r#""notes":[],"help":"add `}` to close the expression"}"#,
+            "\n"));
+    }
+}
@@ -0,0 +1,30 @@
+use ::ast::Location;
+
+/// A stretch of source to underline in a diagnostic, with an optional short explanation.
+/// The primary label marks where the problem is; secondary labels point at whatever else helps explain it.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Label {
+    pub location: Location,
+    pub message: String,
+    pub primary: bool
+}
+
+impl Label {
+    pub fn primary<S: Into<String>>(location: &Location, message: S) -> Self {
+        Label {
+            location: location.clone(),
+            message: message.into(),
+            primary: true
+        }
+    }
+
+    pub fn secondary<S: Into<String>>(location: &Location, message: S) -> Self {
+        Label {
+            location: location.clone(),
+            message: message.into(),
+            primary: false
+        }
+    }
+}
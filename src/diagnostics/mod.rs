@@ -0,0 +1,14 @@
+mod code;
+mod diagnostic;
+mod emitter;
+mod json_emitter;
+mod label;
+mod severity;
+
+pub use self::code::Code;
+pub use self::code::CODES;
+pub use self::diagnostic::Diagnostic;
+pub use self::emitter::Emitter;
+pub use self::json_emitter::JsonEmitter;
+pub use self::label::Label;
+pub use self::severity::Severity;
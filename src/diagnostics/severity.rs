@@ -0,0 +1,18 @@
+use std::fmt;
+
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning")
+        }
+    }
+}
@@ -0,0 +1,438 @@
+use ::ast::Value;
+use ::diagnostics::Code;
+use std::rc::Weak;
+use super::ArgType;
+use super::Call;
+use super::citations::Bibliography;
+use super::citations::choose_citation_style;
+use super::citations::Cite;
+use super::citations::load_bibliographies;
+use super::Command;
+use super::CommandRegistry;
+use super::contents::flag;
+use super::contents::Toc;
+use super::EvalError;
+use super::Evaluator;
+use super::include::Include;
+use super::labels::Target;
+use super::labels::TargetKind;
+use super::Macro;
+use super::Node;
+use super::Signature;
+
+pub fn register(registry: &mut CommandRegistry) {
+    registry.register(Box::new(Litx));
+    registry.register(Box::new(LitxMeta));
+    for level in 1..HEADING_NAMES.len() + 1 {
+        registry.register(Box::new(Heading { level }));
+    }
+    registry.register(Box::new(Emph));
+    registry.register(Box::new(Strong));
+    registry.register(Box::new(List));
+    registry.register(Box::new(Equation));
+    registry.register(Box::new(Figure));
+    registry.register(Box::new(Label));
+    registry.register(Box::new(Ref));
+    registry.register(Box::new(PageRef));
+    registry.register(Box::new(Toc));
+    registry.register(Box::new(Cite));
+    registry.register(Box::new(Bibliography));
+    registry.register(Box::new(Ignore));
+    registry.register(Box::new(Hyphenate));
+    registry.register(Box::new(Define));
+    registry.register(Box::new(Include));
+}
+
+/// `{litx :key value ...}`: the document header. Its properties are checked by `DocumentMeta`.
+struct Litx;
+
+impl Command for Litx {
+    fn name(&self) -> &str {
+        "litx"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new().with_rest(ArgType::Any).with_any_keywords()
+    }
+
+    fn evaluate(&self, call: &Call, eval: &mut Evaluator) -> Result<Vec<Node>, EvalError> {
+        if eval.is_header(call.get_expr()) {
+            if let Some(value) = call.get_prop("bibliography") {
+                load_bibliographies(value, eval);
+            }
+            choose_citation_style(call, eval)?;
+            Ok(Vec::new())
+        } else {
+            Err(EvalError::new("the `litx` header must come first in the document", call.get_location()).with_code(Code::BadHeader))
+        }
+    }
+}
+
+/// `{litx.meta ...}`: options for how the document is put together.
+///
+/// `:table-of-contents start` begins the document with a table of contents, and `none` leaves it to `{toc}`.
+/// `:toc-depth`, `:toc-numbers` and `:toc-pages` set how every table of contents after them is set out.
+/// `:number-headings false` leaves the headings after it unnumbered, so `{ref ...}` gives their titles instead.
+struct LitxMeta;
+
+impl Command for LitxMeta {
+    fn name(&self) -> &str {
+        "litx.meta"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new()
+            .with_keyword("table-of-contents", ArgType::Ident)
+            .with_keyword("toc-depth", ArgType::Number)
+            .with_keyword("toc-numbers", ArgType::Ident)
+            .with_keyword("toc-pages", ArgType::Ident)
+            .with_keyword("number-headings", ArgType::Ident)
+    }
+
+    fn evaluate(&self, call: &Call, eval: &mut Evaluator) -> Result<Vec<Node>, EvalError> {
+        if let Some(Value::Ident(placement)) = call.get_prop("table-of-contents") {
+            match placement.name.as_str() {
+                "start" => eval.set_contents_at_start(true),
+                "none" => eval.set_contents_at_start(false),
+                name => {
+                    return Err(EvalError::new(format!("`:table-of-contents` must be `start` or `none`, not `{}`", name), &placement.location)
+                        .with_code(Code::BadArguments));
+                }
+            }
+        }
+        if let Some(value) = call.get_prop("number-headings") {
+            eval.set_number_headings(flag(value)?);
+        }
+        let options = eval.get_contents_options().with_props(call, "toc-")?;
+        eval.set_contents_options(options);
+        Ok(Vec::new())
+    }
+}
+
+const HEADING_NAMES: [&str; 6] = ["h1", "h2", "h3", "h4", "h5", "h6"];
+
+/// What a reference to a missing label prints, as in LaTeX.
+const UNRESOLVED: &str = "??";
+
+/// `{h1 content}` through `{h6 content}`. Each is numbered within the heading above it, for `{ref ...}` and tables of contents.
+struct Heading {
+    level: usize
+}
+
+impl Command for Heading {
+    fn name(&self) -> &str {
+        HEADING_NAMES[self.level - 1]
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new().with_positional(ArgType::Any)
+    }
+
+    fn evaluate(&self, call: &Call, eval: &mut Evaluator) -> Result<Vec<Node>, EvalError> {
+        let content = eval.evaluate_value(call.get_arg(0).unwrap());
+        Ok(vec![heading(self.level, content, eval)])
+    }
+}
+
+/// Numbers a heading, unless headings are unnumbered, and adds it to the outline, so it can be labelled and listed in
+/// tables of contents.
+pub fn heading(level: usize, content: Vec<Node>, eval: &mut Evaluator) -> Node {
+    let number = if eval.get_number_headings() { Some(eval.next_section_number(level)) } else { None };
+    eval.add_to_outline(level, number.clone(), content.clone());
+    eval.set_label_target(Target { kind: TargetKind::Section, number: number.clone(), title: Some(Node::plain_text(&content)) });
+    Node::Heading { level, number, content, labels: Vec::new(), listed: true }
+}
+
+/// A heading outside the section numbering and the outline, such as the title of a list of references. It can still be
+/// labelled, and is referred to by its title.
+pub fn unlisted_heading(level: usize, content: Vec<Node>, eval: &mut Evaluator) -> Node {
+    eval.set_label_target(Target { kind: TargetKind::Section, number: None, title: Some(Node::plain_text(&content)) });
+    Node::Heading { level, number: None, content, labels: Vec::new(), listed: false }
+}
+
+/// `{emph content}`.
+struct Emph;
+
+impl Command for Emph {
+    fn name(&self) -> &str {
+        "emph"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new().with_positional(ArgType::Any)
+    }
+
+    fn evaluate(&self, call: &Call, eval: &mut Evaluator) -> Result<Vec<Node>, EvalError> {
+        Ok(vec![Node::Emph(eval.evaluate_value(call.get_arg(0).unwrap()))])
+    }
+}
+
+/// `{strong content}`.
+struct Strong;
+
+impl Command for Strong {
+    fn name(&self) -> &str {
+        "strong"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new().with_positional(ArgType::Any)
+    }
+
+    fn evaluate(&self, call: &Call, eval: &mut Evaluator) -> Result<Vec<Node>, EvalError> {
+        Ok(vec![Node::Strong(eval.evaluate_value(call.get_arg(0).unwrap()))])
+    }
+}
+
+/// `{list item ...}`: a bulleted list with one entry per argument.
+struct List;
+
+impl Command for List {
+    fn name(&self) -> &str {
+        "list"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new().with_rest(ArgType::Any)
+    }
+
+    fn evaluate(&self, call: &Call, eval: &mut Evaluator) -> Result<Vec<Node>, EvalError> {
+        let items = call.get_args().iter().map(|arg| eval.evaluate_value(arg)).collect();
+        Ok(vec![Node::List(items)])
+    }
+}
+
+/// `{equation {$ ... $}}`: math displayed on a line of its own and numbered.
+struct Equation;
+
+impl Command for Equation {
+    fn name(&self) -> &str {
+        "equation"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new().with_positional(ArgType::Math)
+    }
+
+    fn evaluate(&self, call: &Call, eval: &mut Evaluator) -> Result<Vec<Node>, EvalError> {
+        let math = match *call.get_arg(0).unwrap() {
+            Value::Math(ref math) => math,
+            _ => unreachable!()
+        };
+        let formula = match eval.evaluate_math(math).pop() {
+            Some(Node::Math(formula)) | Some(Node::Equation { formula, .. }) => formula,
+            _ => return Ok(Vec::new())
+        };
+        let number = eval.next_equation_number();
+        eval.set_label_target(Target { kind: TargetKind::Equation, number: Some(number.to_string()), title: None });
+        Ok(vec![Node::Equation { formula, number: Some(number), labels: Vec::new() }])
+    }
+}
+
+/// `{figure content :caption "..."}`: content set apart from the text around it, with a numbered caption.
+struct Figure;
+
+impl Command for Figure {
+    fn name(&self) -> &str {
+        "figure"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new().with_positional(ArgType::Any).with_keyword("caption", ArgType::Any)
+    }
+
+    fn evaluate(&self, call: &Call, eval: &mut Evaluator) -> Result<Vec<Node>, EvalError> {
+        let content = eval.evaluate_value(call.get_arg(0).unwrap());
+        let caption = match call.get_prop("caption") {
+            Some(caption) => eval.evaluate_value(caption),
+            None => Vec::new()
+        };
+        let number = eval.next_figure_number();
+        let title = Some(Node::plain_text(&caption));
+        eval.set_label_target(Target { kind: TargetKind::Figure, number: Some(number.to_string()), title });
+        Ok(vec![Node::Figure { content, caption, number, labels: Vec::new() }])
+    }
+}
+
+/// `{label "name"}`: names the heading, figure or numbered equation before it, for `{ref ...}` and `{pageref ...}`.
+struct Label;
+
+impl Command for Label {
+    fn name(&self) -> &str {
+        "label"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new().with_positional(ArgType::Text)
+    }
+
+    fn evaluate(&self, call: &Call, eval: &mut Evaluator) -> Result<Vec<Node>, EvalError> {
+        let name = text_value(call.get_arg(0).unwrap());
+        eval.add_label(&name, call.get_location())?;
+        Ok(vec![Node::Anchor(name)])
+    }
+}
+
+/// `{ref "name"}`: the number of what a label names, or its title with `:show title`.
+struct Ref;
+
+impl Command for Ref {
+    fn name(&self) -> &str {
+        "ref"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new().with_positional(ArgType::Text).with_keyword("show", ArgType::Ident)
+    }
+
+    fn evaluate(&self, call: &Call, eval: &mut Evaluator) -> Result<Vec<Node>, EvalError> {
+        let name = text_value(call.get_arg(0).unwrap());
+        let show_title = match call.get_prop("show") {
+            Some(Value::Ident(ident)) if ident.name == "title" => true,
+            Some(Value::Ident(ident)) if ident.name != "number" => {
+                return Err(EvalError::new(format!("`:show` must be `number` or `title`, not `{}`", ident.name), &ident.location)
+                    .with_code(Code::BadArguments));
+            },
+            _ => false
+        };
+        let target = match eval.resolve_label(&name, call.get_location()) {
+            Some(target) => target,
+            None => return Ok(vec![Node::Text(UNRESOLVED.to_string())])
+        };
+        match (target.title, target.number) {
+            (_, Some(number)) if !show_title => Ok(vec![Node::Ref { label: name, number }]),
+            // Unnumbered headings are referred to by their titles.
+            (Some(ref title), _) => Ok(Node::text(title)),
+            (None, _) => {
+                Err(EvalError::new(format!("label `{}` names an equation, which has no title", name), call.get_location())
+                    .with_code(Code::BadLabel))
+            }
+        }
+    }
+}
+
+/// `{pageref "name"}`: the number of the page a label is on, in backends which have pages, and the name of what it
+/// labels in those which do not.
+struct PageRef;
+
+impl Command for PageRef {
+    fn name(&self) -> &str {
+        "pageref"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new().with_positional(ArgType::Text)
+    }
+
+    fn evaluate(&self, call: &Call, eval: &mut Evaluator) -> Result<Vec<Node>, EvalError> {
+        let label = text_value(call.get_arg(0).unwrap());
+        match eval.resolve_label(&label, call.get_location()) {
+            Some(target) => Ok(vec![Node::PageRef { label, title: target.name() }]),
+            None => Ok(vec![Node::Text(UNRESOLVED.to_string())])
+        }
+    }
+}
+
+/// `{ignore ...}`: drops its contents entirely.
+struct Ignore;
+
+impl Command for Ignore {
+    fn name(&self) -> &str {
+        "ignore"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new().with_rest(ArgType::Any).with_any_keywords()
+    }
+
+    fn evaluate(&self, _: &Call, _: &mut Evaluator) -> Result<Vec<Node>, EvalError> {
+        Ok(Vec::new())
+    }
+}
+
+/// `{hyphenate "ta-ble" ...}`: says where words may be broken across lines, overriding the language's patterns.
+struct Hyphenate;
+
+impl Command for Hyphenate {
+    fn name(&self) -> &str {
+        "hyphenate"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new().with_positional(ArgType::Text).with_rest(ArgType::Text)
+    }
+
+    fn evaluate(&self, call: &Call, eval: &mut Evaluator) -> Result<Vec<Node>, EvalError> {
+        for arg in call.get_args() {
+            let word = match *arg {
+                Value::String(ref string) => string.value.clone(),
+                Value::Ident(ref ident) => ident.name.clone(),
+                _ => unreachable!()
+            };
+            let is_word = word.split('-').all(|part| !part.is_empty() && part.chars().all(char::is_alphabetic));
+            if !is_word {
+                eval.error(EvalError::new(format!("`{}` is not a word with hyphens between its letters", word), arg.location())
+                    .with_code(Code::BadArguments));
+                continue;
+            }
+            eval.add_hyphenation(word);
+        }
+        Ok(Vec::new())
+    }
+}
+
+/// `{define name :params {list a b} body ...}`: declares a macro for the rest of the current scope.
+struct Define;
+
+impl Command for Define {
+    fn name(&self) -> &str {
+        "define"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new()
+            .with_positional(ArgType::Ident)
+            .with_rest(ArgType::Any)
+            .with_keyword("params", ArgType::Expr)
+    }
+
+    fn evaluate(&self, call: &Call, eval: &mut Evaluator) -> Result<Vec<Node>, EvalError> {
+        let name = match *call.get_arg(0).unwrap() {
+            Value::Ident(ref ident) => ident.name.clone(),
+            _ => unreachable!()
+        };
+        let mut params: Vec<String> = Vec::new();
+        if let Some(Value::Expr(list)) = call.get_prop("params") {
+            let is_list = list.name.as_ref().map(|ident| ident.name == "list").unwrap_or(false);
+            if !is_list || !list.props.is_empty() {
+                return Err(EvalError::new("`:params` must be a `list` of parameter names", &list.location).with_code(Code::BadMacro));
+            }
+            for param in &list.args {
+                match *param {
+                    Value::Ident(ref ident) if params.contains(&ident.name) => {
+                        return Err(EvalError::new(format!("parameter `{}` is declared twice", ident.name), &ident.location).with_code(Code::BadMacro));
+                    },
+                    Value::Ident(ref ident) => params.push(ident.name.clone()),
+                    _ => return Err(EvalError::new("parameter names must be bare identifiers", param.location()).with_code(Code::BadMacro))
+                }
+            }
+        }
+        eval.define(Macro {
+            name,
+            params,
+            body: call.get_args()[1..].to_vec(),
+            location: call.get_location().clone(),
+            scope: Weak::new()
+        })?;
+        Ok(Vec::new())
+    }
+}
+
+/// The text of an argument checked against `ArgType::Text`.
+fn text_value(value: &Value) -> String {
+    match *value {
+        Value::String(ref string) => string.value.clone(),
+        Value::Ident(ref ident) => ident.name.clone(),
+        _ => unreachable!()
+    }
+}
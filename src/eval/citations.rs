@@ -0,0 +1,272 @@
+use ::ast::Location;
+use ::ast::Value;
+use ::bibliography;
+use ::bibliography::CITATION_STYLES;
+use ::diagnostics::Code;
+use ::lex::Lexer;
+use ::parse::parse;
+use std::fs;
+use std::path::Path;
+use super::builtins::unlisted_heading;
+use super::ArgType;
+use super::Call;
+use super::Command;
+use super::EvalError;
+use super::Evaluator;
+use super::Node;
+use super::Signature;
+
+/// Loads every bibliography named by the header's `:bibliography`, a path or a `{list ...}` of them.
+/// Values of the wrong type are left for `DocumentMeta` to report.
+pub fn load_bibliographies(value: &Value, eval: &mut Evaluator) {
+    let paths = match *value {
+        Value::Expr(ref list) => list.args.iter().collect(),
+        ref value => vec![value]
+    };
+    for path in paths {
+        if let Value::String(ref string) = *path {
+            load_bibliography(&string.value, &string.location, eval);
+        }
+    }
+}
+
+/// Sets the citation style named by the header's `:citation-style`, or by its `:doctype` if that names one.
+pub fn choose_citation_style(call: &Call, eval: &mut Evaluator) -> Result<(), EvalError> {
+    let name = |value: &Value| match *value {
+        Value::String(ref string) => Some(string.value.clone()),
+        Value::Ident(ref ident) => Some(ident.name.clone()),
+        _ => None
+    };
+    if let Some(value) = call.get_prop("citation-style") {
+        if let Some(name) = name(value) {
+            let style = bibliography::citation_style(&name).ok_or_else(|| {
+                let known: Vec<String> = CITATION_STYLES.iter().map(|style| format!("`{}`", style)).collect();
+                EvalError::new(format!("unknown citation style `{}`; expected one of {}", name, known.join(", ")), value.location())
+                    .with_code(Code::BadHeader)
+            })?;
+            eval.set_citation_style(style);
+        }
+    } else if let Some(style) = call.get_prop("doctype").and_then(name).and_then(|doctype| bibliography::citation_style(&doctype)) {
+        eval.set_citation_style(style);
+    }
+    Ok(())
+}
+
+/// Adds the entries of the bibliography at `target` to the document's, reporting its errors at `site` too.
+///
+/// The path is relative to the file naming it, as for `include`. Files ending in `.bib` are read as BibTeX, and
+/// anything else as a litx file of `{entry ...}` expressions.
+fn load_bibliography(target: &str, site: &Location, eval: &mut Evaluator) {
+    let base = site.file.as_ref()
+        .and_then(|file| Path::new(file).parent())
+        .unwrap_or_else(|| Path::new(""));
+    let path = base.join(target);
+    let filename = path.to_string_lossy().into_owned();
+    let source = match fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(err) => {
+            eval.error(EvalError::new(format!("cannot read the bibliography `{}`: {}", filename, err), site)
+                .with_code(Code::BadBibliography));
+            return;
+        }
+    };
+
+    eval.add_source(&filename, &source);
+    let (entries, errors) = if path.extension().map(|extension| extension == "bib").unwrap_or(false) {
+        bibliography::read_bibtex(&source, &filename)
+    } else {
+        match parse(Lexer::new_with_filename(&source, &filename)) {
+            Ok(production) => bibliography::read_native(&::ast::lower(&production)),
+            Err(errors) => {
+                for err in errors {
+                    eval.error(EvalError::from(err).with_related(site, "in the bibliography named here"));
+                }
+                return;
+            }
+        }
+    };
+    for err in errors {
+        eval.error(EvalError::from(err).with_related(site, "in the bibliography named here"));
+    }
+    for entry in entries {
+        if let Err(err) = eval.add_entry(entry) {
+            eval.error(err.with_related(site, "in the bibliography named here"));
+        }
+    }
+}
+
+/// `{cite "knuth84" :page 12}`: an in-text citation of one or more works from the document's bibliographies.
+/// Each work cited is listed by `{bibliography}`.
+pub struct Cite;
+
+impl Command for Cite {
+    fn name(&self) -> &str {
+        "cite"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new()
+            .with_positional(ArgType::Text)
+            .with_rest(ArgType::Text)
+            .with_keyword("page", ArgType::Any)
+    }
+
+    fn evaluate(&self, call: &Call, eval: &mut Evaluator) -> Result<Vec<Node>, EvalError> {
+        let page = match call.get_prop("page") {
+            Some(value) if call.get_args().len() > 1 => {
+                return Err(EvalError::new("`:page` can only be given when citing a single work", value.location())
+                    .with_code(Code::BadArguments));
+            },
+            Some(value) => Some(Node::plain_text(&eval.evaluate_value(value))),
+            None => None
+        };
+        let mut entries = Vec::new();
+        for arg in call.get_args() {
+            let key = match *arg {
+                Value::String(ref string) => &string.value,
+                Value::Ident(ref ident) => &ident.name,
+                _ => unreachable!()
+            };
+            match eval.cite(key, arg.location()) {
+                Ok(entry) => entries.push(entry),
+                Err(err) => eval.error(err)
+            }
+        }
+        if entries.is_empty() {
+            return Ok(Vec::new());
+        }
+        let works: Vec<_> = entries.iter().map(|entry| (entry, page.as_deref())).collect();
+        Ok(eval.get_citation_style().format_citation(&works))
+    }
+}
+
+/// `{bibliography :title "Sources"}`: a section listing every work cited in the document, in order of author.
+/// The title defaults to the citation style's, such as `Works Cited` for MLA. It is an unnumbered heading, which
+/// tables of contents leave out.
+pub struct Bibliography;
+
+impl Command for Bibliography {
+    fn name(&self) -> &str {
+        "bibliography"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new().with_keyword("title", ArgType::Any)
+    }
+
+    fn evaluate(&self, call: &Call, eval: &mut Evaluator) -> Result<Vec<Node>, EvalError> {
+        let title = match call.get_prop("title") {
+            Some(value) => eval.evaluate_value(value),
+            None => Node::text(eval.get_citation_style().references_title())
+        };
+        let mut nodes = vec![unlisted_heading(1, title, eval)];
+        let entries = eval.cited_entries();
+        let style = eval.get_citation_style();
+        nodes.extend(entries.iter().map(|entry| Node::Paragraph(style.format_reference(entry))));
+        Ok(nodes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::eval::*;
+    use ::lex::Lexer;
+    use ::parse::parse;
+    use std::fs;
+
+    /// Writes `files` into a fresh directory and evaluates `source` as if it were a file there.
+    fn evaluate_with_files(name: &str, source: &str, files: &[(&str, &str)]) -> Result<Document, Vec<String>> {
+        let dir = ::std::env::temp_dir().join(format!("litx-citations-{}-{}", name, ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for &(file, contents) in files {
+            fs::write(dir.join(file), contents).unwrap();
+        }
+        let main = dir.join("main.litx").to_string_lossy().into_owned();
+        let doc = ::ast::lower(&parse(Lexer::new_with_filename(source, &main)).unwrap());
+        let result = ::eval::evaluate(&doc, &CommandRegistry::with_builtins())
+            .map_err(|errors| errors.iter().map(|err| err.to_string().replace(&*dir.to_string_lossy(), "DIR")).collect());
+        fs::remove_dir_all(&dir).unwrap();
+        result
+    }
+
+    #[test]
+    fn cites_works() {
+        let bib = "@book{knuth84, author = {Donald E. Knuth}, title = {The {\\TeX}book}, year = 1984, publisher = {Addison-Wesley}}\n\
+            @book{unused, author = {Nobody}, title = {Unread}}";
+        let native = "{entry lamport94 :type book :author \"Leslie Lamport\" :title \"LaTeX\" :year 1994}";
+        let source = "{litx :bibliography {list \"refs.bib\" \"more.litx\"}}\n\n\
+            As shown {cite lamport94 knuth84}, and again {cite \"knuth84\" :page 12}.\n\n{bibliography}";
+        let doc = evaluate_with_files("cites", source, &[("refs.bib", bib), ("more.litx", native)]).unwrap();
+        let text: Vec<String> = doc.blocks.iter().map(|block| Node::plain_text(::std::slice::from_ref(block))).collect();
+        assert_eq!(text, vec![
+            "As shown (Lamport 1994; Knuth 1984), and again (Knuth 1984, 12).",
+            "References",
+            "Knuth, Donald E. 1984. The TeXbook. Addison-Wesley.",
+            "Lamport, Leslie. 1994. LaTeX."
+        ]);
+    }
+
+    #[test]
+    fn bibliography_heading_is_unnumbered() {
+        let bib = "@book{knuth84, author = {Donald E. Knuth}, title = {The {\\TeX}book}, year = 1984}";
+        let source = "{litx :bibliography \"refs.bib\"}\n\n{toc :pages false}\n\n{h1 \"Intro\"}\nAs shown {cite knuth84}.\n\n\
+            {bibliography}\n\n{h1 \"Appendix\"}";
+        let doc = evaluate_with_files("unnumbered", source, &[("refs.bib", bib)]).unwrap();
+        let headings: Vec<_> = doc.blocks.iter()
+            .filter_map(|block| match *block {
+                Node::Heading { ref number, ref content, listed, .. } => Some((number.clone(), Node::plain_text(content), listed)),
+                _ => None
+            })
+            .collect();
+        assert_eq!(headings, vec![
+            (Some("1".to_string()), "Intro".to_string(), true),
+            (None, "References".to_string(), false),
+            (Some("2".to_string()), "Appendix".to_string(), true)
+        ]);
+        let titles: Vec<String> = match doc.blocks[0] {
+            Node::Contents { ref entries, .. } => entries.iter().map(|entry| Node::plain_text(&entry.title)).collect(),
+            _ => Vec::new()
+        };
+        assert_eq!(titles, vec!["Intro", "Appendix"]);
+    }
+
+    #[test]
+    fn suffixes_years_without_a_bibliography() {
+        let bib = "@book{b, author = {Ann Smith}, title = {Beta}, year = 2020}\n@book{a, author = {Ann Smith}, title = {Alpha}, year = 2020}";
+        let source = "{litx :bibliography \"refs.bib\"}\n\nSee {cite b} and {cite a}.";
+        let doc = evaluate_with_files("suffixes", source, &[("refs.bib", bib)]).unwrap();
+        assert_eq!(Node::plain_text(&doc.blocks), "See (Smith 2020b) and (Smith 2020a).");
+    }
+
+    #[test]
+    fn reports_bibliography_errors() {
+        let bib = "@book{a, title = {A}}\n@book{a, title = {Again}}\n@book{b, title = undefined}";
+        let source = "{litx :bibliography {list \"refs.bib\" \"missing.bib\"}}\n\n{cite a c} {cite a b :page 2}";
+        let mut errors = evaluate_with_files("errors", source, &[("refs.bib", bib)]).unwrap_err();
+        let missing = errors.remove(2);
+        assert!(missing.starts_with("DIR/main.litx:1:38: cannot read the bibliography `DIR/missing.bib`: "), "{}", missing);
+        assert_eq!(errors, vec![
+            "DIR/refs.bib:3:18: `undefined` is not a defined `@string`\nDIR/main.litx:1:27: note: in the bibliography named here",
+            "DIR/refs.bib:2:1: entry `a` is already defined\nDIR/refs.bib:1:1: note: previously defined here\n\
+                DIR/main.litx:1:27: note: in the bibliography named here",
+            "DIR/main.litx:3:9: `c` is not in the bibliography",
+            "DIR/main.litx:3:28: `:page` can only be given when citing a single work"
+        ]);
+    }
+
+    #[test]
+    fn chooses_citation_styles() {
+        let bib = "@book{knuth84, author = {Donald E. Knuth}, title = {The {\\TeX}book}, year = 1984}";
+        let cite = |header: &str| {
+            let source = format!("{{litx :bibliography \"refs.bib\" {}}}\n\n{{cite knuth84 :page 12}}\n\n{{bibliography}}", header);
+            evaluate_with_files("styles", &source, &[("refs.bib", bib)])
+                .map(|doc| doc.blocks.iter().map(|block| Node::plain_text(::std::slice::from_ref(block))).collect::<Vec<_>>())
+        };
+        assert_eq!(cite(":doctype mla").unwrap(), vec!["(Knuth 12)", "Works Cited", "Knuth, Donald E. The TeXbook. 1984."]);
+        assert_eq!(cite(":doctype mla :citation-style apa").unwrap(), vec!["(Knuth, 1984, p. 12)", "References", "Knuth, D. E. (1984). The TeXbook."]);
+        assert_eq!(cite(":doctype essay").unwrap()[0], "(Knuth 1984, 12)");
+        assert_eq!(cite(":citation-style harvard").unwrap_err(), vec![
+            "DIR/main.litx:1:48: unknown citation style `harvard`; expected one of `chicago`, `apa`, `mla`"
+        ]);
+    }
+}
@@ -0,0 +1,52 @@
+use ::ast::Expr;
+use ::ast::Location;
+use ::ast::Value;
+use super::EvalError;
+use super::Evaluator;
+use super::Node;
+use super::Signature;
+
+/// Something an expression can invoke, such as `h1` or `emph`.
+/// The evaluator checks each call against `signature` before `evaluate` sees it.
+pub trait Command {
+    fn name(&self) -> &str;
+
+    fn signature(&self) -> Signature;
+
+    fn evaluate(&self, call: &Call, eval: &mut Evaluator) -> Result<Vec<Node>, EvalError>;
+}
+
+/// An expression which has passed its command's signature check.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub struct Call<'e> {
+    expr: &'e Expr
+}
+
+impl <'e> Call<'e> {
+    pub fn new(expr: &'e Expr) -> Self {
+        Call { expr }
+    }
+
+    pub fn get_expr(&self) -> &'e Expr {
+        self.expr
+    }
+
+    pub fn get_location(&self) -> &'e Location {
+        &self.expr.location
+    }
+
+    pub fn get_args(&self) -> &'e [Value] {
+        &self.expr.args
+    }
+
+    pub fn get_arg(&self, index: usize) -> Option<&'e Value> {
+        self.expr.args.get(index)
+    }
+
+    pub fn get_prop(&self, key: &str) -> Option<&'e Value> {
+        self.expr.props.iter()
+            .find(|prop| prop.key.name == key)
+            .map(|prop| &prop.value)
+    }
+}
@@ -0,0 +1,31 @@
+use std::collections::HashMap;
+use super::builtins;
+use super::Command;
+
+/// The commands available to a document, looked up by name.
+#[derive(Default)]
+pub struct CommandRegistry {
+    commands: HashMap<String, Box<dyn Command>>
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// A registry holding every built-in command.
+    pub fn with_builtins() -> Self {
+        let mut registry = CommandRegistry::new();
+        builtins::register(&mut registry);
+        registry
+    }
+
+    /// Adds `command`, replacing any existing command with the same name.
+    pub fn register(&mut self, command: Box<dyn Command>) {
+        self.commands.insert(command.name().to_string(), command);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Command> {
+        self.commands.get(name).map(|command| &**command)
+    }
+}
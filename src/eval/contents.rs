@@ -0,0 +1,95 @@
+use ::ast::Value;
+use ::diagnostics::Code;
+use super::ArgType;
+use super::Call;
+use super::Command;
+use super::EvalError;
+use super::Evaluator;
+use super::Node;
+use super::Signature;
+
+/// How a table of contents is set out: the deepest heading level it lists, whether it shows the headings' numbers,
+/// and whether it shows their pages in backends which have pages.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub struct ContentsOptions {
+    pub depth: usize,
+    pub numbers: bool,
+    pub pages: bool
+}
+
+impl Default for ContentsOptions {
+    fn default() -> Self {
+        ContentsOptions { depth: 3, numbers: true, pages: true }
+    }
+}
+
+impl ContentsOptions {
+    /// Overrides these options with the `:depth`, `:numbers` and `:pages` properties of `call`, each with `prefix`
+    /// in front of its name.
+    pub fn with_props(mut self, call: &Call, prefix: &str) -> Result<Self, EvalError> {
+        if let Some(value) = call.get_prop(&format!("{}depth", prefix)) {
+            let depth = match *value {
+                Value::Number(ref number) => number.source.parse().unwrap_or(0),
+                _ => 0
+            };
+            if !(1..=6).contains(&depth) {
+                return Err(EvalError::new("the depth of a table of contents must be a heading level from 1 to 6", value.location())
+                    .with_code(Code::BadArguments));
+            }
+            self.depth = depth;
+        }
+        if let Some(value) = call.get_prop(&format!("{}numbers", prefix)) {
+            self.numbers = flag(value)?;
+        }
+        if let Some(value) = call.get_prop(&format!("{}pages", prefix)) {
+            self.pages = flag(value)?;
+        }
+        Ok(self)
+    }
+}
+
+/// A heading as a table of contents lists it.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct ContentsEntry {
+    pub level: usize,
+    /// The heading's number, such as `2.1`, unless the table of contents leaves numbers out.
+    pub number: Option<String>,
+    pub title: Vec<Node>,
+    /// Which heading of the document this is, counting from 0, for backends which look up its page.
+    pub heading: usize
+}
+
+/// `{toc :depth 2 :numbers false :pages false}`: a table of contents listing the document's headings.
+/// Any option left out takes its value from `{litx.meta ...}`.
+pub struct Toc;
+
+impl Command for Toc {
+    fn name(&self) -> &str {
+        "toc"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new()
+            .with_keyword("depth", ArgType::Number)
+            .with_keyword("numbers", ArgType::Ident)
+            .with_keyword("pages", ArgType::Ident)
+    }
+
+    fn evaluate(&self, call: &Call, eval: &mut Evaluator) -> Result<Vec<Node>, EvalError> {
+        let options = eval.get_contents_options().with_props(call, "")?;
+        Ok(vec![eval.contents(options)])
+    }
+}
+
+/// Reads `true` or `false`.
+pub fn flag(value: &Value) -> Result<bool, EvalError> {
+    match *value {
+        Value::Ident(ref ident) if ident.name == "true" => Ok(true),
+        Value::Ident(ref ident) if ident.name == "false" => Ok(false),
+        _ => Err(EvalError::new("expected `true` or `false`", value.location()).with_code(Code::BadArguments))
+    }
+}
@@ -0,0 +1,13 @@
+use ::meta::DocumentMeta;
+use super::Node;
+
+/// The result of evaluating a document: its metadata and a sequence of block nodes.
+/// `hyphenations` are the words given to `{hyphenate ...}`, with their hyphens where they may break, such as `ta-ble`.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Document {
+    pub meta: DocumentMeta,
+    pub blocks: Vec<Node>,
+    pub hyphenations: Vec<String>
+}
@@ -0,0 +1,89 @@
+use ::ast::Location;
+use ::bibliography::BibError;
+use ::diagnostics::Code;
+use ::diagnostics::Diagnostic;
+use ::diagnostics::Label;
+use ::meta::MetaError;
+use ::parse::ParseError;
+use std::fmt;
+
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct EvalError {
+    pub code: Code,
+    pub message: String,
+    pub location: Location,
+    /// Other places worth pointing at, such as the definition of the macro which went wrong.
+    pub related: Vec<(Location, String)>
+}
+
+impl EvalError {
+    pub fn new<S: Into<String>>(message: S, location: &Location) -> Self {
+        EvalError {
+            code: Code::CommandFailed,
+            message: message.into(),
+            location: location.clone(),
+            related: Vec::new()
+        }
+    }
+
+    pub fn with_code(mut self, code: Code) -> Self {
+        self.code = code;
+        self
+    }
+
+    pub fn with_related<S: Into<String>>(mut self, location: &Location, message: S) -> Self {
+        self.related.push((location.clone(), message.into()));
+        self
+    }
+}
+
+impl From<MetaError> for EvalError {
+    fn from(err: MetaError) -> Self {
+        EvalError {
+            code: Code::BadHeader,
+            message: err.message,
+            location: err.location,
+            related: Vec::new()
+        }
+    }
+}
+
+impl From<BibError> for EvalError {
+    fn from(err: BibError) -> Self {
+        EvalError::new(err.message, &err.location).with_code(Code::BadBibliography)
+    }
+}
+
+impl <'a, 'b> From<ParseError<'a, 'b>> for EvalError {
+    fn from(err: ParseError<'a, 'b>) -> Self {
+        let location = match err {
+            ParseError::Lex(ref lex) => Location::from_span(&lex.get_span()),
+            _ => Location::from_token(err.get_token().unwrap())
+        };
+        EvalError::new(err.get_message(), &location).with_code(err.get_code())
+    }
+}
+
+impl From<EvalError> for Diagnostic {
+    fn from(err: EvalError) -> Self {
+        let mut diagnostic = Diagnostic::error(err.message)
+            .with_code(err.code)
+            .with_label(Label::primary(&err.location, ""));
+        for (location, message) in err.related {
+            diagnostic = diagnostic.with_label(Label::secondary(&location, message));
+        }
+        diagnostic
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)?;
+        for (location, message) in &self.related {
+            write!(f, "\n{}: note: {}", location, message)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,822 @@
+use ::ast;
+use ::ast::Expr;
+use ::ast::Inline;
+use ::ast::Location;
+use ::ast::Value;
+use ::bibliography::Chicago;
+use ::bibliography::CitationStyle;
+use ::bibliography::Entry;
+use ::diagnostics::Code;
+use ::meta::DocumentMeta;
+use ::source::SourceMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::fs;
+use std::mem;
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+use super::macros::MAX_MACRO_DEPTH;
+use super::Call;
+use super::CommandRegistry;
+use super::ContentsEntry;
+use super::ContentsOptions;
+use super::Document;
+use super::EvalError;
+use super::labels::Label;
+use super::labels::MAX_PASSES;
+use super::labels::Target;
+use super::Macro;
+use super::Node;
+use super::Scope;
+
+/// Walks a lowered document, dispatching each expression to the command registered under its name.
+/// Commands receive the evaluator so that they can evaluate their own arguments and report further errors.
+///
+/// Macros live in scopes: one for the document, and one for each macro expansion in progress, nested in the scope the
+/// macro was defined in. An included file shares the scope of its `include`, so a file of `define`s can be included
+/// for the rest of the document to use.
+/// Scoping is lexical: an expression is evaluated in the scope of the innermost file or expansion in progress whose
+/// source contains it, so a macro body sees the macros around its definition, and an argument substituted into it
+/// those around its use. A `define` reaches the end of the document or expansion it appears in.
+///
+/// References to labels, tables of contents, citations and bibliographies are resolved against the labels, headings
+/// and citations found by the previous pass over the document, so a document which uses them is evaluated again until
+/// those stop changing.
+pub struct Evaluator<'r> {
+    registry: &'r CommandRegistry,
+    header: Option<Location>,
+    frames: Vec<Frame>,
+    depth: usize,
+    files: Vec<PathBuf>,
+    sources: SourceMap,
+    hyphenations: Vec<String>,
+    equations: usize,
+    sections: Vec<usize>,
+    figures: usize,
+    /// The heading, figure or numbered equation a `{label ...}` here would attach to.
+    target: Option<Target>,
+    labels: HashMap<String, Label>,
+    /// The labels found by the previous pass.
+    known_labels: HashMap<String, Label>,
+    /// The labels looked up this pass but not found, with where each was wanted.
+    unresolved: Vec<(String, Location)>,
+    /// Every heading so far, in order, with its number.
+    outline: Vec<ContentsEntry>,
+    /// The headings found by the previous pass.
+    known_outline: Vec<ContentsEntry>,
+    contents_options: ContentsOptions,
+    contents_at_start: bool,
+    /// Whether headings are numbered, which `{litx.meta :number-headings false}` turns off.
+    number_headings: bool,
+    /// Every work in the document's bibliographies, by key.
+    entries: HashMap<String, Entry>,
+    /// The keys cited so far, each once, in the order first cited.
+    citations: Vec<String>,
+    /// The keys cited by the previous pass.
+    known_citations: Vec<String>,
+    citation_style: Box<dyn CitationStyle>,
+    has_references: bool,
+    errors: Vec<EvalError>
+}
+
+impl <'r> Evaluator<'r> {
+    pub fn new(registry: &'r CommandRegistry) -> Self {
+        Evaluator {
+            registry,
+            header: None,
+            frames: Vec::new(),
+            depth: 0,
+            files: Vec::new(),
+            sources: SourceMap::new(),
+            hyphenations: Vec::new(),
+            equations: 0,
+            sections: Vec::new(),
+            figures: 0,
+            target: None,
+            labels: HashMap::new(),
+            known_labels: HashMap::new(),
+            unresolved: Vec::new(),
+            outline: Vec::new(),
+            known_outline: Vec::new(),
+            contents_options: ContentsOptions::default(),
+            contents_at_start: false,
+            number_headings: true,
+            entries: HashMap::new(),
+            citations: Vec::new(),
+            known_citations: Vec::new(),
+            citation_style: Box::new(Chicago),
+            has_references: false,
+            errors: Vec::new()
+        }
+    }
+
+    pub fn error(&mut self, err: EvalError) {
+        self.errors.push(err);
+    }
+
+    pub fn into_errors(self) -> Vec<EvalError> {
+        self.errors
+    }
+
+    /// Keeps the text of a file loaded during evaluation, so its errors can be shown in context.
+    pub fn add_source(&mut self, name: &str, source: &str) {
+        self.sources.add_file(name, source);
+    }
+
+    pub fn take_sources(&mut self) -> SourceMap {
+        mem::take(&mut self.sources)
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Adds a note at `location` to every error reported since there were `first_error` errors,
+    /// for errors that are only understandable with the context of where they were triggered.
+    pub fn note_errors_since<S: Into<String>>(&mut self, first_error: usize, location: &Location, message: S) {
+        let note = (location.clone(), message.into());
+        for err in &mut self.errors[first_error..] {
+            if !err.related.contains(&note) {
+                err.related.push(note.clone());
+            }
+        }
+    }
+
+    /// Records how `word` may be hyphenated, as in `ta-ble`, for the whole document.
+    pub fn add_hyphenation(&mut self, word: String) {
+        self.hyphenations.push(word);
+    }
+
+    /// Numbers the next numbered equation, counting from 1 through the whole document.
+    pub fn next_equation_number(&mut self) -> usize {
+        self.equations += 1;
+        self.equations
+    }
+
+    /// Numbers the next heading at `level`, as `2.1` for the first `h2` in the second `h1`.
+    pub fn next_section_number(&mut self, level: usize) -> String {
+        // A level skipped over, as by an `h3` straight after an `h1`, counts as its first section rather than as 0.
+        if self.sections.len() < level {
+            self.sections.resize(level - 1, 1);
+            self.sections.push(0);
+        }
+        self.sections.truncate(level);
+        self.sections[level - 1] += 1;
+        let numbers: Vec<String> = self.sections.iter().map(|number| number.to_string()).collect();
+        numbers.join(".")
+    }
+
+    pub fn next_figure_number(&mut self) -> usize {
+        self.figures += 1;
+        self.figures
+    }
+
+    /// Makes `target` what any `{label ...}` from here on attaches to, until the next heading, figure or equation.
+    pub fn set_label_target(&mut self, target: Target) {
+        self.target = Some(target);
+    }
+
+    /// Attaches the label `name` to the most recent heading, figure or numbered equation.
+    pub fn add_label(&mut self, name: &str, location: &Location) -> Result<(), EvalError> {
+        let target = match self.target {
+            Some(ref target) => target.clone(),
+            None => {
+                return Err(EvalError::new(format!("label `{}` has no heading, figure or numbered equation before it", name), location)
+                    .with_code(Code::BadLabel));
+            }
+        };
+        if let Some(previous) = self.labels.get(name) {
+            return Err(EvalError::new(format!("label `{}` is already defined", name), location)
+                .with_code(Code::BadLabel)
+                .with_related(&previous.location, "previously defined here"));
+        }
+        self.labels.insert(name.to_string(), Label { target, location: location.clone() });
+        Ok(())
+    }
+
+    /// What the label `name` was attached to on the previous pass, if anything.
+    /// Labels which are still missing once the document has settled are reported as undefined at `location`.
+    pub fn resolve_label(&mut self, name: &str, location: &Location) -> Option<Target> {
+        self.has_references = true;
+        let target = self.known_labels.get(name).map(|label| label.target.clone());
+        if target.is_none() {
+            self.unresolved.push((name.to_string(), location.clone()));
+        }
+        target
+    }
+
+    /// Adds a heading to the outline tables of contents are made from.
+    pub fn add_to_outline(&mut self, level: usize, number: Option<String>, title: Vec<Node>) {
+        let heading = self.outline.len();
+        self.outline.push(ContentsEntry { level, number, title, heading });
+    }
+
+    /// The options set by `{litx.meta ...}` for every table of contents.
+    pub fn get_contents_options(&self) -> ContentsOptions {
+        self.contents_options
+    }
+
+    pub fn set_contents_options(&mut self, options: ContentsOptions) {
+        self.contents_options = options;
+    }
+
+    /// Whether the document should begin with a table of contents, as well as having one wherever `{toc}` is.
+    pub fn set_contents_at_start(&mut self, at_start: bool) {
+        self.contents_at_start = at_start;
+    }
+
+    pub fn get_number_headings(&self) -> bool {
+        self.number_headings
+    }
+
+    pub fn set_number_headings(&mut self, number_headings: bool) {
+        self.number_headings = number_headings;
+    }
+
+    /// A table of contents of the headings found by the previous pass.
+    pub fn contents(&mut self, options: ContentsOptions) -> Node {
+        self.has_references = true;
+        let entries = self.known_outline.iter()
+            .filter(|entry| entry.level <= options.depth)
+            .map(|entry| ContentsEntry {
+                number: if options.numbers { entry.number.clone() } else { None },
+                ..entry.clone()
+            })
+            .collect();
+        Node::Contents { entries, pages: options.pages }
+    }
+
+    /// Adds a work from a bibliography, which may then be cited by its key.
+    pub fn add_entry(&mut self, entry: Entry) -> Result<(), EvalError> {
+        if let Some(previous) = self.entries.get(&entry.key) {
+            return Err(EvalError::new(format!("entry `{}` is already defined", entry.key), &entry.location)
+                .with_code(Code::BadBibliography)
+                .with_related(&previous.location, "previously defined here"));
+        }
+        self.entries.insert(entry.key.clone(), entry);
+        Ok(())
+    }
+
+    /// Looks up the work with `key`, recording that the document cites it. The work has the year suffix the list of
+    /// references gives it, which depends on every work cited, so citing takes another pass like referring to labels.
+    pub fn cite(&mut self, key: &str, location: &Location) -> Result<Entry, EvalError> {
+        self.has_references = true;
+        let entry = match self.entries.get(key) {
+            Some(entry) => entry.clone(),
+            None => {
+                return Err(EvalError::new(format!("`{}` is not in the bibliography", key), location)
+                    .with_code(Code::UnknownCitation));
+            }
+        };
+        if !self.citations.iter().any(|cited| cited == key) {
+            self.citations.push(key.to_string());
+        }
+        Ok(self.references().into_iter().find(|reference| reference.key == key).unwrap_or(entry))
+    }
+
+    /// Every work the previous pass found cited, in the order the list of references gives them.
+    pub fn cited_entries(&mut self) -> Vec<Entry> {
+        self.has_references = true;
+        self.references()
+    }
+
+    /// The works cited by the previous pass, sorted and with year suffixes by the citation style.
+    fn references(&self) -> Vec<Entry> {
+        let mut entries: Vec<Entry> = self.known_citations.iter()
+            .filter_map(|key| self.entries.get(key))
+            .cloned()
+            .collect();
+        self.citation_style.sort_entries(&mut entries);
+        self.citation_style.tell_apart(&mut entries);
+        entries
+    }
+
+    /// How citations and lists of references are formatted, which is Chicago author–date unless the header says otherwise.
+    pub fn get_citation_style(&self) -> &dyn CitationStyle {
+        &*self.citation_style
+    }
+
+    pub fn set_citation_style(&mut self, style: Box<dyn CitationStyle>) {
+        self.citation_style = style;
+    }
+
+    /// Makes `mac` available for the rest of the current scope.
+    pub fn define(&mut self, mac: Macro) -> Result<(), EvalError> {
+        if self.registry.get(&mac.name).is_some() {
+            return Err(EvalError::new(format!("`{}` is a built-in command and cannot be redefined", mac.name), &mac.location)
+                .with_code(Code::BadMacro));
+        }
+        let scope = self.scope_of(&mac.location);
+        if let Some(previous) = scope.get(&mac.name) {
+            return Err(EvalError::new(format!("`{}` is already defined", mac.name), &mac.location)
+                .with_code(Code::BadMacro)
+                .with_related(&previous.location, "previously defined here"));
+        }
+        scope.insert(Macro { scope: Rc::downgrade(&scope), ..mac });
+        Ok(())
+    }
+
+    /// The scope code written at `location` is evaluated in.
+    fn scope_of(&self, location: &Location) -> Rc<Scope> {
+        self.frames.iter()
+            .rev()
+            .find(|frame| frame.region.contains(location))
+            .map(|frame| frame.scope.clone())
+            .expect("evaluating outside of a document")
+    }
+
+    /// Whether `expr` is the `{litx ...}` header of the document being evaluated.
+    pub fn is_header(&self, expr: &Expr) -> bool {
+        self.header.as_ref() == Some(&expr.location)
+    }
+
+    /// Whether the file at the canonical path `path` is already being evaluated, so including it again would never end.
+    pub fn is_including(&self, path: &Path) -> bool {
+        self.files.iter().any(|file| file == path)
+    }
+
+    pub fn evaluate_document(&mut self, doc: &ast::Document) -> Document {
+        let first_error = self.errors.len();
+        let mut passes = 1;
+        loop {
+            let document = self.evaluate_pass(doc);
+            let settled = self.labels == self.known_labels
+                && self.outline == self.known_outline
+                && self.citations == self.known_citations;
+            if !self.has_references || settled {
+                for (name, location) in mem::take(&mut self.unresolved) {
+                    self.error(EvalError::new(format!("undefined label `{}`", name), &location).with_code(Code::UndefinedLabel));
+                }
+                return document;
+            }
+            if passes == MAX_PASSES {
+                self.unsettled_error();
+                return document;
+            }
+            passes += 1;
+            self.known_labels = mem::take(&mut self.labels);
+            self.known_outline = mem::take(&mut self.outline);
+            self.known_citations = mem::take(&mut self.citations);
+            self.errors.truncate(first_error);
+            self.sources = SourceMap::new();
+            self.equations = 0;
+            self.sections.clear();
+            self.figures = 0;
+            self.target = None;
+            self.unresolved.clear();
+            self.contents_options = ContentsOptions::default();
+            self.contents_at_start = false;
+            self.number_headings = true;
+            self.entries.clear();
+            self.citation_style = Box::new(Chicago);
+            self.has_references = false;
+        }
+    }
+
+    /// Reports the first label, by name, whose target was still changing when evaluation gave up.
+    fn unsettled_error(&mut self) {
+        let names: BTreeSet<&String> = self.labels.keys().chain(self.known_labels.keys()).collect();
+        let changed = names.into_iter()
+            .find(|&name| self.labels.get(name) != self.known_labels.get(name))
+            .and_then(|name| self.labels.get(name).or_else(|| self.known_labels.get(name)).map(|label| (name, label)));
+        if let Some((name, label)) = changed {
+            let message = format!("label `{}` still changed after {} passes; does it refer to itself?", name, MAX_PASSES);
+            let err = EvalError::new(message, &label.location).with_code(Code::BadLabel);
+            self.error(err);
+        }
+    }
+
+    fn evaluate_pass(&mut self, doc: &ast::Document) -> Document {
+        // The root document only knows its name through the locations lowered from it.
+        self.files = doc.paragraphs.first()
+            .and_then(|paragraph| paragraph.location.file.as_ref())
+            .and_then(|file| fs::canonicalize(file).ok())
+            .into_iter()
+            .collect();
+        self.header = DocumentMeta::header(doc).map(|expr| expr.location.clone());
+        let meta = match DocumentMeta::from_document(doc) {
+            Ok(meta) => meta,
+            Err(errors) => {
+                self.errors.extend(errors.into_iter().map(EvalError::from));
+                DocumentMeta::default()
+            }
+        };
+        let mut blocks = Vec::new();
+        self.frames.push(Frame { region: Region::Document, scope: Scope::new(None) });
+        for paragraph in &doc.paragraphs {
+            let nodes = self.evaluate_inlines(&paragraph.items);
+            push_blocks(nodes, &mut blocks);
+        }
+        self.frames.pop();
+        if self.contents_at_start {
+            let options = self.contents_options;
+            blocks.insert(0, self.contents(options));
+        }
+        Document { meta, blocks, hyphenations: mem::take(&mut self.hyphenations) }
+    }
+
+    /// Evaluates the document loaded from the canonical path `path` by the `include` at `site` into blocks to splice
+    /// into the current one.
+    pub fn evaluate_included(&mut self, path: PathBuf, site: &Location, doc: &ast::Document) -> Vec<Node> {
+        let mut blocks = Vec::new();
+        let file = doc.paragraphs.first().and_then(|paragraph| paragraph.location.file.clone());
+        let scope = self.scope_of(site);
+        self.files.push(path);
+        self.frames.push(Frame { region: Region::File(file), scope });
+        for paragraph in &doc.paragraphs {
+            let nodes = self.evaluate_inlines(&paragraph.items);
+            push_blocks(nodes, &mut blocks);
+        }
+        self.frames.pop();
+        self.files.pop();
+        blocks
+    }
+
+    pub fn evaluate_inlines(&mut self, items: &[Inline]) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        for item in items {
+            match *item {
+                Inline::Text(ref text) => nodes.push(Node::Text(text.value.clone())),
+                Inline::Space => nodes.push(Node::Space),
+                Inline::LineBreak => nodes.push(Node::LineBreak),
+                Inline::Math(ref math) => nodes.extend(self.evaluate_math(math)),
+                Inline::Expr(ref expr) => nodes.extend(self.evaluate_expr(expr))
+            }
+        }
+        nodes
+    }
+
+    /// Evaluates an argument or property as content.
+    pub fn evaluate_value(&mut self, value: &Value) -> Vec<Node> {
+        match *value {
+            Value::Ident(ref ident) => vec![Node::Text(ident.name.clone())],
+            Value::String(ref string) => Node::text(&string.value),
+            Value::Number(ref number) => vec![Node::Text(number.source.clone())],
+            Value::Expr(ref expr) => self.evaluate_expr(expr),
+            Value::Math(ref math) => self.evaluate_math(math)
+        }
+    }
+
+    /// Interprets `math`, evaluating any expressions inside it as text. Display math becomes an unnumbered equation.
+    pub fn evaluate_math(&mut self, math: &ast::Math) -> Vec<Node> {
+        match ::math::parse(math, &mut |expr| Node::plain_text(&self.evaluate_expr(expr))) {
+            Ok(formula) if math.display => vec![Node::Equation { formula, number: None, labels: Vec::new() }],
+            Ok(formula) => vec![Node::Math(formula)],
+            Err(message) => {
+                self.error(EvalError::new(message, &math.location).with_code(Code::BadMath));
+                Vec::new()
+            }
+        }
+    }
+
+    pub fn evaluate_expr(&mut self, expr: &Expr) -> Vec<Node> {
+        let name = match expr.name {
+            Some(ref ident) => ident.name.as_str(),
+            None => {
+                self.error(EvalError::new("expression has no command name", &expr.location).with_code(Code::UnknownCommand));
+                return Vec::new();
+            }
+        };
+        if let Some(mac) = self.scope_of(&expr.location).lookup(name) {
+            return self.expand(&mac, expr);
+        }
+        let registry = self.registry;
+        let command = match registry.get(name) {
+            Some(command) => command,
+            None => {
+                self.error(EvalError::new(format!("unknown command `{}`", name), &expr.location).with_code(Code::UnknownCommand));
+                return Vec::new();
+            }
+        };
+        if let Err(errors) = command.signature().check(name, expr) {
+            self.errors.extend(errors);
+            return Vec::new();
+        }
+        match command.evaluate(&Call::new(expr), self) {
+            Ok(nodes) => nodes,
+            Err(err) => {
+                self.error(err);
+                Vec::new()
+            }
+        }
+    }
+}
+
+impl <'r> Evaluator<'r> {
+    fn expand(&mut self, mac: &Macro, expr: &Expr) -> Vec<Node> {
+        let defined_here = format!("`{}` is defined here", mac.name);
+        if let Some(prop) = expr.props.first() {
+            self.error(EvalError::new(format!("`{}` does not take a `:{}` property", mac.name, prop.key.name), &prop.location)
+                .with_code(Code::BadArguments)
+                .with_related(&mac.location, defined_here));
+            return Vec::new();
+        }
+        if expr.args.len() != mac.params.len() {
+            let plural = if mac.params.len() == 1 { "" } else { "s" };
+            self.error(EvalError::new(
+                    format!("`{}` expects {} positional argument{}, got {}", mac.name, mac.params.len(), plural, expr.args.len()),
+                    &expr.location)
+                .with_code(Code::BadArguments)
+                .with_related(&mac.location, defined_here));
+            return Vec::new();
+        }
+        if self.depth == MAX_MACRO_DEPTH {
+            self.error(EvalError::new(
+                    format!("`{}` expanded more than {} levels deep; is it infinitely recursive?", mac.name, MAX_MACRO_DEPTH),
+                    &expr.location)
+                .with_code(Code::BadMacro)
+                .with_related(&mac.location, defined_here));
+            return Vec::new();
+        }
+
+        let first_error = self.errors.len();
+        self.depth += 1;
+        let defined_in = mac.scope.upgrade().expect("a macro outlived the scope defining it");
+        self.frames.push(Frame { region: Region::Definition(mac.location.clone()), scope: Scope::new(Some(defined_in)) });
+        let mut nodes = Vec::new();
+        for (i, value) in mac.substitute(&expr.args).iter().enumerate() {
+            if mac.space_before(i) {
+                nodes.push(Node::Space);
+            }
+            nodes.extend(self.evaluate_value(value));
+        }
+        self.frames.pop();
+        self.depth -= 1;
+
+        // Errors from inside the body point at the definition, so also say which use triggered them.
+        self.note_errors_since(first_error, &expr.location, format!("in this use of `{}`", mac.name));
+        nodes
+    }
+}
+
+/// Appends `nodes` to `blocks`, wrapping each run of inline content between block nodes in a paragraph.
+///
+/// Each anchor becomes a label of the last heading, figure or numbered equation in `blocks`, which is what the
+/// evaluator attached it to, so backends can put it on what it labels rather than wherever it was written.
+pub fn push_blocks(nodes: Vec<Node>, blocks: &mut Vec<Node>) {
+    let mut run = Vec::new();
+    for node in nodes {
+        match node {
+            Node::Anchor(name) => match blocks.iter_mut().rev().find_map(target_labels) {
+                Some(labels) => labels.push(name),
+                None => run.push(Node::Anchor(name))
+            },
+            node if node.is_block() => {
+                push_paragraph(&mut run, blocks);
+                blocks.push(node);
+            },
+            node => run.push(node)
+        }
+    }
+    push_paragraph(&mut run, blocks);
+}
+
+fn push_paragraph(run: &mut Vec<Node>, blocks: &mut Vec<Node>) {
+    let is_blank = |node: &Node| *node == Node::Space || *node == Node::LineBreak;
+    while run.last().map(is_blank).unwrap_or(false) {
+        run.pop();
+    }
+    let start = run.iter().take_while(|node| is_blank(node)).count();
+    run.drain(..start);
+    if !run.is_empty() {
+        blocks.push(Node::Paragraph(mem::take(run)));
+    }
+}
+
+/// The labels of `node` if it is a heading, figure or numbered equation.
+fn target_labels(node: &mut Node) -> Option<&mut Vec<String>> {
+    match *node {
+        Node::Heading { ref mut labels, .. } | Node::Figure { ref mut labels, .. } => Some(labels),
+        Node::Equation { ref mut labels, number: Some(_), .. } => Some(labels),
+        _ => None
+    }
+}
+
+/// A document, included file or macro expansion in progress, and the scope the code written in it is evaluated in.
+struct Frame {
+    region: Region,
+    scope: Rc<Scope>
+}
+
+/// The source a `Frame` evaluates.
+enum Region {
+    Document,
+    /// An included file, by the name its locations carry.
+    File(Option<String>),
+    /// The `{define ...}` of the macro being expanded.
+    Definition(Location)
+}
+
+impl Region {
+    fn contains(&self, location: &Location) -> bool {
+        match *self {
+            Region::Document => true,
+            Region::File(ref file) => *file == location.file,
+            Region::Definition(ref definition) => definition.contains(location)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::eval::*;
+    use ::lex::Lexer;
+    use ::parse::parse;
+
+    fn evaluate(source: &str, registry: &CommandRegistry) -> Result<Document, Vec<String>> {
+        let doc = ::ast::lower(&parse(Lexer::new(source)).unwrap());
+        ::eval::evaluate(&doc, registry)
+            .map_err(|errors| errors.iter().map(|err| err.to_string()).collect())
+    }
+
+    #[test]
+    fn headings_split_paragraphs() {
+        let doc = evaluate("{litx :title \"T\"}\n\n{h1 \"An intro\"}\nSome {emph \"text\"}.", &CommandRegistry::with_builtins()).unwrap();
+        assert_eq!(doc.meta.title, Some("T".to_string()));
+        assert_eq!(doc.blocks, vec![
+            Node::Heading {
+                level: 1,
+                number: Some("1".to_string()),
+                content: vec![Node::Text("An".to_string()), Node::Space, Node::Text("intro".to_string())],
+                labels: Vec::new(),
+                listed: true
+            },
+            Node::Paragraph(vec![
+                Node::Text("Some".to_string()),
+                Node::Space,
+                Node::Emph(vec![Node::Text("text".to_string())]),
+                Node::Text(".".to_string())
+            ])
+        ]);
+    }
+
+    #[test]
+    fn signature_errors() {
+        let errors = evaluate("{h1 \"a\" \"b\"} {frobnicate} {emph :x 1 y} {list {h2}}\n\n{litx}", &CommandRegistry::with_builtins()).unwrap_err();
+        assert_eq!(errors, vec![
+            "<input>:1:1: `h1` expects 1 positional argument, got 2",
+            "<input>:1:14: unknown command `frobnicate`",
+            "<input>:1:33: `emph` does not take a `:x` property",
+            "<input>:1:47: `h2` expects 1 positional argument, got 0",
+            "<input>:3:1: the `litx` header must come first in the document"
+        ]);
+    }
+
+    #[test]
+    fn collects_hyphenations() {
+        let doc = evaluate("{hyphenate \"ta-ble\" data-base} A table.", &CommandRegistry::with_builtins()).unwrap();
+        assert_eq!(doc.hyphenations, vec!["ta-ble", "data-base"]);
+        let errors = evaluate("{hyphenate \"ta--ble\" \"two words\"}", &CommandRegistry::with_builtins()).unwrap_err();
+        assert_eq!(errors, vec![
+            "<input>:1:12: `ta--ble` is not a word with hyphens between its letters",
+            "<input>:1:22: `two words` is not a word with hyphens between its letters"
+        ]);
+    }
+
+    #[test]
+    fn evaluates_math() {
+        use ::math::MathNode;
+        let doc = evaluate("{$ x_{emph \"n\"} $}", &CommandRegistry::with_builtins()).unwrap();
+        match doc.blocks[0] {
+            Node::Paragraph(ref content) => assert_eq!(content[0], Node::Math(::math::Formula {
+                nodes: vec![MathNode::Scripts {
+                    base: Box::new(MathNode::Identifier("x".to_string())),
+                    subscript: Some(Box::new(MathNode::Text("n".to_string()))),
+                    superscript: None
+                }],
+                source: "x_{emph \"n\"}".to_string()
+            })),
+            ref block => panic!("expected a paragraph, got {:?}", block)
+        }
+        let doc = evaluate("One {$$ a $$} two {equation {$ b $}} {equation {$$ c $$}}", &CommandRegistry::with_builtins()).unwrap();
+        let numbers: Vec<Option<Option<usize>>> = doc.blocks.iter()
+            .map(|block| match *block {
+                Node::Equation { number, .. } => Some(number),
+                _ => None
+            })
+            .collect();
+        assert_eq!(numbers, vec![None, Some(None), None, Some(Some(1)), Some(Some(2))]);
+        let errors = evaluate("Some {$ \\beta + \\gama $}.", &CommandRegistry::with_builtins()).unwrap_err();
+        assert_eq!(errors, vec!["<input>:1:6: unknown math command `\\gama`"]);
+    }
+
+    #[test]
+    fn resolves_labels() {
+        let source = "See {ref intro :show title} ({ref intro}), {ref sum}, {ref fig} and {ref \"part\"} on {ref part :show title}.\n\n\
+            {h1 \"Start\"} {h1 \"Intro\"}{label intro} {h2 {ref fig}}{label part}\n\n\
+            {equation {$ a + b $}} {label sum} {figure \"x\" :caption \"A cat\"}{label fig}";
+        let doc = evaluate(source, &CommandRegistry::with_builtins()).unwrap();
+        assert_eq!(Node::plain_text(&doc.blocks[..1]), "See Intro (2), 1, 1 and 2.1 on 1.");
+        assert_eq!(doc.blocks[2], Node::Heading {
+            level: 1,
+            number: Some("2".to_string()),
+            content: Node::text("Intro"),
+            labels: vec!["intro".to_string()],
+            listed: true
+        });
+        assert_eq!(doc.blocks[3], Node::Heading {
+            level: 2,
+            number: Some("2.1".to_string()),
+            content: vec![Node::Ref { label: "fig".to_string(), number: "1".to_string() }],
+            labels: vec!["part".to_string()],
+            listed: true
+        });
+        assert_eq!(doc.blocks.len(), 6);
+
+        let errors = evaluate("{label a} {h1 \"A\"}{label a} {label a} {pageref b} {ref a :show page}", &CommandRegistry::with_builtins()).unwrap_err();
+        assert_eq!(errors, vec![
+            "<input>:1:1: label `a` has no heading, figure or numbered equation before it",
+            "<input>:1:29: label `a` is already defined\n<input>:1:19: note: previously defined here",
+            "<input>:1:64: `:show` must be `number` or `title`, not `page`",
+            "<input>:1:39: undefined label `b`"
+        ]);
+    }
+
+    #[test]
+    fn skipped_heading_levels_start_at_one() {
+        let doc = evaluate("{h3 \"A\"}{label a} {h1 \"B\"} {h3 \"C\"}{label c} {h2 \"D\"}{label d}\n\n{ref a}, {ref c}, {ref d}",
+            &CommandRegistry::with_builtins()).unwrap();
+        assert_eq!(Node::plain_text(&doc.blocks[doc.blocks.len() - 1..]), "1.1.1, 2.1.1, 2.2");
+    }
+
+    #[test]
+    fn makes_tables_of_contents() {
+        let source = "{litx.meta :table-of-contents start :toc-numbers false}\n\n\
+            {h1 \"One\"} {h2 {emph \"Two\"}} {h3 \"Three\"} {h1 \"Four\"} {toc :depth 1 :numbers true :pages false}";
+        let doc = evaluate(source, &CommandRegistry::with_builtins()).unwrap();
+        let entry = |level, number: Option<&str>, title: Vec<Node>, heading| ContentsEntry {
+            level,
+            number: number.map(|number| number.to_string()),
+            title,
+            heading
+        };
+        assert_eq!(doc.blocks[0], Node::Contents {
+            entries: vec![
+                entry(1, None, Node::text("One"), 0),
+                entry(2, None, vec![Node::Emph(Node::text("Two"))], 1),
+                entry(3, None, Node::text("Three"), 2),
+                entry(1, None, Node::text("Four"), 3)
+            ],
+            pages: true
+        });
+        assert_eq!(doc.blocks[5], Node::Contents {
+            entries: vec![entry(1, Some("1"), Node::text("One"), 0), entry(1, Some("2"), Node::text("Four"), 3)],
+            pages: false
+        });
+
+        let errors = evaluate("{litx.meta :table-of-contents end :toc-depth 7} {toc :pages no}", &CommandRegistry::with_builtins()).unwrap_err();
+        assert_eq!(errors, vec![
+            "<input>:1:31: `:table-of-contents` must be `start` or `none`, not `end`",
+            "<input>:1:61: expected `true` or `false`"
+        ]);
+    }
+
+    #[test]
+    fn headings_can_be_unnumbered() {
+        let source = "{h1 \"One\"} {litx.meta :number-headings false} {h1 \"Two\"}{label two} See {ref two}. {toc :pages false}";
+        let doc = evaluate(source, &CommandRegistry::with_builtins()).unwrap();
+        assert_eq!(doc.blocks[1], Node::Heading {
+            level: 1,
+            number: None,
+            content: Node::text("Two"),
+            labels: vec!["two".to_string()],
+            listed: true
+        });
+        assert_eq!(Node::plain_text(&doc.blocks[2..3]), "See Two.");
+        let numbers = match doc.blocks[3] {
+            Node::Contents { ref entries, .. } => entries.iter().map(|entry| entry.number.clone()).collect(),
+            _ => Vec::new()
+        };
+        assert_eq!(numbers, vec![Some("1".to_string()), None]);
+    }
+
+    struct Shout;
+
+    impl Command for Shout {
+        fn name(&self) -> &str {
+            "shout"
+        }
+
+        fn signature(&self) -> Signature {
+            Signature::new().with_positional(ArgType::Text)
+        }
+
+        fn evaluate(&self, call: &Call, _: &mut Evaluator) -> Result<Vec<Node>, EvalError> {
+            match *call.get_arg(0).unwrap() {
+                ::ast::Value::String(ref string) => Ok(vec![Node::Strong(Node::text(&string.value.to_uppercase()))]),
+                _ => Err(EvalError::new("`shout` needs a quoted string", call.get_location()))
+            }
+        }
+    }
+
+    #[test]
+    fn user_defined_command() {
+        let mut registry = CommandRegistry::new();
+        registry.register(Box::new(Shout));
+        let doc = evaluate("{shout \"hi\"} {shout 3}", &registry);
+        assert_eq!(doc.unwrap_err(), vec!["<input>:1:21: `shout` expects argument 1 to be a string, got a number"]);
+        let doc = evaluate("{shout \"hi\"}", &registry).unwrap();
+        assert_eq!(doc.blocks, vec![Node::Paragraph(vec![Node::Strong(vec![Node::Text("HI".to_string())])])]);
+    }
+}
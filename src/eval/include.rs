@@ -0,0 +1,155 @@
+use ::ast::Value;
+use ::diagnostics::Code;
+use ::lex::Lexer;
+use ::parse::parse;
+use std::fs;
+use std::path::Path;
+use super::ArgType;
+use super::Call;
+use super::Command;
+use super::EvalError;
+use super::Evaluator;
+use super::Node;
+use super::Signature;
+
+/// `{include "chapter1.litx"}`: splices the blocks of another file into the document.
+///
+/// The path is relative to the file containing the `include`, or to the working directory if that file has no name.
+/// Tokens in the included file carry its own name, so errors inside it point at the right place. Macros it defines at
+/// its top level are defined for the rest of the file including it too.
+pub struct Include;
+
+impl Command for Include {
+    fn name(&self) -> &str {
+        "include"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new().with_positional(ArgType::Text)
+    }
+
+    fn evaluate(&self, call: &Call, eval: &mut Evaluator) -> Result<Vec<Node>, EvalError> {
+        let target = match *call.get_arg(0).unwrap() {
+            Value::String(ref string) => string.value.as_str(),
+            Value::Ident(ref ident) => ident.name.as_str(),
+            _ => unreachable!()
+        };
+        let site = call.get_location();
+        let base = site.file.as_ref()
+            .and_then(|file| Path::new(file).parent())
+            .unwrap_or_else(|| Path::new(""));
+        let path = base.join(target);
+        let filename = path.to_string_lossy().into_owned();
+
+        let source = fs::read_to_string(&path)
+            .map_err(|err| EvalError::new(format!("cannot include `{}`: {}", filename, err), site).with_code(Code::BadInclude))?;
+        let canonical = fs::canonicalize(&path)
+            .map_err(|err| EvalError::new(format!("cannot include `{}`: {}", filename, err), site).with_code(Code::BadInclude))?;
+        if eval.is_including(&canonical) {
+            return Err(EvalError::new(format!("`{}` is already being included; including it again would never end", filename), site)
+                .with_code(Code::BadInclude));
+        }
+
+        eval.add_source(&filename, &source);
+        let production = match parse(Lexer::new_with_filename(&source, &filename)) {
+            Ok(production) => production,
+            Err(errors) => {
+                for err in errors {
+                    eval.error(EvalError::from(err).with_related(site, "in the file included here"));
+                }
+                return Ok(Vec::new());
+            }
+        };
+        let doc = ::ast::lower(&production);
+        let first_error = eval.error_count();
+        let blocks = eval.evaluate_included(canonical, site, &doc);
+        eval.note_errors_since(first_error, site, "in the file included here");
+        Ok(blocks)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::eval::*;
+    use ::lex::Lexer;
+    use ::parse::parse;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Writes `files` into a fresh directory and evaluates the first of them.
+    fn evaluate_files(name: &str, files: &[(&str, &str)]) -> Result<Document, Vec<String>> {
+        let dir = ::std::env::temp_dir().join(format!("litx-include-{}-{}", name, ::std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for &(file, source) in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, source).unwrap();
+        }
+        let root: PathBuf = dir.join(files[0].0);
+        let filename = root.to_string_lossy().into_owned();
+        let source = fs::read_to_string(&root).unwrap();
+        let doc = ::ast::lower(&parse(Lexer::new_with_filename(&source, &filename)).unwrap());
+        let result = ::eval::evaluate(&doc, &CommandRegistry::with_builtins())
+            .map_err(|errors| errors.iter()
+                .map(|err| err.to_string().replace(&format!("{}/", dir.display()), ""))
+                .collect());
+        fs::remove_dir_all(&dir).unwrap();
+        result
+    }
+
+    #[test]
+    fn splices_blocks() {
+        let doc = evaluate_files("splice", &[
+            ("book.litx", "{define who \"world\"}\nBefore {include \"chapters/one.litx\"} after."),
+            ("chapters/one.litx", "{h1 \"One\"}\n\nHello {who}.")
+        ]).unwrap();
+        assert_eq!(doc.blocks, vec![
+            Node::Paragraph(vec![Node::Text("Before".to_string())]),
+            Node::Heading { level: 1, number: Some("1".to_string()), content: vec![Node::Text("One".to_string())], labels: Vec::new(), listed: true },
+            Node::Paragraph(vec![Node::Text("Hello".to_string()), Node::Space, Node::Text("world".to_string()), Node::Text(".".to_string())]),
+            Node::Paragraph(vec![Node::Text("after.".to_string())])
+        ]);
+    }
+
+    #[test]
+    fn included_definitions_reach_the_includer() {
+        let doc = evaluate_files("macros", &[
+            ("book.litx", "{include \"macros.litx\"}\nHello {who}."),
+            ("macros.litx", "{define who \"world\"}")
+        ]).unwrap();
+        assert_eq!(Node::plain_text(&doc.blocks), "Hello world.");
+        let errors = evaluate_files("clash", &[
+            ("book.litx", "{define who \"you\"}\n{include \"macros.litx\"}"),
+            ("macros.litx", "{define who \"world\"}")
+        ]).unwrap_err();
+        assert_eq!(errors, vec![
+            "macros.litx:1:1: `who` is already defined\nbook.litx:1:1: note: previously defined here\n\
+             book.litx:2:1: note: in the file included here"
+        ]);
+    }
+
+    #[test]
+    fn errors_point_into_included_file() {
+        let errors = evaluate_files("errors", &[
+            ("book.litx", "{include \"missing.litx\"}\n{include \"a.litx\"}"),
+            ("a.litx", "{include \"b.litx\"}"),
+            ("b.litx", "{include \"a.litx\"} {h1}\n{emph \"x\"")
+        ]).unwrap_err();
+        assert_eq!(errors, vec![
+            "book.litx:1:1: cannot include `missing.litx`: No such file or directory (os error 2)",
+            "b.litx:2:10: expected `}` to close expression opened at line 2, found end of file\n\
+             a.litx:1:1: note: in the file included here\n\
+             book.litx:2:1: note: in the file included here"
+        ]);
+        let errors = evaluate_files("cycle", &[
+            ("book.litx", "{include \"a.litx\"}"),
+            ("a.litx", "{include \"book.litx\"} {h1}")
+        ]).unwrap_err();
+        assert_eq!(errors, vec![
+            "a.litx:1:1: `book.litx` is already being included; including it again would never end\n\
+             book.litx:1:1: note: in the file included here",
+            "a.litx:1:23: `h1` expects 1 positional argument, got 0\n\
+             book.litx:1:1: note: in the file included here"
+        ]);
+    }
+}
@@ -0,0 +1,59 @@
+use ::ast::Location;
+use std::fmt;
+
+/// The most passes over a document spent waiting for its labels to settle.
+pub const MAX_PASSES: usize = 5;
+
+/// What a label can be attached to.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub enum TargetKind {
+    Section,
+    Figure,
+    Equation
+}
+
+/// The word for the kind of target, as in `Figure 2`.
+impl fmt::Display for TargetKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let word = match *self {
+            TargetKind::Section => "Section",
+            TargetKind::Figure => "Figure",
+            TargetKind::Equation => "Equation"
+        };
+        write!(f, "{}", word)
+    }
+}
+
+/// A heading, figure or numbered equation, as `{ref ...}` sees it.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Target {
+    pub kind: TargetKind,
+    /// The number as it is printed, such as `2.1` for the first `h2` in the second `h1`. Unnumbered headings have none.
+    pub number: Option<String>,
+    /// The text of a heading or the caption of a figure. Equations have none.
+    pub title: Option<String>
+}
+
+impl Target {
+    /// The title, or the kind and number for an equation or a figure without a caption, such as `Equation 2`.
+    pub fn name(&self) -> String {
+        match (self.title.as_ref(), self.number.as_ref()) {
+            (Some(title), _) if !title.is_empty() => title.clone(),
+            (_, Some(number)) => format!("{} {}", self.kind, number),
+            _ => self.kind.to_string()
+        }
+    }
+}
+
+/// A `{label ...}` and the target it attached to.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Label {
+    pub target: Target,
+    pub location: Location
+}
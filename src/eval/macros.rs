@@ -0,0 +1,235 @@
+use ::ast::Expr;
+use ::ast::Location;
+use ::ast::MathTerm;
+use ::ast::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::rc::Weak;
+
+/// How deeply macros may expand inside one another before evaluation gives up.
+pub const MAX_MACRO_DEPTH: usize = 64;
+
+/// A macro made by `{define name :params {list a b} body ...}`.
+///
+/// Expanding it replaces each parameter in the body with the matching argument from the call site.
+/// A parameter is referenced by its bare name in expression arguments, as `{name}` anywhere an expression may go,
+/// or as a whole term inside math. String literals are left untouched.
+///
+/// The body is evaluated in the scope the macro was defined in, which `Evaluator::define` records in `scope`,
+/// so the macros it uses are the ones visible where it was written rather than where it is used.
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct Macro {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Vec<Value>,
+    pub location: Location,
+    pub scope: Weak<Scope>
+}
+
+impl Macro {
+    pub fn substitute(&self, args: &[Value]) -> Vec<Value> {
+        let bindings: HashMap<&str, &Value> = self.params.iter()
+            .map(String::as_str)
+            .zip(args)
+            .collect();
+        self.body.iter()
+            .map(|value| substitute_value(value, &bindings))
+            .collect()
+    }
+
+    /// Whether the body has whitespace before its value `i`, which its expansion keeps as a space.
+    pub fn space_before(&self, i: usize) -> bool {
+        i > 0 && self.body[i - 1].location().end < self.body[i].location().index
+    }
+}
+
+/// The macros defined in a document, with the files it includes, or in a macro expansion, nested in the scope around it.
+///
+/// A macro refers to the scope it is defined in only weakly: it is stored there, so the scope outlives any use of it.
+#[derive(Debug)]
+pub struct Scope {
+    macros: RefCell<HashMap<String, Rc<Macro>>>,
+    parent: Option<Rc<Scope>>
+}
+
+impl Scope {
+    pub fn new(parent: Option<Rc<Scope>>) -> Rc<Scope> {
+        Rc::new(Scope { macros: RefCell::new(HashMap::new()), parent })
+    }
+
+    /// The macro called `name` in this scope or the nearest scope around it which has one.
+    pub fn lookup(&self, name: &str) -> Option<Rc<Macro>> {
+        match self.macros.borrow().get(name) {
+            Some(mac) => Some(mac.clone()),
+            None => self.parent.as_ref().and_then(|parent| parent.lookup(name))
+        }
+    }
+
+    /// The macro called `name` defined in this scope itself.
+    pub fn get(&self, name: &str) -> Option<Rc<Macro>> {
+        self.macros.borrow().get(name).cloned()
+    }
+
+    pub fn insert(&self, mac: Macro) {
+        self.macros.borrow_mut().insert(mac.name.clone(), Rc::new(mac));
+    }
+}
+
+fn substitute_value(value: &Value, bindings: &HashMap<&str, &Value>) -> Value {
+    match *value {
+        Value::Ident(ref ident) => match bindings.get(ident.name.as_str()) {
+            Some(&bound) => bound.clone(),
+            None => value.clone()
+        },
+        Value::Expr(ref expr) => match parameter_reference(expr).and_then(|name| bindings.get(name)) {
+            Some(&bound) => bound.clone(),
+            None => Value::Expr(substitute_expr(expr, bindings))
+        },
+        Value::Math(ref math) => {
+            let mut math = math.clone();
+            math.terms = math.terms.iter()
+                .map(|term| substitute_math_term(term, bindings))
+                .collect();
+            math.update_source();
+            Value::Math(math)
+        },
+        Value::String(_) | Value::Number(_) => value.clone()
+    }
+}
+
+fn substitute_expr(expr: &Expr, bindings: &HashMap<&str, &Value>) -> Expr {
+    let mut expr = expr.clone();
+    for arg in &mut expr.args {
+        *arg = substitute_value(arg, bindings);
+    }
+    for prop in &mut expr.props {
+        prop.value = substitute_value(&prop.value, bindings);
+    }
+    expr
+}
+
+fn substitute_math_term(term: &MathTerm, bindings: &HashMap<&str, &Value>) -> MathTerm {
+    let bound = match *term {
+        MathTerm::Text(ref text) => bindings.get(text.as_str()),
+        MathTerm::Expr(ref expr) => parameter_reference(expr).and_then(|name| bindings.get(name)),
+        _ => None
+    };
+    match bound.copied() {
+        Some(Value::Ident(ident)) => MathTerm::Text(ident.name.clone()),
+        Some(Value::String(string)) => MathTerm::Text(string.value.clone()),
+        Some(Value::Number(number)) => MathTerm::Text(number.source.clone()),
+        Some(Value::Expr(expr)) => MathTerm::Expr(expr.clone()),
+        Some(Value::Math(math)) => MathTerm::Math(math.clone()),
+        None => match *term {
+            MathTerm::Expr(ref expr) => MathTerm::Expr(substitute_expr(expr, bindings)),
+            MathTerm::Math(ref math) => match substitute_value(&Value::Math(math.clone()), bindings) {
+                Value::Math(math) => MathTerm::Math(math),
+                _ => unreachable!()
+            },
+            _ => term.clone()
+        }
+    }
+}
+
+/// The parameter named by a bare `{name}` expression.
+fn parameter_reference(expr: &Expr) -> Option<&str> {
+    if expr.args.is_empty() && expr.props.is_empty() {
+        expr.name.as_ref().map(|ident| ident.name.as_str())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::eval::CommandRegistry;
+    use ::eval::Node;
+    use ::lex::Lexer;
+    use ::parse::parse;
+
+    fn evaluate(source: &str) -> Result<Vec<Node>, Vec<String>> {
+        let doc = ::ast::lower(&parse(Lexer::new(source)).unwrap());
+        ::eval::evaluate(&doc, &CommandRegistry::with_builtins())
+            .map(|doc| doc.blocks)
+            .map_err(|errors| errors.iter().map(|err| err.to_string()).collect())
+    }
+
+    fn text(word: &str) -> Node {
+        Node::Text(word.to_string())
+    }
+
+    #[test]
+    fn substitutes_parameters() {
+        let blocks = evaluate("{define greet :params {list who} {emph who} says hi}\n{greet \"Ada\"}, {greet {strong x}}.").unwrap();
+        assert_eq!(blocks, vec![Node::Paragraph(vec![
+            Node::Emph(vec![text("Ada")]), Node::Space, text("says"), Node::Space, text("hi"),
+            text(","),
+            Node::Space,
+            Node::Emph(vec![Node::Strong(vec![text("x")])]), Node::Space, text("says"), Node::Space, text("hi"),
+            text(".")
+        ])]);
+    }
+
+    #[test]
+    fn keeps_the_spacing_of_the_body() {
+        let blocks = evaluate("{define stress :params {list w} {emph w}\".\" {strong w}}{stress a}").unwrap();
+        assert_eq!(blocks, vec![Node::Paragraph(vec![
+            Node::Emph(vec![text("a")]), text("."), Node::Space, Node::Strong(vec![text("a")])
+        ])]);
+    }
+
+    #[test]
+    fn substitutes_into_math() {
+        match evaluate("{define sq :params {list x} {$ {x}^2 + x $}}{sq 3}").unwrap()[0] {
+            Node::Paragraph(ref nodes) => match nodes[0] {
+                Node::Math(ref math) => assert_eq!(math.source, "3^2 + 3"),
+                _ => panic!()
+            },
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn definitions_are_scoped() {
+        let errors = evaluate("{define outer {define inner x} {inner}}\n{outer} {inner}").unwrap_err();
+        assert_eq!(errors, vec!["<input>:2:9: unknown command `inner`"]);
+    }
+
+    #[test]
+    fn bodies_see_the_macros_around_their_definition() {
+        let blocks = evaluate("{define a lexical} {define b {a}} {define c {define a dynamic} {b}} {c}").unwrap();
+        assert_eq!(blocks, vec![Node::Paragraph(vec![text("lexical")])]);
+        let blocks = evaluate("{define f :params {list x} {g}} {define g x} {f 5}").unwrap();
+        assert_eq!(blocks, vec![Node::Paragraph(vec![text("x")])]);
+    }
+
+    #[test]
+    fn arguments_see_the_macros_around_their_use() {
+        let blocks = evaluate("{define show :params {list what} {emph what}}\n\
+            {define outer {define inner x} {show {inner}}}\n{outer}").unwrap();
+        assert_eq!(blocks, vec![Node::Paragraph(vec![Node::Emph(vec![text("x")])])]);
+    }
+
+    #[test]
+    fn errors_point_at_call_and_definition() {
+        let errors = evaluate("{define two :params {list a b} {h1 a b}}\n\n{two x}\n\n{two x y}\n\n{define two}").unwrap_err();
+        assert_eq!(errors, vec![
+            "<input>:3:1: `two` expects 2 positional arguments, got 1\n<input>:1:1: note: `two` is defined here",
+            "<input>:1:32: `h1` expects 1 positional argument, got 2\n<input>:5:1: note: in this use of `two`",
+            "<input>:7:1: `two` is already defined\n<input>:1:1: note: previously defined here"
+        ]);
+    }
+
+    #[test]
+    fn recursion_is_limited() {
+        let errors = evaluate("{define loop {loop}}\n{loop}").unwrap_err();
+        assert_eq!(errors, vec![
+            "<input>:1:14: `loop` expanded more than 64 levels deep; is it infinitely recursive?\n\
+             <input>:1:1: note: `loop` is defined here\n\
+             <input>:1:14: note: in this use of `loop`\n\
+             <input>:2:1: note: in this use of `loop`"
+        ]);
+    }
+}
@@ -0,0 +1,49 @@
+mod builtins;
+mod citations;
+mod command;
+mod command_registry;
+mod contents;
+mod document;
+mod eval_error;
+mod evaluator;
+mod include;
+mod labels;
+mod macros;
+mod node;
+mod signature;
+
+use ::source::SourceMap;
+pub use self::command::Call;
+pub use self::command::Command;
+pub use self::command_registry::CommandRegistry;
+pub use self::contents::ContentsEntry;
+pub use self::contents::ContentsOptions;
+pub use self::document::Document;
+pub use self::eval_error::EvalError;
+pub use self::evaluator::Evaluator;
+pub use self::labels::Target;
+pub use self::labels::TargetKind;
+pub use self::macros::Macro;
+pub use self::macros::Scope;
+pub use self::node::Node;
+pub use self::signature::ArgType;
+pub use self::signature::Signature;
+
+/// Evaluates every expression in `doc` against `registry`, producing backend-neutral content.
+/// Evaluation carries on past errors, so all of them are returned together.
+pub fn evaluate(doc: &::ast::Document, registry: &CommandRegistry) -> Result<Document, Vec<EvalError>> {
+    evaluate_with_sources(doc, registry, &mut SourceMap::new())
+}
+
+/// Like `evaluate`, but also adds every file loaded by `include` to `sources`.
+pub fn evaluate_with_sources(doc: &::ast::Document, registry: &CommandRegistry, sources: &mut SourceMap) -> Result<Document, Vec<EvalError>> {
+    let mut evaluator = Evaluator::new(registry);
+    let document = evaluator.evaluate_document(doc);
+    sources.extend(evaluator.take_sources().into_files());
+    let errors = evaluator.into_errors();
+    if errors.is_empty() {
+        Ok(document)
+    } else {
+        Err(errors)
+    }
+}
@@ -0,0 +1,114 @@
+use ::math::Formula;
+use super::ContentsEntry;
+
+/// Evaluated content, independent of any backend.
+/// Block nodes only appear at the top level of a `Document`; everything else is inline.
+/// Headings, figures and numbered equations carry the names of the `{label ...}`s naming them in `labels`.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum Node {
+    Text(String),
+    Space,
+    LineBreak,
+    Emph(Vec<Node>),
+    Strong(Vec<Node>),
+    Math(Formula),
+    Paragraph(Vec<Node>),
+    /// A heading, with its section number such as `2.1` unless it is unnumbered. Tables of contents list it if `listed`
+    /// is set, which it is for every heading but the title of a list of references.
+    Heading { level: usize, number: Option<String>, content: Vec<Node>, labels: Vec<String>, listed: bool },
+    List(Vec<Vec<Node>>),
+    /// Math displayed on a line of its own, with its number if it has one.
+    Equation { formula: Formula, number: Option<usize>, labels: Vec<String> },
+    /// Content set apart from the text around it, with a numbered caption.
+    Figure { content: Vec<Node>, caption: Vec<Node>, number: usize, labels: Vec<String> },
+    /// Where a `{label ...}` was, when what it labels is not among the blocks before it, for backends which can link or
+    /// refer to a place in the output.
+    Anchor(String),
+    /// The number of what a label names, such as `2.1`, for backends which number it themselves to refer to by `label`.
+    Ref { label: String, number: String },
+    /// The number of the page a label is on. Backends without pages show `title`, the name of its target, instead,
+    /// linked to it where they can link.
+    PageRef { label: String, title: String },
+    /// A table of contents, with the page of each heading if `pages` is set and the backend has pages.
+    Contents { entries: Vec<ContentsEntry>, pages: bool }
+}
+
+impl Node {
+    pub fn is_block(&self) -> bool {
+        matches!(*self,
+            Node::Paragraph(_)
+            | Node::Heading { .. }
+            | Node::List(_)
+            | Node::Equation { .. }
+            | Node::Figure { .. }
+            | Node::Contents { .. })
+    }
+
+    /// A heading's content after its number, if it has one, as the backends which do not number headings themselves
+    /// print it.
+    pub fn numbered(number: Option<&str>, content: &[Node]) -> Vec<Node> {
+        let mut nodes = match number {
+            Some(number) => vec![Node::Text(number.to_string()), Node::Space],
+            None => Vec::new()
+        };
+        nodes.extend(content.iter().cloned());
+        nodes
+    }
+
+    /// Splits running text into words separated by `Space` nodes.
+    pub fn text(text: &str) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        let mut word = String::new();
+        for c in text.chars() {
+            if c.is_whitespace() {
+                if !word.is_empty() {
+                    nodes.push(Node::Text(word.clone()));
+                    word.clear();
+                }
+                if nodes.last() != Some(&Node::Space) {
+                    nodes.push(Node::Space);
+                }
+            } else {
+                word.push(c);
+            }
+        }
+        if !word.is_empty() {
+            nodes.push(Node::Text(word));
+        }
+        nodes
+    }
+
+    /// The text of `nodes` without any styling, with math as its source and blocks run together.
+    pub fn plain_text(nodes: &[Node]) -> String {
+        let mut text = String::new();
+        for node in nodes {
+            match *node {
+                Node::Text(ref word) => text.push_str(word),
+                Node::Space | Node::LineBreak => text.push(' '),
+                Node::Anchor(_) => (),
+                Node::Ref { ref number, .. } => text.push_str(number),
+                Node::PageRef { ref title, .. } => text.push_str(title),
+                Node::Math(ref formula) | Node::Equation { ref formula, .. } => text.push_str(&formula.source),
+                Node::Emph(ref content) | Node::Strong(ref content) | Node::Paragraph(ref content) | Node::Heading { ref content, .. } => {
+                    text.push_str(&Node::plain_text(content));
+                },
+                Node::List(ref items) => {
+                    let items: Vec<String> = items.iter().map(|item| Node::plain_text(item)).collect();
+                    text.push_str(&items.join(" "));
+                },
+                Node::Figure { ref content, ref caption, .. } => {
+                    text.push_str(&Node::plain_text(content));
+                    text.push(' ');
+                    text.push_str(&Node::plain_text(caption));
+                },
+                Node::Contents { ref entries, .. } => {
+                    let titles: Vec<String> = entries.iter().map(|entry| Node::plain_text(&entry.title)).collect();
+                    text.push_str(&titles.join(" "));
+                }
+            }
+        }
+        text
+    }
+}
@@ -0,0 +1,141 @@
+use ::ast::Expr;
+use ::ast::Value;
+use ::diagnostics::Code;
+use super::EvalError;
+
+/// The kinds of value a command can ask for.
+#[derive(Copy, Clone)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub enum ArgType {
+    /// Anything which can be rendered as content.
+    Any,
+    /// A string or a bare identifier.
+    Text,
+    Ident,
+    Number,
+    Expr,
+    /// Either `{$ ... $}` or `{$$ ... $$}`.
+    Math
+}
+
+impl ArgType {
+    pub fn accepts(self, value: &Value) -> bool {
+        matches!((self, value),
+            (ArgType::Any, _)
+            | (ArgType::Text, &Value::String(_))
+            | (ArgType::Text, &Value::Ident(_))
+            | (ArgType::Ident, &Value::Ident(_))
+            | (ArgType::Number, &Value::Number(_))
+            | (ArgType::Expr, &Value::Expr(_))
+            | (ArgType::Math, &Value::Math(_)))
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            ArgType::Any => "content",
+            ArgType::Text => "a string",
+            ArgType::Ident => "an identifier",
+            ArgType::Number => "a number",
+            ArgType::Expr => "an expression",
+            ArgType::Math => "math"
+        }
+    }
+}
+
+pub fn describe_value(value: &Value) -> &'static str {
+    match *value {
+        Value::Ident(_) => "an identifier",
+        Value::String(_) => "a string",
+        Value::Number(_) => "a number",
+        Value::Expr(_) => "an expression",
+        Value::Math(_) => "math"
+    }
+}
+
+/// The positional arguments and `:key` properties a command accepts.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Default)]
+pub struct Signature {
+    positional: Vec<ArgType>,
+    rest: Option<ArgType>,
+    keywords: Vec<(&'static str, ArgType)>,
+    any_keywords: bool
+}
+
+impl Signature {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_positional(mut self, ty: ArgType) -> Self {
+        self.positional.push(ty);
+        self
+    }
+
+    /// Accepts any number of further positional arguments after the fixed ones.
+    pub fn with_rest(mut self, ty: ArgType) -> Self {
+        self.rest = Some(ty);
+        self
+    }
+
+    pub fn with_keyword(mut self, key: &'static str, ty: ArgType) -> Self {
+        self.keywords.push((key, ty));
+        self
+    }
+
+    /// Leaves checking of properties to the command itself.
+    pub fn with_any_keywords(mut self) -> Self {
+        self.any_keywords = true;
+        self
+    }
+
+    pub fn check(&self, name: &str, expr: &Expr) -> Result<(), Vec<EvalError>> {
+        let mut errors = Vec::new();
+        let count = expr.args.len();
+        let expected = self.positional.len();
+        if count < expected || (count > expected && self.rest.is_none()) {
+            let plural = if expected == 1 { "" } else { "s" };
+            let message = if self.rest.is_some() {
+                format!("`{}` expects at least {} positional argument{}, got {}", name, expected, plural, count)
+            } else {
+                format!("`{}` expects {} positional argument{}, got {}", name, expected, plural, count)
+            };
+            errors.push(EvalError::new(message, &expr.location));
+        }
+        for (i, arg) in expr.args.iter().enumerate() {
+            if let Some(&ty) = self.positional.get(i).or(self.rest.as_ref()) {
+                if !ty.accepts(arg) {
+                    errors.push(EvalError::new(
+                        format!("`{}` expects argument {} to be {}, got {}", name, i + 1, ty.describe(), describe_value(arg)),
+                        arg.location()));
+                }
+            }
+        }
+        for (i, prop) in expr.props.iter().enumerate() {
+            let key = prop.key.name.as_str();
+            if expr.props[..i].iter().any(|prev| prev.key.name == key) {
+                errors.push(EvalError::new(format!("`:{}` is given more than once", key), &prop.location));
+                continue;
+            }
+            if self.any_keywords {
+                continue;
+            }
+            match self.keywords.iter().find(|&&(k, _)| k == key) {
+                Some(&(_, ty)) if !ty.accepts(&prop.value) => {
+                    errors.push(EvalError::new(
+                        format!("`{}` expects `:{}` to be {}, got {}", name, key, ty.describe(), describe_value(&prop.value)),
+                        prop.value.location()));
+                },
+                Some(_) => {},
+                None => errors.push(EvalError::new(format!("`{}` does not take a `:{}` property", name, key), &prop.location))
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.into_iter().map(|err| err.with_code(Code::BadArguments)).collect())
+        }
+    }
+}
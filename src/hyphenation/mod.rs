@@ -0,0 +1,33 @@
+mod patterns;
+
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+pub use self::patterns::Patterns;
+
+/// The pattern file for `language` in `dir`, named as in TeX's hyph-utf8 collection, such as `hyph-en-gb.tex`.
+///
+/// A tag which has no file of its own falls back to a shorter one, so `de-CH-1901` may use `hyph-de.tex`, and a bare
+/// language uses the first of its regional variants if there is nothing more general.
+pub fn find_patterns(dir: &Path, language: &str) -> Option<PathBuf> {
+    let tag = language.to_lowercase().replace('_', "-");
+    let mut tag = tag.as_str();
+    loop {
+        let path = dir.join(format!("hyph-{}.tex", tag));
+        if path.is_file() {
+            return Some(path);
+        }
+        match tag.rfind('-') {
+            Some(end) => tag = &tag[..end],
+            None => break
+        }
+    }
+    let prefix = format!("hyph-{}-", tag);
+    fs::read_dir(dir).ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.starts_with(&prefix) && name.ends_with(".tex"))
+            .unwrap_or(false))
+        .min()
+}
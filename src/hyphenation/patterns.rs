@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Liang's hyphenation patterns for one language, and words whose hyphens are given outright, as TeX uses them.
+///
+/// A pattern is a fragment of a word with digits between some of its letters, and `.` marking the start or end of
+/// the word. Where several patterns match a word, the highest digit between two letters wins: an odd digit allows a
+/// hyphen there and an even one forbids it.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Patterns {
+    /// The letters of each pattern, mapped to the digit before each letter and after the last.
+    patterns: HashMap<String, Vec<u8>>,
+    /// The number of letters in the longest pattern, so matching can stop there.
+    longest: usize,
+    /// Words in lower case, mapped to the number of letters before each of their hyphens.
+    exceptions: HashMap<String, Vec<usize>>,
+    left_min: usize,
+    right_min: usize
+}
+
+impl Default for Patterns {
+    /// No patterns, so only exceptions are hyphenated, keeping TeX's English minimums of two letters before a hyphen
+    /// and three after it.
+    fn default() -> Self {
+        Patterns {
+            patterns: HashMap::new(),
+            longest: 0,
+            exceptions: HashMap::new(),
+            left_min: 2,
+            right_min: 3
+        }
+    }
+}
+
+impl Patterns {
+    /// Reads patterns in TeX's format: the contents of `\patterns{...}` and `\hyphenation{...}`, with `%` comments.
+    /// A file with neither command is taken to be a bare list of patterns.
+    pub fn parse(source: &str) -> Self {
+        let source: String = source.lines()
+            .map(|line| line.split('%').next().unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut patterns = Patterns::default();
+        if !source.contains("\\patterns") && !source.contains("\\hyphenation") {
+            source.split_whitespace().for_each(|pattern| patterns.add_pattern(pattern));
+            return patterns;
+        }
+        let mut rest = source.as_str();
+        while let Some(start) = rest.find('\\') {
+            rest = &rest[start + 1..];
+            let name_end = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+            let name = &rest[..name_end];
+            rest = rest[name_end..].trim_start();
+            if (name != "patterns" && name != "hyphenation") || !rest.starts_with('{') {
+                continue;
+            }
+            let end = rest.find('}').unwrap_or(rest.len());
+            for word in rest[1..end].split_whitespace() {
+                if name == "patterns" {
+                    patterns.add_pattern(word);
+                } else {
+                    patterns.add_exception(word);
+                }
+            }
+            rest = &rest[end..];
+        }
+        patterns
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Patterns::parse(&fs::read_to_string(path)?))
+    }
+
+    /// The fewest letters allowed before the first hyphen and after the last, which patterns are written to expect.
+    pub fn with_minimums(mut self, left: usize, right: usize) -> Self {
+        self.left_min = left;
+        self.right_min = right;
+        self
+    }
+
+    /// Adds a pattern such as `hen5at` or `.ach4`.
+    pub fn add_pattern(&mut self, pattern: &str) {
+        let mut letters = String::new();
+        let mut values = vec![0];
+        for c in pattern.chars() {
+            match c.to_digit(10) {
+                Some(digit) => *values.last_mut().unwrap() = digit as u8,
+                None => {
+                    letters.push(lower(c));
+                    values.push(0);
+                }
+            }
+        }
+        if !letters.is_empty() {
+            self.longest = self.longest.max(values.len() - 1);
+            self.patterns.insert(letters, values);
+        }
+    }
+
+    /// Adds a word, such as `ta-ble`, whose hyphens are exactly where it may break. It replaces any earlier
+    /// exception for the same word, whatever its case.
+    pub fn add_exception(&mut self, word: &str) {
+        let mut letters = String::new();
+        let mut positions = Vec::new();
+        let mut count = 0;
+        for c in word.chars() {
+            if c == '-' {
+                positions.push(count);
+            } else {
+                letters.push(lower(c));
+                count += 1;
+            }
+        }
+        positions.retain(|&position| position > 0 && position < count);
+        positions.dedup();
+        self.exceptions.insert(letters, positions);
+    }
+
+    /// The byte offsets in `word` at which it may be broken with a hyphen.
+    pub fn hyphenate(&self, word: &str) -> Vec<usize> {
+        let letters: Vec<char> = word.chars().map(lower).collect();
+        let key: String = letters.iter().collect();
+        let positions = match self.exceptions.get(&key) {
+            Some(positions) => positions.clone(),
+            None => self.find_positions(&letters)
+        };
+        let offsets: Vec<usize> = word.char_indices().map(|(offset, _)| offset).collect();
+        positions.into_iter().map(|position| offsets[position]).collect()
+    }
+
+    /// The number of letters before each place the patterns allow a hyphen.
+    fn find_positions(&self, letters: &[char]) -> Vec<usize> {
+        let count = letters.len();
+        if self.patterns.is_empty() || count < 2 || count < self.left_min + self.right_min {
+            return Vec::new();
+        }
+        let mut dotted = vec!['.'];
+        dotted.extend(letters);
+        dotted.push('.');
+        // `values[i]` is the digit before `dotted[i]`.
+        let mut values = vec![0; dotted.len() + 1];
+        for start in 0..dotted.len() {
+            let mut fragment = String::new();
+            for &c in dotted[start..].iter().take(self.longest) {
+                fragment.push(c);
+                if let Some(pattern) = self.patterns.get(&fragment) {
+                    for (value, &digit) in values[start..].iter_mut().zip(pattern) {
+                        *value = (*value).max(digit);
+                    }
+                }
+            }
+        }
+        (self.left_min.max(1)..=count - self.right_min.max(1))
+            .filter(|&position| values[position + 1] % 2 == 1)
+            .collect()
+    }
+}
+
+/// `c` in lower case, keeping one letter for one so positions in a word stay the same.
+fn lower(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+#[cfg(test)]
+mod test {
+    use super::Patterns;
+
+    /// The patterns which hyphenate "hyphenation" in Appendix H of The TeXbook.
+    const TEXBOOK: &str = "% From the TeXbook\n\\patterns{hy3ph he2n hena4 hen5at 1na n2at 1tio 2io o2n}\n";
+
+    fn split(patterns: &Patterns, word: &str) -> Vec<String> {
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        for offset in patterns.hyphenate(word) {
+            pieces.push(word[start..offset].to_string());
+            start = offset;
+        }
+        pieces.push(word[start..].to_string());
+        pieces
+    }
+
+    #[test]
+    fn hyphenates_with_patterns() {
+        let patterns = Patterns::parse(TEXBOOK);
+        assert_eq!(split(&patterns, "hyphenation"), vec!["hy", "phen", "ation"]);
+        assert_eq!(split(&patterns, "Hyphenation"), vec!["Hy", "phen", "ation"]);
+        assert_eq!(split(&patterns, "nation"), vec!["na", "tion"]);
+        // Too short to leave two letters before a hyphen and three after it.
+        assert_eq!(split(&patterns, "tion"), vec!["tion"]);
+        assert_eq!(split(&Patterns::parse("1na"), "nation"), vec!["nation"]);
+    }
+
+    #[test]
+    fn exceptions_override_patterns() {
+        let mut patterns = Patterns::parse(&format!("{}\\hyphenation{{ta-ble}}", TEXBOOK));
+        assert_eq!(split(&patterns, "Table"), vec!["Ta", "ble"]);
+        patterns.add_exception("hyphen-ation");
+        assert_eq!(split(&patterns, "hyphenation"), vec!["hyphen", "ation"]);
+        patterns.add_exception("\u{e9}t\u{e9}-ta-ble");
+        assert_eq!(split(&patterns, "\u{c9}T\u{c9}TABLE"), vec!["\u{c9}T\u{c9}", "TA", "BLE"]);
+    }
+}
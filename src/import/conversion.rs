@@ -0,0 +1,10 @@
+use ::diagnostics::Diagnostic;
+
+/// The litx source an importer produced, with a warning for everything it could not carry over as written.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Conversion {
+    pub source: String,
+    pub warnings: Vec<Diagnostic>
+}
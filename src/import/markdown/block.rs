@@ -0,0 +1,398 @@
+use super::inline;
+use super::inline::Inline;
+
+/// One line of Markdown, with the byte offset of its first character so warnings can point back at it.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub struct Line<'s> {
+    pub text: &'s str,
+    pub offset: usize
+}
+
+impl <'s> Line<'s> {
+    fn is_blank(self) -> bool {
+        self.text.trim().is_empty()
+    }
+
+    /// The width of the leading whitespace, with tabs stopping at multiples of four.
+    fn indent(self) -> usize {
+        let mut column = 0;
+        for c in self.text.chars() {
+            match c {
+                ' ' => column += 1,
+                '\t' => column += 4 - column % 4,
+                _ => break
+            }
+        }
+        column
+    }
+
+    /// The line with up to `columns` columns of leading whitespace removed.
+    fn dedent(self, columns: usize) -> Line<'s> {
+        let mut column = 0;
+        let mut bytes = 0;
+        for c in self.text.chars() {
+            if column >= columns {
+                break;
+            }
+            match c {
+                ' ' => column += 1,
+                '\t' => column += 4 - column % 4,
+                _ => break
+            }
+            bytes += 1;
+        }
+        self.skip(bytes)
+    }
+
+    fn skip(self, bytes: usize) -> Line<'s> {
+        let bytes = bytes.min(self.text.len());
+        Line {
+            text: &self.text[bytes..],
+            offset: self.offset + bytes
+        }
+    }
+}
+
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum BlockKind {
+    Heading(usize, Vec<Inline>),
+    Paragraph(Vec<Inline>),
+    List(Vec<Vec<Inline>>)
+}
+
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Block {
+    pub offset: usize,
+    pub kind: BlockKind
+}
+
+/// Splits Markdown into blocks, returning them with a warning, by offset, for each construct litx cannot keep.
+pub fn parse(lines: &[Line]) -> (Vec<Block>, Vec<(usize, String)>) {
+    let mut parser = Parser {
+        warnings: Vec::new()
+    };
+    let blocks = parser.blocks(lines);
+    (blocks, parser.warnings)
+}
+
+struct Parser {
+    warnings: Vec<(usize, String)>
+}
+
+impl Parser {
+    fn warn<S: Into<String>>(&mut self, offset: usize, message: S) {
+        self.warnings.push((offset, message.into()));
+    }
+
+    fn blocks(&mut self, lines: &[Line]) -> Vec<Block> {
+        let mut blocks = Vec::new();
+        let mut i = 0;
+        while i < lines.len() {
+            let line = lines[i];
+            if line.is_blank() {
+                i += 1;
+            } else if let Some((level, content)) = atx_heading(line.text) {
+                let content = self.inlines(content, line.offset);
+                blocks.push(Block { offset: line.offset, kind: BlockKind::Heading(level, content) });
+                i += 1;
+            } else if let Some(fence) = fence(line.text) {
+                let mut code = Vec::new();
+                i += 1;
+                while i < lines.len() && !closes_fence(lines[i].text, fence) {
+                    code.push(lines[i].dedent(fence.indent).text);
+                    i += 1;
+                }
+                i += 1;
+                self.warn(line.offset, "litx has no code blocks, so this one became a paragraph");
+                blocks.push(Block { offset: line.offset, kind: BlockKind::Paragraph(code_inlines(&code)) });
+            } else if line.indent() >= 4 {
+                let mut code = Vec::new();
+                while i < lines.len() && (lines[i].is_blank() || lines[i].indent() >= 4) {
+                    code.push(lines[i].dedent(4).text);
+                    i += 1;
+                }
+                self.warn(line.offset, "litx has no code blocks, so this one became a paragraph");
+                blocks.push(Block { offset: line.offset, kind: BlockKind::Paragraph(code_inlines(&code)) });
+            } else if is_rule(line.text) {
+                self.warn(line.offset, "litx has no horizontal rules, so this one was dropped");
+                i += 1;
+            } else if quote_content(line).is_some() {
+                let mut quoted = Vec::new();
+                while i < lines.len() && !lines[i].is_blank() {
+                    match quote_content(lines[i]) {
+                        Some(content) => quoted.push(content),
+                        // A lazy continuation of the quote's last paragraph.
+                        None if !interrupts(lines[i].text) => quoted.push(lines[i]),
+                        None => break
+                    }
+                    i += 1;
+                }
+                self.warn(line.offset, "litx has no block quotes, so this one's contents became ordinary blocks");
+                blocks.extend(self.blocks(&quoted));
+            } else if list_marker(line.text).is_some() {
+                i = self.list(lines, i, &mut blocks);
+            } else if is_link_definition(line.text) {
+                self.warn(line.offset, "litx has no links, so this link reference definition was dropped");
+                i += 1;
+            } else {
+                let mut j = i + 1;
+                let mut underline = None;
+                while j < lines.len() && !lines[j].is_blank() {
+                    underline = setext_level(lines[j].text);
+                    if underline.is_some() || interrupts(lines[j].text) {
+                        break;
+                    }
+                    j += 1;
+                }
+                let text: Vec<&str> = lines[i..j].iter().map(|line| line.text.trim_start()).collect();
+                let content = self.inlines(text.join("\n").trim_end(), line.offset);
+                match underline {
+                    Some(level) => {
+                        blocks.push(Block { offset: line.offset, kind: BlockKind::Heading(level, content) });
+                        i = j + 1;
+                    },
+                    None => {
+                        blocks.push(Block { offset: line.offset, kind: BlockKind::Paragraph(content) });
+                        i = j;
+                    }
+                }
+            }
+        }
+        blocks
+    }
+
+    /// Parses the list starting at `start` into `blocks`, returning the index of the first line after it.
+    /// Each item's blocks are run together into one entry, since a litx list item is a single piece of text.
+    fn list(&mut self, lines: &[Line], start: usize, blocks: &mut Vec<Block>) -> usize {
+        let first = list_marker(lines[start].text).unwrap();
+        let mut items = Vec::new();
+        let mut i = start;
+        while let Some(marker) = lines.get(i).and_then(|line| list_marker(line.text)).filter(|marker| marker.delimiter == first.delimiter) {
+            let mut item = vec![lines[i].skip(marker.width)];
+            i += 1;
+            while i < lines.len() {
+                let line = lines[i];
+                if line.is_blank() {
+                    match (i..lines.len()).find(|&k| !lines[k].is_blank()) {
+                        Some(next) if lines[next].indent() >= marker.width => {
+                            item.extend(lines[i..next].iter().map(|blank| blank.skip(blank.text.len())));
+                            i = next;
+                        },
+                        _ => break
+                    }
+                } else if line.indent() >= marker.width {
+                    item.push(line.dedent(marker.width));
+                    i += 1;
+                } else if list_marker(line.text).is_some() || interrupts(line.text) {
+                    break;
+                } else {
+                    item.push(line.dedent(line.indent()));
+                    i += 1;
+                }
+            }
+            items.push(item);
+            match (i..lines.len()).find(|&k| !lines[k].is_blank()) {
+                Some(next) if list_marker(lines[next].text).map(|marker| marker.delimiter) == Some(first.delimiter) => i = next,
+                _ => break
+            }
+        }
+        if first.ordered {
+            self.warn(lines[start].offset, "litx has no numbered lists, so this one became a bulleted list");
+        }
+        let mut entries = Vec::new();
+        for item in items {
+            let mut content = Vec::new();
+            let mut nested = Vec::new();
+            for block in self.blocks(&item) {
+                match block.kind {
+                    BlockKind::Heading(_, inlines) | BlockKind::Paragraph(inlines) => {
+                        if !content.is_empty() && !inlines.is_empty() {
+                            content.push(Inline::Space);
+                        }
+                        content.extend(inlines);
+                    },
+                    BlockKind::List(sublist) => {
+                        self.warn(block.offset, "litx has no nested lists, so this one's items were added to the list around it");
+                        nested.extend(sublist);
+                    }
+                }
+            }
+            entries.push(content);
+            entries.extend(nested);
+        }
+        blocks.push(Block { offset: lines[start].offset, kind: BlockKind::List(entries) });
+        i
+    }
+
+    fn inlines(&mut self, text: &str, offset: usize) -> Vec<Inline> {
+        let (inlines, mut losses) = inline::parse(text);
+        losses.dedup();
+        for (i, loss) in losses.iter().enumerate() {
+            if !losses[..i].contains(loss) {
+                self.warn(offset, loss.get_message());
+            }
+        }
+        inlines
+    }
+}
+
+/// A code block as a paragraph with a hard break after every line.
+fn code_inlines(code: &[&str]) -> Vec<Inline> {
+    let mut inlines = Vec::new();
+    let end = code.iter().rposition(|line| !line.trim().is_empty()).map(|last| last + 1).unwrap_or(0);
+    for (i, line) in code[..end].iter().enumerate() {
+        if i > 0 {
+            inlines.push(Inline::HardBreak);
+        }
+        if !line.trim().is_empty() {
+            inlines.push(Inline::Text(line.trim_end().to_string()));
+        }
+    }
+    inlines
+}
+
+/// The text after at most three spaces of indentation, if there are no more than that.
+fn unindented(text: &str) -> Option<&str> {
+    let rest = text.trim_start_matches(' ');
+    if text.len() - rest.len() <= 3 {
+        Some(rest)
+    } else {
+        None
+    }
+}
+
+fn atx_heading(text: &str) -> Option<(usize, &str)> {
+    let rest = unindented(text)?;
+    let level = rest.chars().take_while(|&c| c == '#').count();
+    let after = &rest[level..];
+    if level == 0 || level > 6 || !(after.is_empty() || after.starts_with(' ') || after.starts_with('\t')) {
+        return None;
+    }
+    let content = after.trim();
+    // An optional closing sequence of `#`s, which must be set apart from the content by a space.
+    let unclosed = content.trim_end_matches('#');
+    if unclosed.is_empty() {
+        Some((level, unclosed))
+    } else if unclosed.ends_with(' ') || unclosed.ends_with('\t') {
+        Some((level, unclosed.trim_end()))
+    } else {
+        Some((level, content))
+    }
+}
+
+fn setext_level(text: &str) -> Option<usize> {
+    let underline = unindented(text)?.trim_end();
+    if !underline.is_empty() && underline.chars().all(|c| c == '=') {
+        Some(1)
+    } else if !underline.is_empty() && underline.chars().all(|c| c == '-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+struct Fence {
+    c: char,
+    length: usize,
+    indent: usize
+}
+
+fn fence(text: &str) -> Option<Fence> {
+    let rest = unindented(text)?;
+    let c = rest.chars().next().filter(|&c| c == '`' || c == '~')?;
+    let length = rest.chars().take_while(|&other| other == c).count();
+    if length < 3 || (c == '`' && rest[length..].contains('`')) {
+        return None;
+    }
+    Some(Fence { c, length, indent: text.len() - rest.len() })
+}
+
+fn closes_fence(text: &str, fence: Fence) -> bool {
+    unindented(text)
+        .map(|rest| rest.trim_end())
+        .map(|rest| rest.len() >= fence.length && rest.chars().all(|c| c == fence.c))
+        .unwrap_or(false)
+}
+
+fn is_rule(text: &str) -> bool {
+    let rest = match unindented(text) {
+        Some(rest) => rest,
+        None => return false
+    };
+    let mut marks = rest.chars().filter(|&c| c != ' ' && c != '\t');
+    match marks.next() {
+        Some(c) if c == '-' || c == '*' || c == '_' => marks.clone().count() >= 2 && marks.all(|other| other == c),
+        _ => false
+    }
+}
+
+fn quote_content<'s>(line: Line<'s>) -> Option<Line<'s>> {
+    let rest = unindented(line.text)?;
+    if !rest.starts_with('>') {
+        return None;
+    }
+    let marker = line.text.len() - rest.len() + 1;
+    let space = if rest[1..].starts_with(' ') { 1 } else { 0 };
+    Some(line.skip(marker + space))
+}
+
+fn is_link_definition(text: &str) -> bool {
+    unindented(text)
+        .filter(|rest| rest.starts_with('['))
+        .and_then(|rest| rest.find("]:"))
+        .map(|close| close > 1)
+        .unwrap_or(false)
+}
+
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+struct Marker {
+    /// The bullet, or the `.` or `)` after a number. A different one starts a new list.
+    delimiter: char,
+    ordered: bool,
+    /// Whether the item may begin in the middle of a paragraph: only non-empty items, and only the first number.
+    interrupts: bool,
+    /// The byte offset of the item's content, which continuation lines must be indented to match.
+    width: usize
+}
+
+fn list_marker(text: &str) -> Option<Marker> {
+    let rest = unindented(text)?;
+    let indent = text.len() - rest.len();
+    let digits = rest.chars().take_while(char::is_ascii_digit).count();
+    let (delimiter, ordered, length) = match rest[digits..].chars().next() {
+        Some(c) if digits == 0 && (c == '-' || c == '+' || c == '*') => (c, false, 1),
+        Some(c) if digits > 0 && digits <= 9 && (c == '.' || c == ')') => (c, true, digits + 1),
+        _ => return None
+    };
+    let after = &rest[length..];
+    if !(after.is_empty() || after.starts_with(' ') || after.starts_with('\t')) {
+        return None;
+    }
+    let spaces = after.len() - after.trim_start_matches(' ').len();
+    let empty = after.trim().is_empty();
+    // Content indented five or more spaces is an indented code block inside the item, which starts one space in.
+    let padding = if empty || spaces == 0 || spaces > 4 { 1 } else { spaces };
+    Some(Marker {
+        delimiter,
+        ordered,
+        interrupts: !empty && (!ordered || &rest[..digits] == "1"),
+        width: indent + length + padding
+    })
+}
+
+/// Whether `text` starts a block which can cut a paragraph short.
+fn interrupts(text: &str) -> bool {
+    atx_heading(text).is_some()
+        || fence(text).is_some()
+        || is_rule(text)
+        || unindented(text).map(|rest| rest.starts_with('>')).unwrap_or(false)
+        || list_marker(text).map(|marker| marker.interrupts).unwrap_or(false)
+}
@@ -0,0 +1,365 @@
+use std::mem;
+
+/// Inline Markdown content, reduced to what litx can express.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum Inline {
+    Text(String),
+    Space,
+    /// A line ending within a paragraph, kept so the litx source wraps where the Markdown did.
+    SoftBreak,
+    HardBreak,
+    Emph(Vec<Inline>),
+    Strong(Vec<Inline>),
+    /// The source of `$...$` math.
+    Math(String),
+    /// The source of `$$...$$` math, which is displayed on a line of its own.
+    DisplayMath(String)
+}
+
+/// Something inline which litx has no way to keep.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub enum Loss {
+    LinkTarget,
+    Image
+}
+
+impl Loss {
+    pub fn get_message(self) -> &'static str {
+        match self {
+            Loss::LinkTarget => "litx has no links, so only the link text was kept",
+            Loss::Image => "litx has no images, so only the image's description was kept"
+        }
+    }
+}
+
+/// Parses the inline content of one block. Code spans become plain text, links and images keep only their text,
+/// and anything which is not well-formed Markdown syntax is kept as the characters it was written with.
+pub fn parse(text: &str) -> (Vec<Inline>, Vec<Loss>) {
+    let chars: Vec<char> = text.chars().collect();
+    let mut parser = Parser {
+        chars: &chars,
+        losses: Vec::new()
+    };
+    let inlines = parser.parse(0, chars.len());
+    (inlines, parser.losses)
+}
+
+struct Parser<'c> {
+    chars: &'c [char],
+    losses: Vec<Loss>
+}
+
+impl <'c> Parser<'c> {
+    fn parse(&mut self, start: usize, end: usize) -> Vec<Inline> {
+        let chars = self.chars;
+        let mut inlines = Vec::new();
+        let mut text = String::new();
+        let mut i = start;
+        while i < end {
+            let c = chars[i];
+            match c {
+                '\\' if i + 1 < end && chars[i + 1] == '\n' => {
+                    end_text(&mut inlines, &mut text);
+                    inlines.push(Inline::HardBreak);
+                    i = skip_spaces(chars, i + 2, end);
+                },
+                '\\' if i + 1 < end && chars[i + 1].is_ascii_punctuation() => {
+                    text.push(chars[i + 1]);
+                    i += 2;
+                },
+                ' ' | '\t' | '\n' => {
+                    end_text(&mut inlines, &mut text);
+                    let spaces_end = skip_spaces(chars, i, end);
+                    if spaces_end < end && chars[spaces_end] == '\n' {
+                        // Two or more spaces before a line ending make a hard break.
+                        inlines.push(if spaces_end - i >= 2 { Inline::HardBreak } else { Inline::SoftBreak });
+                        i = skip_spaces(chars, spaces_end + 1, end);
+                    } else {
+                        if spaces_end < end {
+                            inlines.push(Inline::Space);
+                        }
+                        i = spaces_end;
+                    }
+                },
+                '`' => {
+                    let ticks = run_length(chars, i, end);
+                    match find_run(chars, i + ticks, end, ticks) {
+                        Some(close) => {
+                            text.push_str(&code_span(&chars[i + ticks..close]));
+                            i = close + ticks;
+                        },
+                        None => {
+                            text.extend(&chars[i..i + ticks]);
+                            i += ticks;
+                        }
+                    }
+                },
+                '$' => match self.math(i, end) {
+                    Some((math, next)) => {
+                        end_text(&mut inlines, &mut text);
+                        inlines.push(math);
+                        i = next;
+                    },
+                    None => {
+                        text.push('$');
+                        i += 1;
+                    }
+                },
+                '*' | '_' => match self.emphasis(i, start, end) {
+                    Some((inline, next)) => {
+                        end_text(&mut inlines, &mut text);
+                        inlines.push(inline);
+                        i = next;
+                    },
+                    None => {
+                        let length = run_length(chars, i, end);
+                        text.extend(&chars[i..i + length]);
+                        i += length;
+                    }
+                },
+                '!' if i + 1 < end && chars[i + 1] == '[' => match self.link(i + 1, end) {
+                    Some((content, next)) => {
+                        end_text(&mut inlines, &mut text);
+                        self.losses.push(Loss::Image);
+                        inlines.extend(content);
+                        i = next;
+                    },
+                    None => {
+                        text.push('!');
+                        i += 1;
+                    }
+                },
+                '[' => match self.link(i, end) {
+                    Some((content, next)) => {
+                        end_text(&mut inlines, &mut text);
+                        self.losses.push(Loss::LinkTarget);
+                        inlines.extend(content);
+                        i = next;
+                    },
+                    None => {
+                        text.push('[');
+                        i += 1;
+                    }
+                },
+                '<' => match autolink(chars, i, end) {
+                    Some(next) => {
+                        text.extend(&chars[i + 1..next - 1]);
+                        i = next;
+                    },
+                    None => {
+                        text.push('<');
+                        i += 1;
+                    }
+                },
+                _ => {
+                    text.push(c);
+                    i += 1;
+                }
+            }
+        }
+        end_text(&mut inlines, &mut text);
+        inlines
+    }
+
+    /// `$$...$$`, or `$...$` as Pandoc reads it: the opening `$` is followed by a non-space,
+    /// and the closing `$` is preceded by one and not followed by a digit, so prices are left alone.
+    fn math(&self, i: usize, end: usize) -> Option<(Inline, usize)> {
+        let chars = self.chars;
+        if i + 1 < end && chars[i + 1] == '$' {
+            let mut k = i + 2;
+            while k + 1 < end {
+                match chars[k] {
+                    '\\' => k += 2,
+                    '$' if chars[k + 1] == '$' => return math_source(&chars[i + 2..k]).map(|source| (Inline::DisplayMath(source), k + 2)),
+                    _ => k += 1
+                }
+            }
+            return None;
+        }
+        if i + 1 >= end || chars[i + 1].is_whitespace() {
+            return None;
+        }
+        let mut k = i + 1;
+        while k < end {
+            match chars[k] {
+                '\\' => k += 2,
+                '$' if chars[k - 1].is_whitespace() || (k + 1 < end && chars[k + 1].is_ascii_digit()) => return None,
+                '$' => return math_source(&chars[i + 1..k]).map(|source| (Inline::Math(source), k + 1)),
+                _ => k += 1
+            }
+        }
+        None
+    }
+
+    /// Emphasis opened by the run of `*` or `_` at `i`, closed by the next run of the same length.
+    /// An underscore only opens and closes at the edges of words, so `snake_case` is left alone.
+    fn emphasis(&mut self, i: usize, start: usize, end: usize) -> Option<(Inline, usize)> {
+        let chars = self.chars;
+        let c = chars[i];
+        let length = run_length(chars, i, end);
+        let content_start = i + length;
+        if length > 3 || content_start >= end || chars[content_start].is_whitespace() {
+            return None;
+        }
+        if c == '_' && i > start && chars[i - 1].is_alphanumeric() {
+            return None;
+        }
+        let mut k = content_start;
+        while k < end {
+            match chars[k] {
+                '\\' => k += 2,
+                '`' => {
+                    let ticks = run_length(chars, k, end);
+                    k = find_run(chars, k + ticks, end, ticks).map(|close| close + ticks).unwrap_or(k + ticks);
+                },
+                other if other == c => {
+                    let close_length = run_length(chars, k, end);
+                    let closes = close_length == length
+                        && !chars[k - 1].is_whitespace()
+                        && (c == '*' || k + close_length >= end || !chars[k + close_length].is_alphanumeric());
+                    if closes {
+                        let content = self.parse(content_start, k);
+                        let inline = match length {
+                            1 => Inline::Emph(content),
+                            2 => Inline::Strong(content),
+                            _ => Inline::Strong(vec![Inline::Emph(content)])
+                        };
+                        return Some((inline, k + length));
+                    }
+                    k += close_length;
+                },
+                _ => k += 1
+            }
+        }
+        None
+    }
+
+    /// The text of an inline `[text](destination)` starting at `i`, and the index just past it.
+    fn link(&mut self, i: usize, end: usize) -> Option<(Vec<Inline>, usize)> {
+        let chars = self.chars;
+        let close = matching(chars, i, end, '[', ']')?;
+        if close + 1 >= end || chars[close + 1] != '(' {
+            return None;
+        }
+        let destination_end = matching(chars, close + 1, end, '(', ')')?;
+        Some((self.parse(i + 1, close), destination_end + 1))
+    }
+}
+
+fn end_text(inlines: &mut Vec<Inline>, text: &mut String) {
+    if !text.is_empty() {
+        inlines.push(Inline::Text(mem::take(text)));
+    }
+}
+
+fn skip_spaces(chars: &[char], mut i: usize, end: usize) -> usize {
+    while i < end && (chars[i] == ' ' || chars[i] == '\t') {
+        i += 1;
+    }
+    i
+}
+
+/// How many times the character at `i` repeats, starting there.
+fn run_length(chars: &[char], i: usize, end: usize) -> usize {
+    chars[i..end].iter().take_while(|&&c| c == chars[i]).count()
+}
+
+/// The start of the next run of exactly `length` backticks.
+fn find_run(chars: &[char], mut i: usize, end: usize, length: usize) -> Option<usize> {
+    while i < end {
+        if chars[i] == '`' {
+            let found = run_length(chars, i, end);
+            if found == length {
+                return Some(i);
+            }
+            i += found;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// The closer balancing the opener at `i`, skipping anything escaped.
+fn matching(chars: &[char], mut i: usize, end: usize, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    while i < end {
+        match chars[i] {
+            '\\' => i += 1,
+            c if c == open => depth += 1,
+            c if c == close => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            },
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// The index just past a `<scheme:...>` or `<user@host>` autolink starting at `i`.
+fn autolink(chars: &[char], i: usize, end: usize) -> Option<usize> {
+    let mut k = i + 1;
+    while k < end && chars[k] != '>' {
+        if chars[k].is_whitespace() || chars[k] == '<' {
+            return None;
+        }
+        k += 1;
+    }
+    let target = chars.get(i + 1..k)?;
+    if k < end && (target.contains(&':') || target.contains(&'@')) {
+        Some(k + 1)
+    } else {
+        None
+    }
+}
+
+/// A code span's content, with line endings as spaces and one padding space stripped from each side.
+fn code_span(chars: &[char]) -> String {
+    let content: String = chars.iter().map(|&c| if c == '\n' { ' ' } else { c }).collect();
+    if content.len() >= 2 && content.starts_with(' ') && content.ends_with(' ') && !content.trim().is_empty() {
+        content[1..content.len() - 1].to_string()
+    } else {
+        content
+    }
+}
+
+fn math_source(chars: &[char]) -> Option<String> {
+    let source: String = chars.iter().map(|&c| if c == '\n' { ' ' } else { c }).collect();
+    let source = source.trim();
+    if source.is_empty() {
+        None
+    } else {
+        Some(source.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Inline::*;
+    use super::Loss;
+    use super::parse;
+
+    fn text(text: &str) -> super::Inline {
+        Text(text.to_string())
+    }
+
+    #[test]
+    fn inlines() {
+        let (inlines, losses) = parse("*a **b** c* snake_case `x*y*` [link](http://x) $5 and $6, $x^2$ $$y$$  \nend\\*");
+        assert_eq!(inlines, vec![
+            Emph(vec![text("a"), Space, Strong(vec![text("b")]), Space, text("c")]),
+            Space, text("snake_case"), Space, text("x*y*"), Space, text("link"), Space,
+            text("$5"), Space, text("and"), Space, text("$6,"), Space, Math("x^2".to_string()), Space, DisplayMath("y".to_string()), HardBreak,
+            text("end*")
+        ]);
+        assert_eq!(losses, vec![Loss::LinkTarget]);
+    }
+}
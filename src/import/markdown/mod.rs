@@ -0,0 +1,457 @@
+mod block;
+mod inline;
+
+use ::ast::Location;
+use ::diagnostics::Code;
+use ::diagnostics::Diagnostic;
+use ::diagnostics::Label;
+use ::diagnostics::Severity;
+use ::lex::Lexer;
+use ::parse::parse;
+use ::source::SourceFile;
+use self::block::Block;
+use self::block::BlockKind;
+use self::block::Line;
+use self::inline::Inline;
+use super::Conversion;
+
+/// Converts the CommonMark in `file` into litx source, which always lexes and parses without errors.
+///
+/// Headings, paragraphs, hard line breaks, emphasis, strong emphasis, bulleted lists and `$`-delimited math carry over,
+/// and YAML front matter becomes the `{litx ...}` header. litx has no links, images, code, quotes, rules or nested lists,
+/// and can only style a heading or list item as a whole, so those are simplified or dropped, with a warning for each.
+pub fn convert(file: &SourceFile) -> Conversion {
+    let lines = lines(file.get_source());
+    let mut converter = Converter {
+        file,
+        warnings: Vec::new()
+    };
+    let (header, body) = converter.front_matter(&lines);
+    let (blocks, warnings) = block::parse(&lines[body..]);
+    for (offset, message) in warnings {
+        converter.warn(offset, message);
+    }
+    let mut parts: Vec<String> = header.into_iter().collect();
+    parts.extend(blocks.iter().filter_map(|block| converter.block(block)));
+    let mut source = parts.join("\n\n");
+    if !source.is_empty() {
+        source.push('\n');
+    }
+    let mut warnings = converter.warnings;
+    warnings.sort_by_key(|warning| warning.get_primary().map(|label| label.location.index));
+    Conversion {
+        source,
+        warnings
+    }
+}
+
+fn lines<'s>(source: &'s str) -> Vec<Line<'s>> {
+    let mut offset = 0;
+    source.split('\n')
+        .map(|text| {
+            let line = Line { text: text.trim_end_matches('\r'), offset };
+            offset += text.len() + 1;
+            line
+        })
+        .collect()
+}
+
+struct Converter<'f> {
+    file: &'f SourceFile,
+    warnings: Vec<Diagnostic>
+}
+
+impl <'f> Converter<'f> {
+    /// Points a warning at the rest of the line from `offset`.
+    fn warn<S: Into<String>>(&mut self, offset: usize, message: S) {
+        let (line, column) = self.file.get_line_col(offset);
+        let length = self.file.get_line(line).map(str::len).unwrap_or(column);
+        let location = Location {
+            file: Some(self.file.get_name().to_string()),
+            index: offset,
+            end: offset - column + length,
+            line,
+            column
+        };
+        self.warnings.push(Diagnostic::new(Severity::Warning, message)
+            .with_code(Code::NotImported)
+            .with_label(Label::primary(&location, "")));
+    }
+
+    /// The `{litx ...}` header for a leading YAML front-matter block, and the index of the first line after the block.
+    /// Only simple `key: value`, `key: [a, b]` and `- item` list entries are understood.
+    fn front_matter(&mut self, lines: &[Line]) -> (Option<String>, usize) {
+        if lines.first().map(|line| line.text.trim_end()) != Some("---") {
+            return (None, 0);
+        }
+        let end = match lines[1..].iter().position(|line| matches!(line.text.trim_end(), "---" | "...")) {
+            Some(end) => end + 1,
+            None => return (None, 0)
+        };
+        let mut entries: Vec<(usize, &str, Vec<String>)> = Vec::new();
+        for line in &lines[1..end] {
+            let text = line.text.trim_end();
+            let item = text.trim_start();
+            if item.is_empty() || item.starts_with('#') {
+                continue;
+            }
+            if item == "-" || item.starts_with("- ") {
+                if let Some(entry) = entries.last_mut() {
+                    entry.2.push(yaml_scalar(&item[1..]));
+                }
+            } else if let Some(colon) = text.find(':').filter(|_| text == item) {
+                let value = text[colon + 1..].trim();
+                let values = if value.starts_with('[') && value.ends_with(']') {
+                    value[1..value.len() - 1].split(',').map(yaml_scalar).filter(|value| !value.is_empty()).collect()
+                } else if value.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![yaml_scalar(value)]
+                };
+                entries.push((line.offset, text[..colon].trim(), values));
+            }
+        }
+        let (mut doctype, mut title, mut date, mut lang) = (None, None, None, None);
+        let mut authors = Vec::new();
+        for (offset, key, values) in entries {
+            let value = Some(values.join(", ")).filter(|value| !value.is_empty());
+            match key {
+                "doctype" => doctype = value,
+                "title" => title = value,
+                "date" => date = value,
+                "lang" | "language" => lang = value,
+                "author" | "authors" => authors.extend(values),
+                _ => self.warn(offset, format!("the `{}` front matter has no litx equivalent, so it was dropped", key))
+            }
+        }
+        let mut props = Vec::new();
+        for &(key, ref value) in &[("doctype", doctype), ("title", title)] {
+            if let Some(ref value) = *value {
+                props.push(format!(":{} {}", key, string(value)));
+            }
+        }
+        match authors.len() {
+            0 => {},
+            1 => props.push(format!(":author {}", string(&authors[0]))),
+            _ => {
+                let authors: Vec<String> = authors.iter().map(|author| string(author)).collect();
+                props.push(format!(":authors {{list {}}}", authors.join(" ")));
+            }
+        }
+        for &(key, ref value) in &[("date", date), ("lang", lang)] {
+            if let Some(ref value) = *value {
+                props.push(format!(":{} {}", key, string(value)));
+            }
+        }
+        let header = if props.is_empty() {
+            None
+        } else {
+            Some(format!("{{litx {}}}", props.join(" ")))
+        };
+        (header, end + 1)
+    }
+
+    fn block(&mut self, block: &Block) -> Option<String> {
+        match block.kind {
+            BlockKind::Heading(level, ref content) => Some(format!("{{h{} {}}}", level, self.argument(content, block.offset))),
+            BlockKind::Paragraph(ref content) => {
+                let paragraph = self.paragraph(trim(content), block.offset);
+                if paragraph.is_empty() {
+                    None
+                } else {
+                    Some(paragraph)
+                }
+            },
+            BlockKind::List(ref items) => {
+                let mut list = "{list".to_string();
+                for item in items {
+                    list.push_str("\n  ");
+                    list.push_str(&self.argument(item, block.offset));
+                }
+                list.push('}');
+                Some(list)
+            }
+        }
+    }
+
+    fn paragraph(&mut self, content: &[Inline], offset: usize) -> String {
+        let mut paragraph = String::new();
+        for inline in content {
+            match *inline {
+                Inline::Text(ref text) => paragraph.push_str(&escape(text)),
+                Inline::Space => paragraph.push(' '),
+                Inline::SoftBreak => paragraph.push('\n'),
+                Inline::HardBreak => paragraph.push_str("\\n\n"),
+                ref styled => paragraph.push_str(&self.styled(styled, offset))
+            }
+        }
+        paragraph
+    }
+
+    /// `content` as a single argument to a command: one styled span or piece of math, or otherwise a string.
+    fn argument(&mut self, content: &[Inline], offset: usize) -> String {
+        match *trim(content) {
+            [ref styled @ Inline::Emph(_)]
+            | [ref styled @ Inline::Strong(_)]
+            | [ref styled @ Inline::Math(_)]
+            | [ref styled @ Inline::DisplayMath(_)] => self.styled(styled, offset),
+            ref content => {
+                if content.iter().any(|inline| matches!(*inline, Inline::Emph(_) | Inline::Strong(_) | Inline::Math(_) | Inline::DisplayMath(_))) {
+                    self.warn(offset, "litx can only style a heading or list item as a whole, so this one's formatting was dropped");
+                }
+                string(&plain_text(content))
+            }
+        }
+    }
+
+    fn styled(&mut self, inline: &Inline, offset: usize) -> String {
+        match *inline {
+            Inline::Emph(ref content) => format!("{{emph {}}}", self.argument(content, offset)),
+            Inline::Strong(ref content) => format!("{{strong {}}}", self.argument(content, offset)),
+            Inline::Math(ref source) => self.math(source, false, offset),
+            Inline::DisplayMath(ref source) => self.math(source, true, offset),
+            _ => string(&plain_text(std::slice::from_ref(inline)))
+        }
+    }
+
+    /// Math is kept as it is wherever litx can typeset it that way, with LaTeX's groups in braces becoming nested
+    /// `{$ ... $}` groups. Otherwise braces and quotes are escaped, which litx needs but which changes what they mean to
+    /// LaTeX, and if that is still not enough, so is every other character with a meaning in litx math.
+    fn math(&mut self, source: &str, display: bool, offset: usize) -> String {
+        let (open, close) = if display { ("{$$", "$$}") } else { ("{$", "$}") };
+        let source: String = source.chars()
+            .filter(|c| !c.is_control() || c.is_whitespace())
+            .map(|c| if c.is_whitespace() { ' ' } else { c })
+            .collect();
+        let grouped = group(&source);
+        match typeset(&grouped) {
+            Ok(()) => return format!("{} {} {}", open, grouped, close),
+            Err(None) => {
+                self.warn(offset, "this math is not valid litx as written, so its braces and quotes were escaped");
+                let escaped = escape_math(&source, &['{', '}', '"']);
+                if typeset(&escaped).is_ok() {
+                    return format!("{} {} {}", open, escaped, close);
+                }
+            },
+            Err(Some(message)) => {
+                self.warn(offset, format!("litx cannot typeset this math ({}), so it was kept as plain symbols", message));
+            }
+        }
+        format!("{} {} {}", open, escape_math(&source, &['\\', '{', '}', '"', '^', '_']), close)
+    }
+}
+
+/// Checks that `source` is math litx can typeset, failing with the reason if it parses but cannot be typeset.
+fn typeset(source: &str) -> Result<(), Option<String>> {
+    let math = format!("{{$ {} $}}", source);
+    let tree = parse(Lexer::new(&math)).map_err(|_| None)?;
+    for paragraph in &::ast::lower(&tree).paragraphs {
+        for item in &paragraph.items {
+            if let ::ast::Inline::Math(ref math) = *item {
+                ::math::parse(math, &mut |_| String::new()).map_err(Some)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `source` with each group in braces made a nested `{$ ... $}`, leaving escaped braces alone.
+fn group(source: &str) -> String {
+    let mut grouped = String::with_capacity(source.len());
+    let mut chars = source.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                grouped.push(c);
+                grouped.extend(chars.next());
+            },
+            '{' => grouped.push_str("{$ "),
+            '}' => grouped.push_str(" $}"),
+            _ => grouped.push(c)
+        }
+    }
+    grouped
+}
+
+/// `source` with a backslash before each of the `special` characters.
+fn escape_math(source: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(source.len());
+    for c in source.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// `content` without whitespace or breaks at either end.
+fn trim(content: &[Inline]) -> &[Inline] {
+    let is_space = |inline: &Inline| matches!(*inline, Inline::Space | Inline::SoftBreak | Inline::HardBreak);
+    let start = content.iter().position(|inline| !is_space(inline)).unwrap_or(content.len());
+    let end = content.iter().rposition(|inline| !is_space(inline)).map(|last| last + 1).unwrap_or(start);
+    &content[start..end]
+}
+
+fn plain_text(content: &[Inline]) -> String {
+    let mut text = String::new();
+    for inline in content {
+        match *inline {
+            Inline::Text(ref part) | Inline::Math(ref part) | Inline::DisplayMath(ref part) => text.push_str(part),
+            Inline::Space | Inline::SoftBreak | Inline::HardBreak => text.push(' '),
+            Inline::Emph(ref content) | Inline::Strong(ref content) => text.push_str(&plain_text(content))
+        }
+    }
+    text
+}
+
+/// Escapes paragraph text so none of it is read as litx syntax.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars().filter(|&c| !c.is_control() || c == '\t') {
+        if c == '\\' || c == '{' || c == '}' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// `text` as a litx string literal.
+fn string(text: &str) -> String {
+    let mut string = String::with_capacity(text.len() + 2);
+    string.push('"');
+    for c in text.chars() {
+        match c {
+            '\\' | '"' => {
+                string.push('\\');
+                string.push(c);
+            },
+            c if c.is_whitespace() => string.push(' '),
+            c if c.is_control() => {},
+            c => string.push(c)
+        }
+    }
+    string.push('"');
+    string
+}
+
+/// A YAML scalar without its quotes.
+fn yaml_scalar(value: &str) -> String {
+    let value = value.trim();
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        value[1..value.len() - 1].replace("\\\"", "\"").replace("\\\\", "\\")
+    } else if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        value[1..value.len() - 1].replace("''", "'")
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::diagnostics::Code;
+    use ::eval::CommandRegistry;
+    use ::lex::Lexer;
+    use ::parse::parse;
+    use ::source::SourceFile;
+
+    fn convert(markdown: &str) -> (String, Vec<String>) {
+        let conversion = super::convert(&SourceFile::new("notes.md", markdown));
+        let tree = parse(Lexer::new(&conversion.source)).expect("the converted source should parse");
+        ::eval::evaluate(&::ast::lower(&tree), &CommandRegistry::with_builtins()).expect("the converted source should evaluate");
+        let warnings = conversion.warnings.iter()
+            .map(|warning| {
+                assert_eq!(warning.code, Some(Code::NotImported));
+                let location = &warning.get_primary().unwrap().location;
+                format!("{}:{}: {}", location.line + 1, location.column + 1, warning.message)
+            })
+            .collect();
+        (conversion.source, warnings)
+    }
+
+    #[test]
+    fn converts_notes() {
+        let (source, warnings) = convert("\
+---
+title: \"Notes: {draft}\"
+author: [Ann, 'Bob']
+tags: [x]
+---
+
+# Intro *here*
+
+Some *stress*, __weight__ and ***both***,
+with $x^{2}$ math, $\\mathbb{R}$, a [link](http://x) and `{code}`.\\
+Next line \\{escaped\\} \\\\.
+
+Setext
+------
+
+- one
+- *two*
+  - nested
+* three
+
+1. first
+2. second
+
+> quoted
+
+```
+let x = \"}\";
+```
+");
+        assert_eq!(source, "\
+{litx :title \"Notes: {draft}\" :authors {list \"Ann\" \"Bob\"}}
+
+{h1 \"Intro here\"}
+
+Some {emph \"stress\"}, {strong \"weight\"} and {strong {emph \"both\"}},
+with {$ x^{$ 2 $} $} math, {$ \\\\mathbb\\{R\\} $}, a link and \\{code\\}.\\n
+Next line \\{escaped\\} \\\\.
+
+{h2 \"Setext\"}
+
+{list
+  \"one\"
+  {emph \"two\"}
+  \"nested\"}
+
+{list
+  \"three\"}
+
+{list
+  \"first\"
+  \"second\"}
+
+quoted
+
+let x = \"\\}\";
+");
+        assert_eq!(warnings, vec![
+            "4:1: the `tags` front matter has no litx equivalent, so it was dropped",
+            "7:1: litx can only style a heading or list item as a whole, so this one's formatting was dropped",
+            "9:1: litx has no links, so only the link text was kept",
+            "9:1: litx cannot typeset this math (unknown math command `\\mathbb`), so it was kept as plain symbols",
+            "18:3: litx has no nested lists, so this one's items were added to the list around it",
+            "21:1: litx has no numbered lists, so this one became a bulleted list",
+            "24:1: litx has no block quotes, so this one's contents became ordinary blocks",
+            "26:1: litx has no code blocks, so this one became a paragraph"
+        ]);
+    }
+
+    #[test]
+    fn output_always_parses() {
+        let samples = [
+            "{h1 \"x\"} $} !} {! {$ \\n \\",
+            "$a}b$ and $$ \"q\" {! $$ and $\\}$",
+            "# {\n\n- }\n- \"\\\n\n*{*",
+            "\u{1}control\u{7f} `}` <http://a}b>",
+            "[*a* **b**](x) ![alt](y \"t\")"
+        ];
+        for sample in &samples {
+            convert(sample);
+        }
+    }
+}
@@ -0,0 +1,4 @@
+mod conversion;
+pub mod markdown;
+
+pub use self::conversion::Conversion;
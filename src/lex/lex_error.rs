@@ -0,0 +1,61 @@
+use ::ast::Location;
+use ::diagnostics::Code;
+use ::diagnostics::Diagnostic;
+use ::diagnostics::Label;
+use ::source::Span;
+use super::Token;
+use super::TokenType;
+use std::fmt;
+
+/// A span of input that the lexer could not match against any token pattern.
+/// The lexer emits these as `TokenType::Error` tokens and carries on, so every error can be reported in one pass.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct LexError<'a, 'b> {
+    content: &'a str,
+    span: Span<'b>
+}
+
+impl <'a, 'b> LexError<'a, 'b> {
+    pub fn from_token(token: &Token<'a, 'b>) -> Option<Self> {
+        if token.get_type() != TokenType::Error {
+            return None;
+        }
+        Some(LexError {
+            content: token.get_content(),
+            span: token.get_span()
+        })
+    }
+
+    pub fn get_content(&self) -> &'a str {
+        self.content
+    }
+
+    pub fn get_span(&self) -> Span<'b> {
+        self.span
+    }
+
+    pub fn get_message(&self) -> String {
+        format!("unrecognised input {:?}", self.content)
+    }
+}
+
+impl <'a, 'b> fmt::Display for LexError<'a, 'b> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Lines and columns are stored zero-based, but people count from one.
+        write!(f, "{}:{}:{}: {}",
+            self.span.file.unwrap_or("<input>"),
+            self.span.start_line_col.0 + 1,
+            self.span.start_line_col.1 + 1,
+            self.get_message())
+    }
+}
+
+impl <'a, 'b> From<LexError<'a, 'b>> for Diagnostic {
+    fn from(err: LexError<'a, 'b>) -> Self {
+        Diagnostic::error(err.get_message())
+            .with_code(Code::UnrecognisedInput)
+            .with_label(Label::primary(&Location::from_span(&err.span), "not part of any litx syntax"))
+    }
+}
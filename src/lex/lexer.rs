@@ -1,5 +1,6 @@
-#![cfg_attr(feature = "cargo-clippy", allow(trivial_regex))]
+#![allow(clippy::trivial_regex)]
 
+use ::source::Span;
 use super::TokenType;
 use super::Token;
 use super::TokenBuilder;
@@ -17,30 +18,37 @@ decl_regex!(RGX_BLANK_LINE, r#"^(?m)\n\r?(\s*\n\r?)+"#);
 decl_regex!(RGX_CLOSE_CMNT, r#"^!\}"#);
 decl_regex!(RGX_CLOSE_EXPR, r#"^\}"#);
 decl_regex!(RGX_CLOSE_MATH, r#"^\$\}"#);
-decl_regex!(RGX_ESCAPED,    r#"^\\\S"#);
+decl_regex!(RGX_CLOSE_DISP, r#"^\$\$\}"#);
+// A backslash escapes the next character, or a whole `!}`, `$$}` or `$}` closer.
+decl_regex!(RGX_ESCAPED,    r#"^\\(!\}|\$\$\}|\$\}|\S)"#);
 decl_regex!(RGX_KEY_START,  r#"^:"#);
 decl_regex!(RGX_NEW_LINE,   r#"^(?m)\n\r?"#);
 decl_regex!(RGX_NUMBER,     r#"^[0-9]+(\.[0-9]+)?"#);
 decl_regex!(RGX_OPEN_CMNT,  r#"^\{!"#);
 decl_regex!(RGX_OPEN_EXPR,  r#"^\{"#);
 decl_regex!(RGX_OPEN_MATH,  r#"^\{\$"#);
+decl_regex!(RGX_OPEN_DISP,  r#"^\{\$\$"#);
 decl_regex!(RGX_QUOTE,      r#"^""#);
-decl_regex!(RGX_SPACE,      r#"^\s+"#);
+// Stop short of new lines so that trailing spaces can't swallow a blank line.
+decl_regex!(RGX_SPACE,      r#"^[^\S\n]+"#);
 // TODO: Double check the correctness of this.
-// So far: whitespace, quote, colon, {, }, $}, !), \ are forbidden.
-decl_regex!(RGX_WORD,       r#"^[^\s":\{\}(\$\})(!\})\\]+"#);
-decl_regex!(RGX_CHAR,       r#"^\S"#);
+// So far: whitespace, quote, colon, {, }, $}, !), \ and control characters are forbidden.
+decl_regex!(RGX_WORD,       r#"^[^\s\p{Cc}":\{\}(\$\})(!\})\\]+"#);
+// Control characters are never valid outside of whitespace, so they fall through to an error token.
+decl_regex!(RGX_CHAR,       r#"^[^\s\p{Cc}]"#);
 
 lazy_static! {
-    static ref REGEX_TOKENTYPE_PAIR: [(&'static Regex, TokenType); 15] = [
-        // Opening an expression needs to be checked after opening comments/maths.
+    static ref REGEX_TOKENTYPE_PAIR: [(&'static Regex, TokenType); 17] = [
+        // Opening an expression needs to be checked after opening comments/maths, and display maths before maths.
         // Otherwise, the order of the literals shouldn't really matter.
         // Here escaped characters are maximal priority though, to help guarantee their semantic meaning.
         (&RGX_ESCAPED, TokenType::Escaped),
         (&RGX_OPEN_CMNT, TokenType::OpenComment),
+        (&RGX_OPEN_DISP, TokenType::OpenDisplayMath),
         (&RGX_OPEN_MATH, TokenType::OpenMath),
         (&RGX_OPEN_EXPR, TokenType::OpenExpression),
         (&RGX_CLOSE_CMNT, TokenType::CloseComment),
+        (&RGX_CLOSE_DISP, TokenType::CloseDisplayMath),
         (&RGX_CLOSE_MATH, TokenType::CloseMath),
         (&RGX_CLOSE_EXPR, TokenType::CloseExpression),
         (&RGX_KEY_START, TokenType::KeyStart),
@@ -70,7 +78,7 @@ pub struct Lexer<'a, 'b> {
 impl <'a> Lexer<'a, 'static> {
     pub fn new(source_string: &'a str) -> Lexer<'a, 'static> {
         Lexer {
-            source_string: source_string,
+            source_string,
             source_filename: None,
             current_line: 0,
             current_line_index: 0,
@@ -83,7 +91,7 @@ impl <'a> Lexer<'a, 'static> {
 impl <'a, 'b> Lexer<'a, 'b> {
     pub fn new_with_filename(source_string: &'a str, source_filename: &'b str) -> Lexer<'a, 'b> {
         Lexer {
-            source_string: source_string,
+            source_string,
             source_filename: Some(source_filename),
             current_line: 0,
             current_line_index: 0,
@@ -101,33 +109,117 @@ impl <'a, 'b> Iterator for Lexer<'a, 'b> {
                 return None;
             } else {
                 self.eof_returned = true;
-                return Some(Token::new_eof(self.source_filename));
+                let span = Span::empty(self.source_filename, self.byte_index, (self.current_line, self.current_line_index));
+                return Some(Token::new_eof(span));
             }
         }
         let text = &self.source_string[self.byte_index..];
+        // `{$$}` is an empty formula, `{$` then `$}`, as it was before display math; the regexes can't look ahead.
+        if text.starts_with("{$$}") {
+            return Some(self.emit(TokenType::OpenMath, &text[..2]));
+        }
         for &(rgx, ty) in REGEX_TOKENTYPE_PAIR.iter() {
             if let Some(m) = rgx.find(text) {
                 assert_eq!(0, m.start());
-
-                let token = TokenBuilder::new()
-                    .with_type(ty)
-                    .with_content(m.as_str())
-                    .with_source_filename(self.source_filename)
-                    .with_index(self.byte_index)
-                    .with_line(self.current_line)
-                    .with_linespan(self.current_line_index, self.current_line_index + m.end())
-                    .build().unwrap();
-
-                self.byte_index += m.end();
-                self.current_line_index += m.end();
-                if ty == TokenType::NewLine || ty == TokenType::BlankLine {
-                    self.current_line += m.as_str().chars().filter(|&c| c == '\n').count();
-                    self.current_line_index = 0;
-                }
-                return Some(token);
+                return Some(self.emit(ty, m.as_str()));
             }
         }
-        // TODO: I need to figure out some way to indicate an error here.
-        unimplemented!();
+        // Nothing matched, so skip a single character and report it.
+        // Lexing resumes straight afterwards so that later errors are found in the same run.
+        let len = text.chars().next().map(char::len_utf8).unwrap_or(1);
+        Some(self.emit(TokenType::Error, &text[..len]))
+    }
+}
+
+impl <'a, 'b> Lexer<'a, 'b> {
+    fn emit(&mut self, ty: TokenType, content: &'a str) -> Token<'a, 'b> {
+        let token = TokenBuilder::new()
+            .with_type(ty)
+            .with_content(content)
+            .with_span(Span::covering(self.source_filename, self.byte_index, (self.current_line, self.current_line_index), content))
+            .build().unwrap();
+
+        self.byte_index += content.len();
+        self.current_line_index += content.len();
+        if ty == TokenType::NewLine || ty == TokenType::BlankLine {
+            self.current_line += content.chars().filter(|&c| c == '\n').count();
+            self.current_line_index = 0;
+        }
+        token
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Lexer;
+    use ::lex::LexError;
+    use ::lex::TokenType;
+
+    #[test]
+    fn control_character_is_error_token() {
+        let types: Vec<_> = Lexer::new("foo\u{7}bar baz").map(|tok| tok.get_type()).collect();
+        assert_eq!(types, vec![
+            TokenType::Word,
+            TokenType::Error,
+            TokenType::Word,
+            TokenType::Space,
+            TokenType::Word,
+            TokenType::EOF
+        ]);
+    }
+
+    #[test]
+    fn trailing_space_before_blank_line() {
+        let types: Vec<_> = Lexer::new("a \n\nb").map(|tok| tok.get_type()).collect();
+        assert_eq!(types, vec![
+            TokenType::Word,
+            TokenType::Space,
+            TokenType::BlankLine,
+            TokenType::Word,
+            TokenType::EOF
+        ]);
+    }
+
+    #[test]
+    fn reports_every_error() {
+        let errors: Vec<_> = Lexer::new_with_filename("a\u{0}\nb \u{1b}c", "test.litx")
+            .filter_map(|tok| LexError::from_token(&tok))
+            .collect();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].get_span().file, Some("test.litx"));
+        assert_eq!((errors[0].get_span().start, errors[0].get_span().start_line_col), (1, (0, 1)));
+        assert_eq!((errors[1].get_span().start, errors[1].get_span().start_line_col), (5, (1, 2)));
+        assert_eq!(errors[1].to_string(), "test.litx:2:3: unrecognised input \"\\u{1b}\"");
+    }
+
+    #[test]
+    fn escaped_closers() {
+        let tokens: Vec<_> = Lexer::new("a\\!} b\\$}\\}\\$$}").map(|tok| (tok.get_type(), tok.get_content())).collect();
+        assert_eq!(tokens, vec![
+            (TokenType::Word, "a"),
+            (TokenType::Escaped, "\\!}"),
+            (TokenType::Space, " "),
+            (TokenType::Word, "b"),
+            (TokenType::Escaped, "\\$}"),
+            (TokenType::Escaped, "\\}"),
+            (TokenType::Escaped, "\\$$}"),
+            (TokenType::EOF, "EOF")
+        ]);
+    }
+
+    #[test]
+    fn empty_math_is_not_display_math() {
+        let tokens: Vec<_> = Lexer::new("{$$} {$$ x $$}").map(|tok| (tok.get_type(), tok.get_content())).collect();
+        assert_eq!(tokens, vec![
+            (TokenType::OpenMath, "{$"),
+            (TokenType::CloseMath, "$}"),
+            (TokenType::Space, " "),
+            (TokenType::OpenDisplayMath, "{$$"),
+            (TokenType::Space, " "),
+            (TokenType::Word, "x"),
+            (TokenType::Space, " "),
+            (TokenType::CloseDisplayMath, "$$}"),
+            (TokenType::EOF, "EOF")
+        ]);
     }
 }
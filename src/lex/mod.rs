@@ -1,7 +1,9 @@
+mod lex_error;
 mod lexer;
 mod token;
 mod token_type;
 
+pub use self::lex_error::LexError;
 pub use self::lexer::Lexer;
 pub use self::token::Token;
 pub use self::token::TokenBuilder;
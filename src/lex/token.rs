@@ -1,4 +1,5 @@
 use ::graphviz::Graphviz;
+use ::source::Span;
 use super::TokenType;
 use std::io;
 
@@ -8,21 +9,16 @@ use std::io;
 pub struct Token<'a, 'b> {
     ty: TokenType,
     content: &'a str,
-    source_filename: Option<&'b str>,
-    index: usize,
-    line: usize,
-    linespan: (usize, usize),
+    span: Span<'b>
 }
 
 impl <'b> Token<'static, 'b> {
-    pub fn new_eof(source_filename: Option<&'b str>) -> Self {
+    /// The end of the input; `span` should be the empty span just past the last byte.
+    pub fn new_eof(span: Span<'b>) -> Self {
         Token {
             ty: TokenType::EOF,
             content: "EOF",
-            source_filename: source_filename,
-            index: 0,
-            line: 0,
-            linespan: (0, 3)
+            span
         }
     }
 }
@@ -35,12 +31,16 @@ impl <'a, 'b> Token<'a, 'b> {
     pub fn get_content(&self) -> &'a str {
         self.content
     }
+
+    pub fn get_span(&self) -> Span<'b> {
+        self.span
+    }
 }
 
 
 impl <'a, 'b, W: io::Write> Graphviz<W> for Token<'a, 'b> {
     fn get_vertex_name(&self) -> String {
-        format!("{:?}_{}_{}", self.ty, self.source_filename.unwrap_or(""), self.index)
+        format!("{:?}_{}_{}", self.ty, self.span.file.unwrap_or(""), self.span.start)
     }
 }
 
@@ -50,10 +50,7 @@ impl <'a, 'b, W: io::Write> Graphviz<W> for Token<'a, 'b> {
 pub struct TokenBuilder<'a, 'b> {
     ty: Option<TokenType>,
     content: Option<&'a str>,
-    source_filename: Option<Option<&'b str>>,
-    index: Option<usize>,
-    line: Option<usize>,
-    linespan: Option<(usize, usize)>
+    span: Option<Span<'b>>
 }
 
 impl <'a, 'b> TokenBuilder<'a, 'b> {
@@ -62,19 +59,10 @@ impl <'a, 'b> TokenBuilder<'a, 'b> {
     }
 
     pub fn build(&self) -> Option<Token<'a, 'b>> {
-        macro_rules! extract {
-            ($id:expr) => {
-                if let Some(val) = $id { val }
-                else { return None; }
-            }
-        }
         Some(Token {
-            ty: extract!(self.ty),
-            content: extract!(self.content),
-            source_filename: extract!(self.source_filename),
-            index: extract!(self.index),
-            line: extract!(self.line),
-            linespan: extract!(self.linespan)
+            ty: self.ty?,
+            content: self.content?,
+            span: self.span?
         })
     }
 
@@ -88,23 +76,8 @@ impl <'a, 'b> TokenBuilder<'a, 'b> {
         self
     }
 
-    pub fn with_source_filename(mut self, source_filename: Option<&'b str>) -> Self {
-        self.source_filename = Some(source_filename);
-        self
-    }
-
-    pub fn with_index(mut self, index: usize) -> Self {
-        self.index = Some(index);
-        self
-    }
-
-    pub fn with_line(mut self, line: usize) -> Self {
-        self.line = Some(line);
-        self
-    }
-
-    pub fn with_linespan(mut self, from: usize, to: usize) -> Self {
-        self.linespan = Some((from, to));
+    pub fn with_span(mut self, span: Span<'b>) -> Self {
+        self.span = Some(span);
         self
     }
 }
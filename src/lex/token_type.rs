@@ -5,6 +5,7 @@ pub enum TokenType {
     BlankLine,
     Char,
     CloseComment,
+    CloseDisplayMath,
     CloseExpression,
     CloseMath,
     Escaped,
@@ -12,10 +13,12 @@ pub enum TokenType {
     NewLine,
     Number,
     OpenComment,
+    OpenDisplayMath,
     OpenExpression,
     OpenMath,
     Quote,
     Space,
     Word,
+    Error,
     EOF
 }
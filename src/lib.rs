@@ -3,7 +3,17 @@ extern crate lazy_static;
 extern crate regex;
 
 
+pub mod ast;
+pub mod bibliography;
+pub mod diagnostics;
+pub mod eval;
+pub mod hyphenation;
+pub mod import;
 pub mod lex;
+pub mod math;
+pub mod meta;
 pub mod parse;
+pub mod render;
+pub mod source;
 pub mod graphviz;
 mod unslice;
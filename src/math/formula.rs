@@ -0,0 +1,34 @@
+/// Evaluated `{$ ... $}` content: the math as a tree, and its source for backends which only want it verbatim.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Formula {
+    pub nodes: Vec<MathNode>,
+    pub source: String
+}
+
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum MathNode {
+    /// A variable, which is a single letter, such as `x` or `α` from `\alpha`.
+    Identifier(String),
+    /// The name of a function, such as `sin` from `\sin`, which is set upright.
+    Function(String),
+    Number(String),
+    /// A symbol such as `+`, `=` or `(`.
+    Operator(String),
+    /// The content of an expression embedded in the math, set as ordinary text.
+    Text(String),
+    /// A nested `{$ ... $}` group, which acts as a single term.
+    Row(Vec<MathNode>),
+    Scripts {
+        base: Box<MathNode>,
+        subscript: Option<Box<MathNode>>,
+        superscript: Option<Box<MathNode>>
+    },
+    Fraction {
+        numerator: Box<MathNode>,
+        denominator: Box<MathNode>
+    }
+}
@@ -0,0 +1,7 @@
+mod formula;
+mod parser;
+
+pub use self::formula::Formula;
+pub use self::formula::MathNode;
+pub use self::parser::command_name;
+pub use self::parser::parse;
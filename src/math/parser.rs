@@ -0,0 +1,237 @@
+use ::ast::Expr;
+use ::ast::Math;
+use ::ast::MathTerm;
+use std::iter::Peekable;
+use std::vec;
+use super::Formula;
+use super::MathNode;
+
+/// Greek letters by their TeX names.
+const GREEK: [(&str, char); 40] = [
+    ("alpha", 'α'), ("beta", 'β'), ("gamma", 'γ'), ("delta", 'δ'), ("epsilon", 'ϵ'), ("varepsilon", 'ε'),
+    ("zeta", 'ζ'), ("eta", 'η'), ("theta", 'θ'), ("vartheta", 'ϑ'), ("iota", 'ι'), ("kappa", 'κ'),
+    ("lambda", 'λ'), ("mu", 'μ'), ("nu", 'ν'), ("xi", 'ξ'), ("pi", 'π'), ("rho", 'ρ'), ("sigma", 'σ'),
+    ("tau", 'τ'), ("upsilon", 'υ'), ("phi", 'ϕ'), ("varphi", 'φ'), ("chi", 'χ'), ("psi", 'ψ'), ("omega", 'ω'),
+    ("Gamma", 'Γ'), ("Delta", 'Δ'), ("Theta", 'Θ'), ("Lambda", 'Λ'), ("Xi", 'Ξ'), ("Pi", 'Π'), ("Sigma", 'Σ'),
+    ("Upsilon", 'Υ'), ("Phi", 'Φ'), ("Psi", 'Ψ'), ("Omega", 'Ω'),
+    ("infty", '∞'), ("partial", '∂'), ("nabla", '∇')
+];
+
+/// Operators and relations by their TeX names.
+const OPERATORS: [(&str, char); 36] = [
+    ("cdot", '⋅'), ("times", '×'), ("div", '÷'), ("pm", '±'), ("mp", '∓'), ("ast", '∗'),
+    ("leq", '≤'), ("le", '≤'), ("geq", '≥'), ("ge", '≥'), ("neq", '≠'), ("ne", '≠'),
+    ("approx", '≈'), ("equiv", '≡'), ("sim", '∼'), ("propto", '∝'),
+    ("to", '→'), ("rightarrow", '→'), ("leftarrow", '←'), ("Rightarrow", '⇒'), ("iff", '⇔'),
+    ("in", '∈'), ("notin", '∉'), ("subset", '⊂'), ("subseteq", '⊆'), ("cup", '∪'), ("cap", '∩'),
+    ("forall", '∀'), ("exists", '∃'), ("neg", '¬'), ("land", '∧'), ("lor", '∨'),
+    ("sum", '∑'), ("prod", '∏'), ("int", '∫'), ("ldots", '…')
+];
+
+const FUNCTIONS: [&str; 20] = [
+    "sin", "cos", "tan", "sec", "csc", "cot", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh",
+    "log", "ln", "exp", "lim", "max", "min", "det", "gcd"
+];
+
+enum Token {
+    Atom(MathNode),
+    Superscript,
+    Subscript,
+    Fraction
+}
+
+/// Interprets the terms of `math` as a tree. Expressions embedded in it are turned into text by `evaluate`.
+///
+/// Letters are variables and runs of digits are numbers; anything else is an operator. `^` and `_` attach the term
+/// after them to the one before as a superscript or subscript, and `\frac` takes the next two terms as a fraction.
+/// A nested `{$ ... $}` groups its contents into a single term. A backslash before a letter starts a TeX-style name,
+/// such as `\alpha` or `\leq`, and before anything else stands for that character.
+pub fn parse<F: FnMut(&Expr) -> String>(math: &Math, evaluate: &mut F) -> Result<Formula, String> {
+    Ok(Formula {
+        nodes: parse_terms(&math.terms, evaluate)?,
+        source: math.source.clone()
+    })
+}
+
+fn parse_terms<F: FnMut(&Expr) -> String>(terms: &[MathTerm], evaluate: &mut F) -> Result<Vec<MathNode>, String> {
+    let mut tokens = tokenize(terms, evaluate)?.into_iter().peekable();
+    let mut nodes = Vec::new();
+    while tokens.peek().is_some() {
+        nodes.push(parse_scripted(&mut tokens)?);
+    }
+    Ok(nodes)
+}
+
+fn tokenize<F: FnMut(&Expr) -> String>(terms: &[MathTerm], evaluate: &mut F) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    // The letters at the start of a text term which belong to the name before it.
+    let mut skip = 0;
+    for (i, term) in terms.iter().enumerate() {
+        let mut next_skip = 0;
+        match *term {
+            MathTerm::Text(ref text) => tokenize_text(&text[skip..], &mut tokens),
+            MathTerm::Escaped(ref escaped) if escaped.chars().all(char::is_alphabetic) => {
+                // The lexer only takes one character after a backslash, so the rest of the name is the next text.
+                let rest = match terms.get(i + 1) {
+                    Some(MathTerm::Text(next)) => &next[..next.find(|c: char| !c.is_alphabetic()).unwrap_or(next.len())],
+                    _ => ""
+                };
+                next_skip = rest.len();
+                tokens.push(command(&format!("{}{}", escaped, rest))?);
+            },
+            MathTerm::Escaped(ref escaped) => tokens.push(Token::Atom(MathNode::Operator(escaped.clone()))),
+            MathTerm::Space => {},
+            MathTerm::Expr(ref expr) => tokens.push(Token::Atom(MathNode::Text(evaluate(expr)))),
+            MathTerm::Math(ref math) => tokens.push(Token::Atom(MathNode::Row(parse_terms(&math.terms, evaluate)?)))
+        }
+        skip = next_skip;
+    }
+    Ok(tokens)
+}
+
+fn tokenize_text(text: &str, tokens: &mut Vec<Token>) {
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '0'..='9' => {
+                let mut number = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    let decimal_point = next == '.' && chars.clone().nth(1).is_some_and(|after| after.is_ascii_digit());
+                    if !next.is_ascii_digit() && !decimal_point {
+                        break;
+                    }
+                    number.push(next);
+                    chars.next();
+                }
+                Token::Atom(MathNode::Number(number))
+            },
+            '^' => Token::Superscript,
+            '_' => Token::Subscript,
+            '-' => Token::Atom(MathNode::Operator("\u{2212}".to_string())),
+            '*' => Token::Atom(MathNode::Operator("\u{2217}".to_string())),
+            '\'' => Token::Atom(MathNode::Operator("\u{2032}".to_string())),
+            _ if c.is_alphabetic() => Token::Atom(MathNode::Identifier(c.to_string())),
+            _ => Token::Atom(MathNode::Operator(c.to_string()))
+        };
+        tokens.push(token);
+    }
+}
+
+fn command(name: &str) -> Result<Token, String> {
+    if name == "frac" {
+        return Ok(Token::Fraction);
+    }
+    if let Some(&(_, letter)) = GREEK.iter().find(|&&(greek, _)| greek == name) {
+        return Ok(Token::Atom(MathNode::Identifier(letter.to_string())));
+    }
+    if let Some(&(_, symbol)) = OPERATORS.iter().find(|&&(operator, _)| operator == name) {
+        return Ok(Token::Atom(MathNode::Operator(symbol.to_string())));
+    }
+    if FUNCTIONS.contains(&name) {
+        return Ok(Token::Atom(MathNode::Function(name.to_string())));
+    }
+    Err(format!("unknown math command `\\{}`", name))
+}
+
+/// The TeX name of a Greek letter or operator symbol, such as `alpha` for `α`, if it has one.
+pub fn command_name(symbol: &str) -> Option<&'static str> {
+    GREEK.iter().chain(OPERATORS.iter())
+        .find(|&&(_, c)| symbol.chars().eq(Some(c)))
+        .map(|&(name, _)| name)
+}
+
+/// A term and any scripts attached to it.
+fn parse_scripted(tokens: &mut Peekable<vec::IntoIter<Token>>) -> Result<MathNode, String> {
+    let base = parse_term(tokens, "")?;
+    let mut subscript = None;
+    let mut superscript = None;
+    loop {
+        let (script, name) = match tokens.peek() {
+            Some(&Token::Superscript) => (&mut superscript, "`^`"),
+            Some(&Token::Subscript) => (&mut subscript, "`_`"),
+            _ => break
+        };
+        tokens.next();
+        if script.is_some() {
+            return Err(format!("{} is given twice for the same term", name));
+        }
+        *script = Some(Box::new(parse_term(tokens, name)?));
+    }
+    if subscript.is_none() && superscript.is_none() {
+        Ok(base)
+    } else {
+        Ok(MathNode::Scripts { base: Box::new(base), subscript, superscript })
+    }
+}
+
+/// A single term, which `after` needs if it is not empty.
+fn parse_term(tokens: &mut Peekable<vec::IntoIter<Token>>, after: &str) -> Result<MathNode, String> {
+    match tokens.next() {
+        Some(Token::Atom(node)) => Ok(node),
+        Some(Token::Fraction) => {
+            let missing = |_| "`\\frac` needs a numerator and a denominator after it".to_string();
+            let numerator = parse_term(tokens, "`\\frac`").map_err(missing)?;
+            let denominator = parse_term(tokens, "`\\frac`").map_err(missing)?;
+            Ok(MathNode::Fraction { numerator: Box::new(numerator), denominator: Box::new(denominator) })
+        },
+        Some(Token::Superscript) if after.is_empty() => Err("`^` needs a term before it".to_string()),
+        Some(Token::Subscript) if after.is_empty() => Err("`_` needs a term before it".to_string()),
+        Some(_) | None => Err(format!("{} needs a term after it", after))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::ast::Inline;
+    use ::lex::Lexer;
+    use ::parse::parse;
+    use super::*;
+
+    fn formula(source: &str) -> Result<Vec<MathNode>, String> {
+        let doc = ::ast::lower(&parse(Lexer::new(source)).unwrap());
+        match doc.paragraphs[0].items[0] {
+            Inline::Math(ref math) => super::parse(math, &mut |expr| format!("[{}]", expr)).map(|formula| formula.nodes),
+            _ => panic!("not math")
+        }
+    }
+
+    fn atom(kind: fn(String) -> MathNode, text: &str) -> MathNode {
+        kind(text.to_string())
+    }
+
+    #[test]
+    fn builds_tree() {
+        use super::MathNode::*;
+        assert_eq!(formula("{$ \\alpha^2 + x_{$i+1$} \\leq 3.5 $}").unwrap(), vec![
+            Scripts { base: Box::new(atom(Identifier, "α")), subscript: None, superscript: Some(Box::new(atom(Number, "2"))) },
+            atom(Operator, "+"),
+            Scripts {
+                base: Box::new(atom(Identifier, "x")),
+                subscript: Some(Box::new(Row(vec![atom(Identifier, "i"), atom(Operator, "+"), atom(Number, "1")]))),
+                superscript: None
+            },
+            atom(Operator, "≤"),
+            atom(Number, "3.5")
+        ]);
+        assert_eq!(formula("{$ \\frac{$a$}2 \\sin{x} - y. $}").unwrap(), vec![
+            Fraction { numerator: Box::new(Row(vec![atom(Identifier, "a")])), denominator: Box::new(atom(Number, "2")) },
+            atom(Function, "sin"),
+            atom(Text, "[{x}]"),
+            atom(Operator, "\u{2212}"),
+            atom(Identifier, "y"),
+            atom(Operator, ".")
+        ]);
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert_eq!(formula("{$ \\alpah $}"), Err("unknown math command `\\alpah`".to_string()));
+        assert_eq!(formula("{$ x^ $}"), Err("`^` needs a term after it".to_string()));
+        assert_eq!(formula("{$ _2 $}"), Err("`_` needs a term before it".to_string()));
+        assert_eq!(formula("{$ x^2^3 $}"), Err("`^` is given twice for the same term".to_string()));
+        assert_eq!(formula("{$ \\frac 1 $}"), Err("`\\frac` needs a numerator and a denominator after it".to_string()));
+        assert_eq!(formula("{$ \\{x\\} $}").unwrap(), vec![
+            MathNode::Operator("{".to_string()), MathNode::Identifier("x".to_string()), MathNode::Operator("}".to_string())
+        ]);
+    }
+}
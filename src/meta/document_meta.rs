@@ -0,0 +1,180 @@
+use ::ast::Document;
+use ::ast::Expr;
+use ::ast::Inline;
+use ::ast::Prop;
+use ::ast::Value;
+use super::MetaError;
+
+/// Document-wide settings declared by a leading `{litx ...}` expression.
+/// Every backend should read these from here rather than inspecting the header itself.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(PartialEq)]
+pub struct DocumentMeta {
+    pub doctype: Option<String>,
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    pub date: Option<String>,
+    pub language: Option<String>,
+    /// The bibliography databases citations are looked up in, relative to the document.
+    pub bibliography: Vec<String>,
+    /// How citations are formatted, such as `mla`. Without it, a doctype naming a citation style chooses it.
+    pub citation_style: Option<String>
+}
+
+impl DocumentMeta {
+    /// The `{litx ...}` expression at the very start of `doc`, if there is one.
+    pub fn header(doc: &Document) -> Option<&Expr> {
+        doc.paragraphs.first()
+            .and_then(|paragraph| paragraph.items.first())
+            .and_then(|item| match *item {
+                Inline::Expr(ref expr) if is_named(expr, "litx") => Some(expr),
+                _ => None
+            })
+    }
+
+    /// Reads the header of `doc`. A document without one gets the default, empty metadata.
+    pub fn from_document(doc: &Document) -> Result<DocumentMeta, Vec<MetaError>> {
+        match DocumentMeta::header(doc) {
+            Some(header) => DocumentMeta::from_header(header),
+            None => Ok(Default::default())
+        }
+    }
+
+    pub fn from_header(header: &Expr) -> Result<DocumentMeta, Vec<MetaError>> {
+        let mut meta = DocumentMeta::default();
+        let mut errors = Vec::new();
+        if let Some(arg) = header.args.first() {
+            errors.push(MetaError {
+                message: "the `litx` header only takes `:key value` properties".to_string(),
+                location: arg.location().clone()
+            });
+        }
+        for (i, prop) in header.props.iter().enumerate() {
+            if header.props[..i].iter().any(|prev| prev.key.name == prop.key.name) {
+                errors.push(error(prop, format!("`{}` is set more than once", prop.key.name)));
+                continue;
+            }
+            let result = match prop.key.name.as_str() {
+                "doctype" => text(prop).map(|value| meta.doctype = Some(value)),
+                "title" => text(prop).map(|value| meta.title = Some(value)),
+                "date" => text(prop).map(|value| meta.date = Some(value)),
+                "lang" => text(prop).map(|value| meta.language = Some(value)),
+                "author" | "authors" => text_list(prop).map(|mut value| meta.authors.append(&mut value)),
+                "bibliography" => text_list(prop).map(|value| meta.bibliography = value),
+                "citation-style" => text(prop).map(|value| meta.citation_style = Some(value)),
+                key => Err(error(prop, format!("unknown key `{}` in the `litx` header", key)))
+            };
+            if let Err(err) = result {
+                errors.push(err);
+            }
+        }
+        if header.props.iter().any(|prop| prop.key.name == "author")
+            && header.props.iter().any(|prop| prop.key.name == "authors") {
+            errors.push(MetaError {
+                message: "use either `author` or `authors`, not both".to_string(),
+                location: header.location.clone()
+            });
+        }
+        if errors.is_empty() {
+            Ok(meta)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn is_named(expr: &Expr, name: &str) -> bool {
+    expr.name.as_ref().map(|ident| ident.name == name).unwrap_or(false)
+}
+
+fn error(prop: &Prop, message: String) -> MetaError {
+    MetaError {
+        message,
+        location: prop.location.clone()
+    }
+}
+
+fn describe(value: &Value) -> &'static str {
+    match *value {
+        Value::Ident(_) => "an identifier",
+        Value::String(_) => "a string",
+        Value::Number(_) => "a number",
+        Value::Expr(_) => "an expression",
+        Value::Math(_) => "math"
+    }
+}
+
+fn as_text(value: &Value) -> Option<String> {
+    match *value {
+        Value::Ident(ref ident) => Some(ident.name.clone()),
+        Value::String(ref string) => Some(string.value.clone()),
+        _ => None
+    }
+}
+
+fn text(prop: &Prop) -> Result<String, MetaError> {
+    as_text(&prop.value).ok_or_else(|| {
+        error(prop, format!("`{}` expects a string, got {}", prop.key.name, describe(&prop.value)))
+    })
+}
+
+/// A single string, or a `{list ...}` of them.
+fn text_list(prop: &Prop) -> Result<Vec<String>, MetaError> {
+    match prop.value {
+        Value::Expr(ref list) if is_named(list, "list") && list.props.is_empty() => {
+            list.args.iter()
+                .map(|item| as_text(item).ok_or_else(|| MetaError {
+                    message: format!("`{}` expects a list of strings, got {} in the list", prop.key.name, describe(item)),
+                    location: item.location().clone()
+                }))
+                .collect()
+        },
+        _ => as_text(&prop.value).map(|value| vec![value]).ok_or_else(|| {
+            error(prop, format!("`{}` expects a string or a `list` of strings, got {}", prop.key.name, describe(&prop.value)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::lex::Lexer;
+    use ::parse::parse;
+    use super::DocumentMeta;
+
+    fn meta(source: &str) -> Result<DocumentMeta, Vec<String>> {
+        let doc = ::ast::lower(&parse(Lexer::new(source)).unwrap());
+        DocumentMeta::from_document(&doc)
+            .map_err(|errors| errors.iter().map(|err| err.to_string()).collect())
+    }
+
+    #[test]
+    fn reads_header() {
+        let meta = meta("{litx\n    :doctype mla\n    :title \"Example\"\n    :authors {list \"A\" \"B\"}\n    :date \"2017-03-01\"\n    :lang en}\n\nText.").unwrap();
+        assert_eq!(meta, DocumentMeta {
+            doctype: Some("mla".to_string()),
+            title: Some("Example".to_string()),
+            authors: vec!["A".to_string(), "B".to_string()],
+            date: Some("2017-03-01".to_string()),
+            language: Some("en".to_string()),
+            bibliography: Vec::new(),
+            citation_style: None
+        });
+    }
+
+    #[test]
+    fn missing_header() {
+        assert_eq!(meta("Text {litx :title \"x\"}.").unwrap(), DocumentMeta::default());
+    }
+
+    #[test]
+    fn reports_bad_keys_and_types() {
+        assert_eq!(meta("{litx :title {$x$} :colour red :authors {list \"A\" 3} :title \"x\"}").unwrap_err(), vec![
+            "<input>:1:7: `title` expects a string, got math",
+            "<input>:1:20: unknown key `colour` in the `litx` header",
+            "<input>:1:51: `authors` expects a list of strings, got a number in the list",
+            "<input>:1:54: `title` is set more than once"
+        ]);
+    }
+}
@@ -0,0 +1,17 @@
+use ::ast::Location;
+use std::fmt;
+
+/// A problem with the `{litx ...}` header, such as an unknown key or a value of the wrong type.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct MetaError {
+    pub message: String,
+    pub location: Location
+}
+
+impl fmt::Display for MetaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
@@ -0,0 +1,5 @@
+mod document_meta;
+mod meta_error;
+
+pub use self::document_meta::DocumentMeta;
+pub use self::meta_error::MetaError;
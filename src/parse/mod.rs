@@ -1,13 +1,24 @@
+mod parse_error;
 mod parser;
 mod production;
 mod production_type;
 
 use self::parser::Parser;
+pub use self::parse_error::ParseError;
 pub use self::production::Branch;
 pub use self::production::Production;
 pub use self::production_type::ProductionType;
 
-pub fn parse<'a, 'b, I>(iter: I) -> Production<'a, 'b>
+/// Parses a token stream into a concrete syntax tree.
+/// Parsing always runs to the end of the input, so every error in the document is returned together.
+pub fn parse<'a, 'b, I>(iter: I) -> Result<Production<'a, 'b>, Vec<ParseError<'a, 'b>>>
 where I: Iterator<Item=::lex::Token<'a, 'b>> {
-    Parser::new(iter).parse_s()
+    let mut parser = Parser::new(iter);
+    let tree = parser.parse_s();
+    let errors = parser.into_errors();
+    if errors.is_empty() {
+        Ok(tree)
+    } else {
+        Err(errors)
+    }
 }
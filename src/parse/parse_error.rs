@@ -0,0 +1,131 @@
+use ::ast::Location;
+use ::diagnostics::Code;
+use ::diagnostics::Diagnostic;
+use ::diagnostics::Label;
+use ::lex::LexError;
+use ::lex::Token;
+use ::lex::TokenType;
+use std::fmt;
+
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum ParseError<'a, 'b> {
+    /// The lexer could not make sense of part of the input.
+    Lex(LexError<'a, 'b>),
+    /// An opening token was never matched by its closer; `found` is where the parser gave up looking.
+    Unclosed { open: Token<'a, 'b>, found: Token<'a, 'b> },
+    /// A closing token which does not close anything.
+    Unmatched { close: Token<'a, 'b> },
+    /// A token the grammar does not allow at this point.
+    Unexpected { found: Token<'a, 'b>, expected: &'static str }
+}
+
+impl <'a, 'b> ParseError<'a, 'b> {
+    /// The token the error should be reported against, if the error came from the parser.
+    pub fn get_token(&self) -> Option<&Token<'a, 'b>> {
+        match *self {
+            ParseError::Lex(_) => None,
+            ParseError::Unclosed { ref found, .. } => Some(found),
+            ParseError::Unmatched { ref close } => Some(close),
+            ParseError::Unexpected { ref found, .. } => Some(found)
+        }
+    }
+
+    pub fn get_code(&self) -> Code {
+        match *self {
+            ParseError::Lex(_) => Code::UnrecognisedInput,
+            ParseError::Unclosed { ref open, .. } => match open.get_type() {
+                TokenType::OpenComment => Code::UnclosedComment,
+                TokenType::OpenMath | TokenType::OpenDisplayMath => Code::UnclosedMath,
+                TokenType::Quote => Code::UnclosedString,
+                _ => Code::UnclosedExpression
+            },
+            ParseError::Unmatched { .. } => Code::UnmatchedCloser,
+            ParseError::Unexpected { .. } => Code::UnexpectedToken
+        }
+    }
+
+    pub fn get_message(&self) -> String {
+        match *self {
+            ParseError::Lex(ref err) => err.get_message(),
+            ParseError::Unclosed { ref open, ref found } => {
+                let (closer, name) = describe_opener(open.get_type());
+                format!("expected `{}` to close {} opened at line {}, found {}",
+                    closer, name, open.get_span().start_line_col.0 + 1, describe_token(found))
+            },
+            ParseError::Unmatched { ref close } => format!("unmatched `{}`", close.get_content()),
+            ParseError::Unexpected { ref found, expected } => {
+                format!("expected {}, found {}", expected, describe_token(found))
+            }
+        }
+    }
+}
+
+impl <'a, 'b> fmt::Display for ParseError<'a, 'b> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::Lex(ref err) => write!(f, "{}", err),
+            _ => {
+                let span = self.get_token().unwrap().get_span();
+                write!(f, "{}:{}:{}: {}",
+                    span.file.unwrap_or("<input>"),
+                    span.start_line_col.0 + 1,
+                    span.start_line_col.1 + 1,
+                    self.get_message())
+            }
+        }
+    }
+}
+
+impl <'a, 'b> From<ParseError<'a, 'b>> for Diagnostic {
+    fn from(err: ParseError<'a, 'b>) -> Self {
+        let message = err.get_message();
+        let code = err.get_code();
+        match err {
+            ParseError::Lex(err) => Diagnostic::from(err),
+            ParseError::Unclosed { open, found } => {
+                let (closer, name) = describe_opener(open.get_type());
+                Diagnostic::error(message)
+                    .with_code(code)
+                    .with_label(Label::primary(&Location::from_token(&found), ""))
+                    .with_label(Label::secondary(&Location::from_token(&open), format!("unclosed {} opened here", name)))
+                    .with_help(format!("add `{}` to close the {}", closer, name))
+            },
+            ParseError::Unmatched { close } => {
+                // Escaping the final `}` is enough to stop any closer from being one.
+                let content = close.get_content();
+                Diagnostic::error(message)
+                    .with_code(code)
+                    .with_label(Label::primary(&Location::from_token(&close), "nothing to close here"))
+                    .with_help(format!("to write it as text, escape the brace: `{}\\}}`", &content[..content.len() - 1]))
+            },
+            ParseError::Unexpected { found, .. } => {
+                Diagnostic::error(message)
+                    .with_code(code)
+                    .with_label(Label::primary(&Location::from_token(&found), ""))
+            }
+        }
+    }
+}
+
+fn describe_opener(ty: TokenType) -> (&'static str, &'static str) {
+    match ty {
+        TokenType::OpenComment => ("!}", "comment"),
+        TokenType::OpenExpression => ("}", "expression"),
+        TokenType::OpenMath => ("$}", "math"),
+        TokenType::OpenDisplayMath => ("$$}", "display math"),
+        TokenType::Quote => ("\"", "string"),
+        _ => unreachable!()
+    }
+}
+
+fn describe_token(token: &Token) -> String {
+    match token.get_type() {
+        TokenType::EOF => "end of file".to_string(),
+        TokenType::BlankLine => "a blank line".to_string(),
+        TokenType::NewLine => "a new line".to_string(),
+        TokenType::Space => "whitespace".to_string(),
+        _ => format!("`{}`", token.get_content())
+    }
+}
@@ -1,51 +1,119 @@
+use ::lex::LexError;
 use ::lex::Token;
 use ::lex::TokenType;
+use ::source::Span;
 use super::Branch;
+use super::ParseError;
 use super::Production;
 use super::ProductionType;
 use std::iter::Peekable;
 
 /// This is a somewhat mechanical implementation of the LL(1) CFG specified by `grammar.cfg`.
 /// Small cleanups have been made to keep the parse tree relatively simple, but otherwise it is a fairly faithful rendition.
+///
+/// Errors never abort the parse: they are collected, stray tokens are dropped,
+/// and unfinished expressions, maths and strings are abandoned at a blank line or at the closer of an enclosing context.
 #[derive(Debug)]
 pub struct Parser<'a, 'b, I: Iterator<Item=Token<'a, 'b>>> {
-    iter: Peekable<I>
+    iter: Peekable<I>,
+    closers: Vec<TokenType>,
+    errors: Vec<ParseError<'a, 'b>>
 }
 
 impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
     pub fn new(iter: I) -> Self {
         Parser {
-            iter: iter.peekable()
+            iter: iter.peekable(),
+            closers: Vec::new(),
+            errors: Vec::new()
         }
     }
 
+    pub fn into_errors(self) -> Vec<ParseError<'a, 'b>> {
+        self.errors
+    }
+
     pub fn pop_token(&mut self) -> Token<'a, 'b> {
+        self.skip_lex_errors();
         self.iter.next().unwrap()
     }
 
+    pub fn peek_token(&mut self) -> Token<'a, 'b> {
+        self.skip_lex_errors();
+        self.iter.peek()
+            .cloned()
+            .unwrap_or_else(|| Token::new_eof(Span::empty(None, 0, (0, 0))))
+    }
+
     pub fn peek_type(&mut self) -> TokenType {
+        self.skip_lex_errors();
         self.iter.peek()
             .map(|tok| tok.get_type())
             .unwrap_or(TokenType::EOF)
     }
 
+    /// Error tokens are valid nowhere in the grammar, so they are reported and dropped before the parser sees them.
+    fn skip_lex_errors(&mut self) {
+        while self.iter.peek().map(|tok| tok.get_type()) == Some(TokenType::Error) {
+            let token = self.iter.next().unwrap();
+            self.errors.push(ParseError::Lex(LexError::from_token(&token).unwrap()));
+        }
+    }
+
+    /// Reports and drops a token which cannot appear at this point.
+    fn skip_unexpected(&mut self, expected: &'static str) {
+        let found = self.pop_token();
+        self.errors.push(match found.get_type() {
+            TokenType::CloseComment
+            | TokenType::CloseExpression
+            | TokenType::CloseMath
+            | TokenType::CloseDisplayMath => ParseError::Unmatched { close: found },
+            _ => ParseError::Unexpected { found, expected }
+        });
+    }
+
+    /// Whether an open expression or math has to stop consuming tokens here.
+    fn peek_sync(&mut self) -> bool {
+        let ty = self.peek_type();
+        ty == TokenType::EOF
+        || ty == TokenType::BlankLine
+        || self.closers.contains(&ty)
+    }
+
+    /// Finishes `production` with the closer matching `open`, or reports that it was left open.
+    fn close(&mut self, production: &mut Production<'a, 'b>, open: Token<'a, 'b>, closer: TokenType) {
+        if self.peek_type() == closer {
+            production.push_terminal(self.pop_token());
+        } else {
+            let found = self.peek_token();
+            self.errors.push(ParseError::Unclosed { open, found });
+        }
+    }
+
     pub fn parse_s(&mut self) -> Production<'a, 'b> {
-        assert!(self.peek_s());
         let mut s = Production::new_nonterminal(ProductionType::S);
         if self.peek_aws() {
             s.push_production(self.parse_aws());
         }
-        s.push_production(self.parse_blocks());
+        loop {
+            if self.peek_blocks() {
+                s.push_production(self.parse_blocks());
+            }
+            if self.peek_type() == TokenType::EOF {
+                break;
+            }
+            // Blocks only stop early at a closer with no opener; drop it and carry on with the rest of the document.
+            self.skip_unexpected("a block");
+            if self.peek_aws() {
+                s.push_production(self.parse_aws());
+            }
+        }
         s.push_terminal(self.pop_token());
         s
     }
 
-    pub fn peek_s(&mut self) -> bool {
-        self.peek_aws() || self.peek_blocks()
-    }
-
     pub fn parse_blocks(&mut self) -> Production<'a, 'b> {
-        assert!(self.peek_blocks());
+        debug_assert!(self.peek_blocks());
         let mut blocks = Production::new_nonterminal(ProductionType::Blocks);
         while self.peek_blocks() {
             blocks.push_production(self.parse_block());
@@ -63,7 +131,7 @@ impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
     }
 
     pub fn parse_block(&mut self) -> Production<'a, 'b> {
-        assert!(self.peek_block());
+        debug_assert!(self.peek_block());
         let mut block = Production::new_nonterminal(ProductionType::Block);
         while self.peek_block() {
             if self.peek_text() {
@@ -73,9 +141,11 @@ impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
                     block.push_production(self.parse_comment());
                 } else if self.peek_expr() {
                     block.push_production(self.parse_expr());
-                } else {
-                    assert!(self.peek_math());
+                } else if self.peek_math() {
                     block.push_production(self.parse_math());
+                } else {
+                    debug_assert!(self.peek_display_math());
+                    block.push_production(self.parse_display_math());
                 }
                 if self.peek_ws() {
                     block.push_production(self.parse_ws());
@@ -90,10 +160,11 @@ impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
         || self.peek_comment()
         || self.peek_expr()
         || self.peek_math()
+        || self.peek_display_math()
     }
 
     pub fn parse_text(&mut self) -> Production<'a, 'b> {
-        assert!(self.peek_text());
+        debug_assert!(self.peek_text());
         let mut text = Production::new_nonterminal(ProductionType::Text);
         while self.peek_ws() || self.peek_text_item() {
             if self.peek_text() {
@@ -111,31 +182,29 @@ impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
     }
 
     pub fn parse_text_item(&mut self) -> Branch<'a, 'b> {
-        assert!(self.peek_text_item());
+        debug_assert!(self.peek_text_item());
         Branch::Terminal(self.pop_token())
     }
 
     pub fn peek_text_item(&mut self) -> bool {
-        match self.peek_type() {
+        matches!(self.peek_type(),
             TokenType::Word
             | TokenType::Char
             | TokenType::Number
             | TokenType::Escaped
             | TokenType::KeyStart
-            | TokenType::Quote => true,
-            _ => false
-        }
+            | TokenType::Quote)
     }
 
     pub fn parse_comment(&mut self) -> Production<'a, 'b> {
-        assert!(self.peek_comment());
+        debug_assert!(self.peek_comment());
         let mut comment = Production::new_nonterminal(ProductionType::Comment);
-        comment.push_terminal(self.pop_token());
+        let open = self.pop_token();
+        comment.push_terminal(open.clone());
         if self.peek_comment_body() {
             comment.push_production(self.parse_comment_body());
         }
-        assert_eq!(TokenType::CloseComment, self.peek_type());
-        comment.push_terminal(self.pop_token());
+        self.close(&mut comment, open, TokenType::CloseComment);
         comment
     }
 
@@ -144,13 +213,13 @@ impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
     }
 
     pub fn parse_comment_body(&mut self) -> Production<'a, 'b> {
-        assert!(self.peek_comment_body());
+        debug_assert!(self.peek_comment_body());
         let mut body = Production::new_nonterminal(ProductionType::CommentBody);
         while self.peek_comment_body() {
             if self.peek_comment() {
                 body.push_production(self.parse_comment());
             } else {
-                assert!(self.peek_comment_term());
+                debug_assert!(self.peek_comment_term());
                 body.push(self.parse_comment_term());
             }
         }
@@ -162,28 +231,32 @@ impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
     }
 
     pub fn parse_comment_term(&mut self) -> Branch<'a, 'b> {
-        assert!(self.peek_comment_term());
+        debug_assert!(self.peek_comment_term());
         Branch::Terminal(self.pop_token())
     }
 
     pub fn peek_comment_term(&mut self) -> bool {
-        match self.peek_type() {
+        !matches!(self.peek_type(),
             TokenType::EOF
             | TokenType::OpenComment
-            | TokenType::CloseComment => false,
-            _ => true
-        }
+            | TokenType::CloseComment)
     }
 
     pub fn parse_expr(&mut self) -> Production<'a, 'b> {
-        assert!(self.peek_expr());
+        debug_assert!(self.peek_expr());
         let mut expr = Production::new_nonterminal(ProductionType::Expr);
-        expr.push_terminal(self.pop_token());
-        if self.peek_expr_body() {
-            expr.push_production(self.parse_expr_body());
+        let open = self.pop_token();
+        expr.push_terminal(open.clone());
+        self.closers.push(TokenType::CloseExpression);
+        while !self.peek_sync() {
+            if self.peek_expr_body() {
+                expr.push_production(self.parse_expr_body());
+            } else {
+                self.skip_unexpected("an expression item or `}`");
+            }
         }
-        assert_eq!(TokenType::CloseExpression, self.peek_type());
-        expr.push_terminal(self.pop_token());
+        self.closers.pop();
+        self.close(&mut expr, open, TokenType::CloseExpression);
         expr
     }
 
@@ -192,15 +265,15 @@ impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
     }
 
     pub fn parse_expr_body(&mut self) -> Production<'a, 'b> {
-        assert!(self.peek_expr_body());
+        debug_assert!(self.peek_expr_body());
         let mut body = Production::new_nonterminal(ProductionType::ExprBody);
         while self.peek_expr_body() {
-            if self.peek_aws() {
-                body.push_production(self.parse_aws())
+            if self.peek_ws() {
+                body.push_production(self.parse_ws())
             } else if self.peek_expr_item() {
                 body.push(self.parse_expr_item());
             } else {
-                assert!(self.peek_expr_prop());
+                debug_assert!(self.peek_expr_prop());
                 body.push_production(self.parse_expr_prop());
             }
         }
@@ -208,13 +281,13 @@ impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
     }
 
     pub fn peek_expr_body(&mut self) -> bool {
-        self.peek_aws()
+        self.peek_ws()
         || self.peek_expr_item()
         || self.peek_expr_prop()
     }
 
     pub fn parse_expr_item(&mut self) -> Branch<'a, 'b> {
-        assert!(self.peek_expr_item());
+        debug_assert!(self.peek_expr_item());
         if self.peek_expr_ident() {
             Branch::Nonterminal(self.parse_expr_ident())
         } else if self.peek_expr_literal() {
@@ -223,9 +296,11 @@ impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
             Branch::Nonterminal(self.parse_comment())
         } else if self.peek_expr() {
             Branch::Nonterminal(self.parse_expr())
-        } else {
-            assert!(self.peek_math());
+        } else if self.peek_math() {
             Branch::Nonterminal(self.parse_math())
+        } else {
+            debug_assert!(self.peek_display_math());
+            Branch::Nonterminal(self.parse_display_math())
         }
     }
 
@@ -235,15 +310,38 @@ impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
         || self.peek_comment()
         || self.peek_expr()
         || self.peek_math()
+        || self.peek_display_math()
     }
 
     pub fn parse_expr_prop(&mut self) -> Production<'a, 'b> {
-        assert!(self.peek_expr_prop());
+        debug_assert!(self.peek_expr_prop());
         let mut prop = Production::new_nonterminal(ProductionType::ExprProp);
         prop.push_terminal(self.pop_token());
+        if !self.peek_expr_ident() {
+            let found = self.peek_token();
+            self.errors.push(ParseError::Unexpected { found, expected: "a property name after `:`" });
+            return prop;
+        }
         prop.push_production(self.parse_expr_ident());
-        prop.push_production(self.parse_aws());
-        prop.push(self.parse_expr_item());
+        if self.peek_ws() {
+            prop.push_production(self.parse_ws());
+        } else {
+            let found = self.peek_token();
+            self.errors.push(ParseError::Unexpected { found, expected: "whitespace after the property name" });
+        }
+        // Comments between the name and the value are kept in the tree but are not the value.
+        while self.peek_comment() {
+            prop.push_production(self.parse_comment());
+            if self.peek_ws() {
+                prop.push_production(self.parse_ws());
+            }
+        }
+        if self.peek_expr_item() {
+            prop.push(self.parse_expr_item());
+        } else {
+            let found = self.peek_token();
+            self.errors.push(ParseError::Unexpected { found, expected: "a property value" });
+        }
         prop
     }
 
@@ -252,7 +350,7 @@ impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
     }
 
     pub fn parse_expr_ident(&mut self) -> Production<'a, 'b> {
-        assert!(self.peek_expr_ident());
+        debug_assert!(self.peek_expr_ident());
         let mut ident = Production::new_nonterminal(ProductionType::ExprIdent);
         while self.peek_expr_ident() {
             ident.push_terminal(self.pop_token());
@@ -261,19 +359,17 @@ impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
     }
 
     pub fn peek_expr_ident(&mut self) -> bool {
-        match self.peek_type() {
+        matches!(self.peek_type(),
             TokenType::Char
-            | TokenType::Word => true,
-            _ => false
-        }
+            | TokenType::Word)
     }
 
     pub fn parse_expr_literal(&mut self) -> Branch<'a, 'b> {
-        assert!(self.peek_expr_literal());
+        debug_assert!(self.peek_expr_literal());
         if self.peek_string() {
             Branch::Nonterminal(self.parse_string())
         } else {
-            assert_eq!(TokenType::Number, self.peek_type());
+            debug_assert_eq!(TokenType::Number, self.peek_type());
             Branch::Terminal(self.pop_token())
         }
     }
@@ -284,14 +380,20 @@ impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
     }
 
     pub fn parse_math(&mut self) -> Production<'a, 'b> {
-        assert!(self.peek_math());
+        debug_assert!(self.peek_math());
         let mut math = Production::new_nonterminal(ProductionType::Math);
-        math.push_terminal(self.pop_token());
-        if self.peek_math_body() {
-            math.push_production(self.parse_math_body());
+        let open = self.pop_token();
+        math.push_terminal(open.clone());
+        self.closers.push(TokenType::CloseMath);
+        while !self.peek_sync() {
+            if self.peek_math_body() {
+                math.push_production(self.parse_math_body());
+            } else {
+                self.skip_unexpected("a math term or `$}`");
+            }
         }
-        assert_eq!(TokenType::CloseMath, self.peek_type());
-        math.push_terminal(self.pop_token());
+        self.closers.pop();
+        self.close(&mut math, open, TokenType::CloseMath);
         math
     }
 
@@ -299,13 +401,35 @@ impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
         self.peek_type() == TokenType::OpenMath
     }
 
+    pub fn parse_display_math(&mut self) -> Production<'a, 'b> {
+        debug_assert!(self.peek_display_math());
+        let mut math = Production::new_nonterminal(ProductionType::DisplayMath);
+        let open = self.pop_token();
+        math.push_terminal(open.clone());
+        self.closers.push(TokenType::CloseDisplayMath);
+        while !self.peek_sync() {
+            if self.peek_math_body() {
+                math.push_production(self.parse_math_body());
+            } else {
+                self.skip_unexpected("a math term or `$$}`");
+            }
+        }
+        self.closers.pop();
+        self.close(&mut math, open, TokenType::CloseDisplayMath);
+        math
+    }
+
+    pub fn peek_display_math(&mut self) -> bool {
+        self.peek_type() == TokenType::OpenDisplayMath
+    }
+
     pub fn parse_math_body(&mut self) -> Production<'a, 'b> {
-        assert!(self.peek_math_body());
+        debug_assert!(self.peek_math_body());
         let mut body = Production::new_nonterminal(ProductionType::MathBody);
-        if self.peek_aws() {
-            body.push_production(self.parse_aws());
+        if self.peek_ws() {
+            body.push_production(self.parse_ws());
         } else {
-            assert!(self.peek_math_term());
+            debug_assert!(self.peek_math_term());
             body.push(self.parse_math_term());
         }
         if self.peek_math_body() {
@@ -315,11 +439,11 @@ impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
     }
 
     pub fn peek_math_body(&mut self) -> bool {
-        self.peek_aws() || self.peek_math_term()
+        self.peek_ws() || self.peek_math_term()
     }
 
     pub fn parse_math_term(&mut self) -> Branch<'a, 'b> {
-        assert!(self.peek_math_term());
+        debug_assert!(self.peek_math_term());
         if self.peek_comment() {
             Branch::Nonterminal(self.parse_comment())
         } else if self.peek_expr() {
@@ -335,25 +459,23 @@ impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
         self.peek_comment()
         || self.peek_expr()
         || self.peek_math()
-        || match self.peek_type() {
+        || matches!(self.peek_type(),
             TokenType::Char
             | TokenType::Escaped
             | TokenType::KeyStart
             | TokenType::Number
-            | TokenType::Word => true,
-            _ => false
-        }
+            | TokenType::Word)
     }
 
     pub fn parse_string(&mut self) -> Production<'a, 'b> {
-        assert!(self.peek_string());
+        debug_assert!(self.peek_string());
         let mut string = Production::new_nonterminal(ProductionType::String);
-        string.push_terminal(self.pop_token());
+        let open = self.pop_token();
+        string.push_terminal(open.clone());
         if self.peek_string_body() {
             string.push_production(self.parse_string_body());
         }
-        assert_eq!(TokenType::Quote, self.peek_type());
-        string.push_terminal(self.pop_token());
+        self.close(&mut string, open, TokenType::Quote);
         string
     }
 
@@ -362,7 +484,7 @@ impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
     }
 
     pub fn parse_string_body(&mut self) -> Production<'a, 'b> {
-        assert!(self.peek_string_body());
+        debug_assert!(self.peek_string_body());
         let mut body = Production::new_nonterminal(ProductionType::StringBody);
         while self.peek_string_term() {
             body.push(self.parse_string_term());
@@ -375,20 +497,19 @@ impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
     }
 
     pub fn parse_string_term(&mut self) -> Branch<'a, 'b> {
-        assert!(self.peek_string_term());
+        debug_assert!(self.peek_string_term());
         Branch::Terminal(self.pop_token())
     }
 
     pub fn peek_string_term(&mut self) -> bool {
-        match self.peek_type() {
+        !matches!(self.peek_type(),
             TokenType::Quote
-            | TokenType::EOF => false,
-            _ => true
-        }
+            | TokenType::BlankLine
+            | TokenType::EOF)
     }
 
     pub fn parse_aws(&mut self) -> Production<'a, 'b> {
-        assert!(self.peek_aws());
+        debug_assert!(self.peek_aws());
         let mut aws = Production::new_nonterminal(ProductionType::AnyWhiteSpace);
         aws.push_terminal(self.pop_token());
         if self.peek_aws() {
@@ -398,16 +519,14 @@ impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
     }
 
     pub fn peek_aws(&mut self) -> bool {
-        match self.peek_type() {
+        matches!(self.peek_type(),
             TokenType::BlankLine
             | TokenType::NewLine
-            | TokenType::Space => true,
-            _ => false
-        }
+            | TokenType::Space)
     }
 
     pub fn parse_sws(&mut self) -> Production<'a, 'b> {
-        assert!(self.peek_sws());
+        debug_assert!(self.peek_sws());
         let mut sws = Production::new_nonterminal(ProductionType::SigWhiteSpace);
         sws.push_terminal(self.pop_token());
         if self.peek_ws() {
@@ -421,7 +540,7 @@ impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
     }
 
     pub fn parse_ws(&mut self) -> Production<'a, 'b> {
-        assert!(self.peek_ws());
+        debug_assert!(self.peek_ws());
         let mut ws = Production::new_nonterminal(ProductionType::WhiteSpace);
         ws.push_terminal(self.pop_token());
         if self.peek_ws() {
@@ -431,10 +550,99 @@ impl <'a, 'b, I: Iterator<Item=Token<'a, 'b>>> Parser<'a, 'b, I> {
     }
 
     pub fn peek_ws(&mut self) -> bool {
-        match self.peek_type() {
+        matches!(self.peek_type(),
             TokenType::NewLine
-            | TokenType::Space => true,
-            _ => false
+            | TokenType::Space)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::diagnostics::Diagnostic;
+    use ::lex::Lexer;
+    use ::parse::Branch;
+    use ::parse::parse;
+
+    fn errors(source: &str) -> Vec<String> {
+        match parse(Lexer::new_with_filename(source, "test.litx")) {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors.iter().map(|err| err.to_string()).collect()
         }
     }
+
+    #[test]
+    fn well_formed() {
+        assert!(errors("").is_empty());
+        assert!(errors("\n\n").is_empty());
+        assert!(errors("{litx\n    :title \"Example\"\n    :authors {list \"A\" \"B\"}}\n\nText {$ 1+2 $}. {! a {!nested!} comment !}").is_empty());
+    }
+
+    #[test]
+    fn unclosed_expression() {
+        assert_eq!(errors("Some text.\n{h1 \"Intro\"\n\nNext paragraph."), vec![
+            "test.litx:2:12: expected `}` to close expression opened at line 2, found a blank line"
+        ]);
+    }
+
+    #[test]
+    fn unclosed_inside_enclosing_context() {
+        assert_eq!(errors("{$ x + {f y $}"), vec![
+            "test.litx:1:13: expected `}` to close expression opened at line 1, found `$}`"
+        ]);
+    }
+
+    #[test]
+    fn display_math() {
+        assert!(errors("Before {$$ x^{$2$} $$} and {equation {$$ y $$}}.").is_empty());
+        assert_eq!(errors("{$$ x $}\n\n{$ {$$ y $$} $}"), vec![
+            "test.litx:1:7: unmatched `$}`",
+            "test.litx:1:9: expected `$$}` to close display math opened at line 1, found a blank line",
+            "test.litx:3:4: expected a math term or `$}`, found `{$$`",
+            "test.litx:3:10: unmatched `$$}`"
+        ]);
+    }
+
+    #[test]
+    fn recovers_and_reports_everything() {
+        assert_eq!(errors("stray } here\n{emph \"open\n\n{$ \" $} {foo :}\n{! never closed"), vec![
+            "test.litx:1:7: unmatched `}`",
+            "test.litx:2:12: expected `\"` to close string opened at line 2, found a blank line",
+            "test.litx:2:12: expected `}` to close expression opened at line 2, found a blank line",
+            "test.litx:4:4: expected a math term or `$}`, found `\"`",
+            "test.litx:4:15: expected a property name after `:`, found `}`",
+            "test.litx:5:16: expected `!}` to close comment opened at line 5, found end of file"
+        ]);
+    }
+
+    #[test]
+    fn lex_errors_are_reported() {
+        assert_eq!(errors("a\u{0}b {c \u{1}}"), vec![
+            "test.litx:1:2: unrecognised input \"\\0\"",
+            "test.litx:1:8: unrecognised input \"\\u{1}\""
+        ]);
+    }
+
+    #[test]
+    fn production_spans() {
+        let source = "Intro.\n\n{h1 \"x\"}";
+        let tree = parse(Lexer::new_with_filename(source, "test.litx")).unwrap();
+        let span = tree.get_span().unwrap();
+        assert_eq!((span.start, span.end, span.end_line_col), (0, source.len(), (2, 8)));
+        let blocks = match tree.get_children()[0] {
+            Branch::Nonterminal(ref blocks) => blocks,
+            _ => panic!()
+        };
+        let second = blocks.get_children()[2].get_span().unwrap();
+        assert_eq!((second.start, second.start_line_col, second.end), (8, (2, 0), source.len()));
+    }
+
+    #[test]
+    fn unclosed_diagnostic_points_at_opener() {
+        let err = parse(Lexer::new_with_filename("Text {h1 \"x\"\n\nNext", "test.litx")).unwrap_err().remove(0);
+        let diagnostic = Diagnostic::from(err);
+        assert_eq!(diagnostic.labels.len(), 2);
+        assert_eq!((diagnostic.labels[0].location.line, diagnostic.labels[0].location.column), (0, 12));
+        assert_eq!((diagnostic.labels[1].location.column, diagnostic.labels[1].message.as_str()), (5, "unclosed expression opened here"));
+        assert_eq!(diagnostic.help, Some("add `}` to close the expression".to_string()));
+    }
 }
@@ -1,5 +1,6 @@
 use ::lex::Token;
 use ::graphviz::Graphviz;
+use ::source::Span;
 use super::ProductionType;
 use std::io;
 
@@ -22,7 +23,7 @@ impl <'a, 'b> Production<'a, 'b> {
     pub fn new_nonterminal(ty: ProductionType) -> Self {
         const DEFAULT_CAPACITY: usize = 4;
         Production {
-            ty: ty,
+            ty,
             id: unsafe {
                 let tmp = PRODUCTION_ID;
                 PRODUCTION_ID += 1;
@@ -32,6 +33,21 @@ impl <'a, 'b> Production<'a, 'b> {
         }
     }
 
+    pub fn get_type(&self) -> ProductionType {
+        self.ty
+    }
+
+    pub fn get_children(&self) -> &[Branch<'a, 'b>] {
+        &self.children
+    }
+
+    /// The span from the first token below this production to the last, or `None` if it has no tokens at all.
+    pub fn get_span(&self) -> Option<Span<'b>> {
+        let first = self.children.iter().filter_map(Branch::get_span).next()?;
+        let last = self.children.iter().rev().filter_map(Branch::get_span).next()?;
+        Some(first.to(&last))
+    }
+
     pub fn push(&mut self, bran: Branch<'a, 'b>) {
         self.children.push(bran);
     }
@@ -45,6 +61,15 @@ impl <'a, 'b> Production<'a, 'b> {
     }
 }
 
+impl <'a, 'b> Branch<'a, 'b> {
+    pub fn get_span(&self) -> Option<Span<'b>> {
+        match *self {
+            Branch::Terminal(ref token) => Some(token.get_span()),
+            Branch::Nonterminal(ref production) => production.get_span()
+        }
+    }
+}
+
 impl <'a, 'b, W: io::Write> Graphviz<W> for Production<'a, 'b> {
     fn get_vertex_name(&self) -> String {
         format!("{:?}_{}", self.ty, self.id)
@@ -1,4 +1,6 @@
+#[derive(Copy, Clone)]
 #[derive(Debug)]
+#[derive(Eq, PartialEq)]
 pub enum ProductionType {
     S,
     Blocks,
@@ -12,6 +14,7 @@ pub enum ProductionType {
     ExprProp,
     ExprIdent,
     Math,
+    DisplayMath,
     MathBody,
     String,
     StringBody,
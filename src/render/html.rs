@@ -0,0 +1,190 @@
+use ::eval::Document;
+use ::eval::Node;
+use std::io;
+use super::mathml::to_mathml;
+
+/// Writes `doc` as a standalone HTML5 page, with the title, authors and language taken from its metadata.
+pub fn render<W: io::Write>(doc: &Document, writer: &mut W) -> io::Result<()> {
+    let meta = &doc.meta;
+    writeln!(writer, "<!DOCTYPE html>")?;
+    match meta.language {
+        Some(ref language) => writeln!(writer, "<html lang=\"{}\">", escape(language))?,
+        None => writeln!(writer, "<html>")?
+    }
+    writeln!(writer, "<head>")?;
+    writeln!(writer, "<meta charset=\"utf-8\">")?;
+    if let Some(ref title) = meta.title {
+        writeln!(writer, "<title>{}</title>", escape(title))?;
+    }
+    for author in &meta.authors {
+        writeln!(writer, "<meta name=\"author\" content=\"{}\">", escape(author))?;
+    }
+    if let Some(ref date) = meta.date {
+        writeln!(writer, "<meta name=\"date\" content=\"{}\">", escape(date))?;
+    }
+    writeln!(writer, "</head>")?;
+    writeln!(writer, "<body>")?;
+    if meta.title.is_some() || !meta.authors.is_empty() {
+        writeln!(writer, "<header>")?;
+        if let Some(ref title) = meta.title {
+            writeln!(writer, "<p class=\"title\">{}</p>", escape(title))?;
+        }
+        if !meta.authors.is_empty() {
+            writeln!(writer, "<p class=\"authors\">{}</p>", escape(&meta.authors.join(", ")))?;
+        }
+        writeln!(writer, "</header>")?;
+    }
+    for block in &doc.blocks {
+        write_node(block, writer)?;
+    }
+    writeln!(writer, "</body>")?;
+    writeln!(writer, "</html>")?;
+    Ok(())
+}
+
+fn write_node<W: io::Write>(node: &Node, writer: &mut W) -> io::Result<()> {
+    match *node {
+        Node::Text(ref text) => write!(writer, "{}", escape(text)),
+        Node::Space => write!(writer, " "),
+        Node::LineBreak => write!(writer, "<br>"),
+        Node::Math(ref formula) => write!(writer, "{}", to_mathml(formula, false)),
+        Node::Anchor(ref label) => write!(writer, "<a id=\"{}\"></a>", escape(label)),
+        Node::Ref { ref label, ref number } => write!(writer, "<a href=\"#{}\">{}</a>", escape(label), escape(number)),
+        // Without pages, the reference links to what it names instead.
+        Node::PageRef { ref label, ref title } => write!(writer, "<a href=\"#{}\">{}</a>", escape(label), escape(title)),
+        Node::Emph(ref content) => write_tagged("em", content, writer),
+        Node::Strong(ref content) => write_tagged("strong", content, writer),
+        Node::Paragraph(ref content) => {
+            write_tagged("p", content, writer)?;
+            writeln!(writer)
+        },
+        Node::Heading { level, ref number, ref content, ref labels, .. } => {
+            write!(writer, "{}", open_tag(&format!("h{}", level), "", labels))?;
+            for node in &Node::numbered(number.as_deref(), content) {
+                write_node(node, writer)?;
+            }
+            writeln!(writer, "</h{}>", level)
+        },
+        Node::List(ref items) => {
+            writeln!(writer, "<ul>")?;
+            for item in items {
+                write_tagged("li", item, writer)?;
+                writeln!(writer)?;
+            }
+            writeln!(writer, "</ul>")
+        },
+        // The number sits at the right margin, with the math centred in the space left of it.
+        Node::Equation { ref formula, number: Some(number), ref labels } => {
+            writeln!(writer, "{}<div style=\"flex: 1\">{}</div><span class=\"equation-number\">({})</span></div>",
+                open_tag("div", " class=\"equation\" style=\"display: flex; align-items: center\"", labels), to_mathml(formula, true), number)
+        },
+        Node::Equation { ref formula, number: None, .. } => writeln!(writer, "<div class=\"equation\">{}</div>", to_mathml(formula, true)),
+        Node::Figure { ref content, ref caption, number, ref labels } => {
+            write!(writer, "{}", open_tag("figure", "", labels))?;
+            for node in content {
+                write_node(node, writer)?;
+            }
+            write!(writer, "<figcaption>Figure {}", number)?;
+            if !caption.is_empty() {
+                write!(writer, ": ")?;
+            }
+            for node in caption {
+                write_node(node, writer)?;
+            }
+            writeln!(writer, "</figcaption></figure>")
+        },
+        Node::Contents { ref entries, .. } => {
+            writeln!(writer, "<nav class=\"contents\">")?;
+            writeln!(writer, "<p class=\"contents-title\">Contents</p>")?;
+            writeln!(writer, "<ul style=\"list-style: none\">")?;
+            for entry in entries {
+                write!(writer, "<li style=\"margin-left: {}em\">", (entry.level - 1) as f64 * 1.5)?;
+                if let Some(ref number) = entry.number {
+                    write!(writer, "{} ", escape(number))?;
+                }
+                for node in &entry.title {
+                    write_node(node, writer)?;
+                }
+                writeln!(writer, "</li>")?;
+            }
+            writeln!(writer, "</ul>")?;
+            writeln!(writer, "</nav>")
+        }
+    }
+}
+
+fn write_tagged<W: io::Write>(tag: &str, content: &[Node], writer: &mut W) -> io::Result<()> {
+    write!(writer, "<{}>", tag)?;
+    for node in content {
+        write_node(node, writer)?;
+    }
+    write!(writer, "</{}>", tag)
+}
+
+/// `<tag attributes>` for a heading, figure or equation, with its first label as the `id` links point at, and an anchor
+/// just inside it for each other label.
+fn open_tag(tag: &str, attributes: &str, labels: &[String]) -> String {
+    let mut html = match labels.first() {
+        Some(label) => format!("<{} id=\"{}\"{}>", tag, escape(label), attributes),
+        None => format!("<{}{}>", tag, attributes)
+    };
+    for label in labels.iter().skip(1) {
+        html.push_str(&format!("<a id=\"{}\"></a>", escape(label)));
+    }
+    html
+}
+
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use ::render::test::render;
+
+    #[test]
+    fn page() {
+        let html = render("{litx :title \"A & B\" :authors {list \"C\" \"D\"} :lang en}\n\n{h1 \"Intro\"}\nSome <text> and {emph \"this\"}.\nNext\\nline.\n\n{ignore \"x\"}\nLast {$ a<b $}.", super::render);
+        assert_eq!(html, "\
+<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+<meta charset=\"utf-8\">
+<title>A &amp; B</title>
+<meta name=\"author\" content=\"C\">
+<meta name=\"author\" content=\"D\">
+</head>
+<body>
+<header>
+<p class=\"title\">A &amp; B</p>
+<p class=\"authors\">C, D</p>
+</header>
+<h1>1 Intro</h1>
+<p>Some &lt;text&gt; and <em>this</em>. Next<br>line.</p>
+<p>Last <math alttext=\"a&lt;b\"><mi>a</mi><mo>&lt;</mo><mi>b</mi></math>.</p>
+</body>
+</html>
+");
+    }
+
+    #[test]
+    fn figures_and_references() {
+        let html = render("{figure {emph \"x\"} :caption \"A cat\"} {label cat}\nFigure {ref cat} is on page {pageref cat}.\n\n\
+            {equation {$ a $}}{label sum} See {pageref sum}.", super::render);
+        assert!(html.contains("<body>\n\
+            <figure id=\"cat\"><em>x</em><figcaption>Figure 1: A cat</figcaption></figure>\n\
+            <p>Figure <a href=\"#cat\">1</a> is on page <a href=\"#cat\">A cat</a>.</p>\n"), "{}", html);
+        assert!(html.contains("<p>See <a href=\"#sum\">Equation 1</a>.</p>\n</body>"), "{}", html);
+    }
+}
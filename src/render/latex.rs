@@ -0,0 +1,263 @@
+use ::eval::Document;
+use ::eval::Node;
+use std::io;
+use super::latex_math::to_latex;
+
+/// Document classes which `:doctype` may name directly. Any other doctype, such as `mla`, becomes an `article`.
+const CLASSES: [&str; 7] = ["article", "report", "book", "letter", "memoir", "amsart", "scrartcl"];
+
+/// Sectioning commands by heading level, for classes without chapters and for those with them.
+const SECTIONS: [&str; 6] = ["section", "subsection", "subsubsection", "paragraph", "subparagraph", "subparagraph"];
+const CHAPTERED_SECTIONS: [&str; 6] = ["chapter", "section", "subsection", "subsubsection", "paragraph", "subparagraph"];
+
+/// Writes `doc` as a LaTeX source file which compiles on its own.
+pub fn render<W: io::Write>(doc: &Document, writer: &mut W) -> io::Result<()> {
+    let meta = &doc.meta;
+    let class = meta.doctype.as_deref()
+        .filter(|doctype| CLASSES.contains(doctype))
+        .unwrap_or("article");
+    writeln!(writer, "\\documentclass{{{}}}", class)?;
+    writeln!(writer, "\\usepackage[utf8]{{inputenc}}")?;
+    writeln!(writer, "\\usepackage[T1]{{fontenc}}")?;
+    // For `\text` in math.
+    writeln!(writer, "\\usepackage{{amsmath}}")?;
+    if let Some(language) = meta.language.as_ref().and_then(|language| babel_language(language)) {
+        writeln!(writer, "\\usepackage[{}]{{babel}}", language)?;
+    }
+    if let Some(ref title) = meta.title {
+        writeln!(writer, "\\title{{{}}}", escape(title))?;
+    }
+    if !meta.authors.is_empty() {
+        let authors: Vec<String> = meta.authors.iter().map(|author| escape(author)).collect();
+        writeln!(writer, "\\author{{{}}}", authors.join(" \\and "))?;
+    }
+    // Without a date, LaTeX would print today's.
+    writeln!(writer, "\\date{{{}}}", meta.date.as_ref().map(|date| escape(date)).unwrap_or_default())?;
+    writeln!(writer)?;
+    writeln!(writer, "\\begin{{document}}")?;
+    if meta.title.is_some() {
+        writeln!(writer, "\\maketitle")?;
+    }
+    let sections = match class {
+        "book" | "report" | "memoir" => &CHAPTERED_SECTIONS,
+        _ => &SECTIONS
+    };
+    for block in &doc.blocks {
+        writeln!(writer)?;
+        write_block(block, sections, writer)?;
+    }
+    writeln!(writer)?;
+    writeln!(writer, "\\end{{document}}")?;
+    Ok(())
+}
+
+fn write_block<W: io::Write>(node: &Node, sections: &[&str; 6], writer: &mut W) -> io::Result<()> {
+    match *node {
+        Node::Heading { level, number: Some(_), ref content, ref labels, .. } => {
+            write!(writer, "\\{}{{", sections[level - 1])?;
+            write_inlines(content, writer)?;
+            writeln!(writer, "}}{}", label_commands(labels))
+        },
+        // Starred sections are left out of the table of contents unless they are added to it by hand.
+        Node::Heading { level, number: None, ref content, ref labels, listed } => {
+            write!(writer, "\\{}*{{", sections[level - 1])?;
+            write_inlines(content, writer)?;
+            write!(writer, "}}")?;
+            if listed {
+                write!(writer, "\\addcontentsline{{toc}}{{{}}}{{", sections[level - 1])?;
+                write_inlines(content, writer)?;
+                write!(writer, "}}")?;
+            }
+            writeln!(writer, "{}", label_commands(labels))
+        },
+        Node::List(ref items) => {
+            writeln!(writer, "\\begin{{itemize}}")?;
+            for item in items {
+                write!(writer, "  \\item ")?;
+                write_inlines(item, writer)?;
+                writeln!(writer)?;
+            }
+            writeln!(writer, "\\end{{itemize}}")
+        },
+        Node::Paragraph(ref content) => {
+            write_inlines(content, writer)?;
+            writeln!(writer)
+        },
+        // LaTeX numbers `equation`s itself, and `{ref ...}`s are written as `\ref`s to match.
+        Node::Equation { ref formula, number: Some(_), ref labels } => {
+            writeln!(writer, "\\begin{{equation}}\n  {}{}\n\\end{{equation}}", to_latex(formula), label_commands(labels))
+        },
+        Node::Equation { ref formula, number: None, .. } => writeln!(writer, "\\[ {} \\]", to_latex(formula)),
+        // Figures are numbered by LaTeX too.
+        Node::Figure { ref content, ref caption, ref labels, .. } => {
+            writeln!(writer, "\\begin{{figure}}[h]")?;
+            writeln!(writer, "  \\centering")?;
+            write!(writer, "  ")?;
+            write_inlines(content, writer)?;
+            writeln!(writer)?;
+            write!(writer, "  ")?;
+            write_command("caption", caption, writer)?;
+            // `\label` refers to the last counter stepped, so it has to follow `\caption` inside the figure.
+            writeln!(writer, "{}", label_commands(labels))?;
+            writeln!(writer, "\\end{{figure}}")
+        },
+        // LaTeX makes its own table of contents, numbered and with pages, from the sections it has typeset.
+        Node::Contents { ref entries, .. } => {
+            if let Some(depth) = entries.iter().map(|entry| entry.level).max() {
+                let chaptered = sections[0] == "chapter";
+                writeln!(writer, "\\setcounter{{tocdepth}}{{{}}}", if chaptered { depth - 1 } else { depth })?;
+            }
+            writeln!(writer, "\\tableofcontents")
+        },
+        ref inline => {
+            write_inline(inline, writer)?;
+            writeln!(writer)
+        }
+    }
+}
+
+fn write_inlines<W: io::Write>(nodes: &[Node], writer: &mut W) -> io::Result<()> {
+    for node in nodes {
+        write_inline(node, writer)?;
+    }
+    Ok(())
+}
+
+fn write_inline<W: io::Write>(node: &Node, writer: &mut W) -> io::Result<()> {
+    match *node {
+        Node::Text(ref text) => write!(writer, "{}", escape(text)),
+        Node::Space => write!(writer, " "),
+        Node::LineBreak => writeln!(writer, "\\\\"),
+        Node::Math(ref math) | Node::Equation { formula: ref math, .. } => write!(writer, "${}$", to_latex(math)),
+        Node::Anchor(ref label) => write!(writer, "\\label{{{}}}", escape(label)),
+        Node::Contents { .. } => Ok(()),
+        // LaTeX numbers what labels name by its own rules, such as by chapter, so it writes the numbers too.
+        Node::Ref { ref label, .. } => write!(writer, "\\ref{{{}}}", escape(label)),
+        // LaTeX knows the pages, where litx does not.
+        Node::PageRef { ref label, .. } => write!(writer, "\\pageref{{{}}}", escape(label)),
+        Node::Emph(ref content) => write_command("emph", content, writer),
+        Node::Strong(ref content) => write_command("textbf", content, writer),
+        Node::Paragraph(ref content) | Node::Heading { ref content, .. } | Node::Figure { ref content, .. } => {
+            write_inlines(content, writer)
+        },
+        Node::List(ref items) => {
+            for item in items {
+                write_inlines(item, writer)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// A `\label{...}` for each of the labels of a heading, figure or equation.
+fn label_commands(labels: &[String]) -> String {
+    labels.iter().map(|label| format!("\\label{{{}}}", escape(label))).collect()
+}
+
+fn write_command<W: io::Write>(command: &str, content: &[Node], writer: &mut W) -> io::Result<()> {
+    write!(writer, "\\{}{{", command)?;
+    write_inlines(content, writer)?;
+    write!(writer, "}}")
+}
+
+/// The babel name for a language tag such as `en` or `fr-CA`, if it is one babel is known to have.
+fn babel_language(tag: &str) -> Option<&'static str> {
+    match tag.split('-').next().unwrap_or(tag) {
+        "en" => Some("english"),
+        "fr" => Some("french"),
+        "de" => Some("ngerman"),
+        "es" => Some("spanish"),
+        "it" => Some("italian"),
+        "pt" => Some("portuguese"),
+        "nl" => Some("dutch"),
+        _ => None
+    }
+}
+
+/// Escapes the characters LaTeX treats specially, so `text` is typeset exactly as written.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '{' | '}' | '$' | '&' | '#' | '_' | '%' => {
+                escaped.push('\\');
+                escaped.push(c);
+            },
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            _ => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use ::render::test::render;
+
+    #[test]
+    fn article() {
+        let tex = render("{litx :doctype mla :title \"R&D\" :authors {list \"A\" \"B\"} :lang en-GB}\n\n\
+            {h1 \"Intro\"}\n100% of {emph \"these\"} cost \\$5 \\!} {strong \"#1\"}~^_\\\\\\nnext {$ \\alpha^2 $}.\n\n\
+            {h2 \"List\"}\n{list \"a\" \"b\"}", super::render);
+        assert_eq!(tex, "\
+\\documentclass{article}
+\\usepackage[utf8]{inputenc}
+\\usepackage[T1]{fontenc}
+\\usepackage{amsmath}
+\\usepackage[english]{babel}
+\\title{R\\&D}
+\\author{A \\and B}
+\\date{}
+
+\\begin{document}
+\\maketitle
+
+\\section{Intro}
+
+100\\% of \\emph{these} cost \\$5 !\\} \\textbf{\\#1}\\textasciitilde{}\\textasciicircum{}\\_\\textbackslash{}\\\\
+next $\\alpha^{2}$.
+
+\\subsection{List}
+
+\\begin{itemize}
+  \\item a
+  \\item b
+\\end{itemize}
+
+\\end{document}
+");
+    }
+
+    #[test]
+    fn math_is_written_from_its_tree() {
+        let tex = render("{$ \\frac{$a+1$}{$x_i$} \\leq \\sin y' - {emph \"if #\"} $}\n\n\
+            {equation {$ E = mc^2 $}}\n\n{$$ \\Gamma * 50\\% $$}", super::render);
+        assert!(tex.contains("\n$\\frac{a + 1}{x_{i}} \\leq \\sin y ' - \\text{if \\#}$\n"), "{}", tex);
+        assert!(tex.contains("\\begin{equation}\n  E = m c^{2}\n\\end{equation}\n"), "{}", tex);
+        assert!(tex.contains("\\[ \\Gamma * 50 \\% \\]"), "{}", tex);
+    }
+
+    #[test]
+    fn labels_go_inside_what_they_label() {
+        let tex = render("{h1 \"Intro\"}{label intro}\n\n{figure \"x\" :caption \"A cat\"} {label cat}\n\n\
+            {equation {$ a $}}{label sum} See {ref sum}.{label late}", super::render);
+        assert!(tex.contains("\\section{Intro}\\label{intro}\n"), "{}", tex);
+        assert!(tex.contains("  \\caption{A cat}\\label{cat}\n\\end{figure}\n"), "{}", tex);
+        assert!(tex.contains("\\begin{equation}\n  a\\label{sum}\\label{late}\n\\end{equation}\n\nSee \\ref{sum}.\n"), "{}", tex);
+    }
+
+    #[test]
+    fn book_has_chapters() {
+        let tex = render("{litx :doctype book}\n\n{h1 \"One\"}\n\n{h2 \"Two\"}", super::render);
+        assert!(tex.starts_with("\\documentclass{book}\n"));
+        assert!(tex.contains("\\chapter{One}\n\n\\section{Two}\n"));
+    }
+
+    #[test]
+    fn unnumbered_headings_are_starred() {
+        let tex = render("{litx.meta :number-headings false}\n\n{h2 \"Two\"}{label two}", super::render);
+        assert!(tex.contains("\\subsection*{Two}\\addcontentsline{toc}{subsection}{Two}\\label{two}\n"), "{}", tex);
+    }
+}
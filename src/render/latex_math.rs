@@ -0,0 +1,60 @@
+use ::math::command_name;
+use ::math::Formula;
+use ::math::MathNode;
+use super::latex::escape;
+
+/// `formula` in LaTeX's math notation, without the delimiters around it. Letters and symbols the parser read from
+/// names such as `\alpha` go back to those names, and embedded text is set with `\text`.
+pub fn to_latex(formula: &Formula) -> String {
+    let nodes: Vec<String> = formula.nodes.iter().map(latex).collect();
+    nodes.join(" ")
+}
+
+fn latex(node: &MathNode) -> String {
+    match *node {
+        MathNode::Identifier(ref text) | MathNode::Operator(ref text) => symbol(text),
+        MathNode::Function(ref name) => format!("\\{}", name),
+        MathNode::Number(ref number) => number.clone(),
+        MathNode::Text(ref text) => format!("\\text{{{}}}", escape(text)),
+        MathNode::Row(_) => group(node),
+        MathNode::Scripts { ref base, ref subscript, ref superscript } => {
+            let mut scripted = latex(base);
+            if let Some(ref subscript) = *subscript {
+                scripted.push_str(&format!("_{}", group(subscript)));
+            }
+            if let Some(ref superscript) = *superscript {
+                scripted.push_str(&format!("^{}", group(superscript)));
+            }
+            scripted
+        },
+        MathNode::Fraction { ref numerator, ref denominator } => format!("\\frac{}{}", group(numerator), group(denominator))
+    }
+}
+
+/// `node` in braces, as one argument or script. A row's own braces serve.
+fn group(node: &MathNode) -> String {
+    match *node {
+        MathNode::Row(ref nodes) => {
+            let nodes: Vec<String> = nodes.iter().map(latex).collect();
+            format!("{{{}}}", nodes.join(" "))
+        },
+        ref node => format!("{{{}}}", latex(node))
+    }
+}
+
+/// A letter or operator, by its TeX name if it has one and escaped if LaTeX treats it specially.
+fn symbol(text: &str) -> String {
+    match text {
+        "\u{2212}" => "-".to_string(),
+        "\u{2217}" => "*".to_string(),
+        "\u{2032}" => "'".to_string(),
+        "{" | "}" | "$" | "&" | "#" | "_" | "%" => format!("\\{}", text),
+        "\\" => "\\backslash".to_string(),
+        "^" => "\\hat{}".to_string(),
+        "~" => "\\sim".to_string(),
+        _ => match command_name(text) {
+            Some(name) => format!("\\{}", name),
+            None => text.to_string()
+        }
+    }
+}
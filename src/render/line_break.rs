@@ -0,0 +1,366 @@
+/// The penalty at which a break is forbidden outright; its negation forces one.
+pub const INFINITE_PENALTY: f64 = 10000.0;
+
+/// An item in a paragraph's box/glue/penalty stream, in the terms of Knuth and Plass's "Breaking Paragraphs into Lines".
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum Element {
+    /// Something of fixed width, such as a word.
+    Box { width: f64 },
+    /// Space which may stretch or shrink. A `stretch` of `f64::INFINITY` fills whatever is left of the line.
+    /// A line may break at glue which follows a box, and the glue then disappears.
+    Glue { width: f64, stretch: f64, shrink: f64 },
+    /// A place to break, at a cost. `width` is only typeset if the line breaks here, as with a hyphen.
+    /// Breaking at two flagged penalties in a row costs extra.
+    Penalty { width: f64, penalty: f64, flagged: bool }
+}
+
+/// The end of a line: the index of the element where it breaks, and how far its glue must stretch (or,
+/// if negative, shrink) to fill the measure, as a fraction of the glue's total stretchability or shrinkability.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Break {
+    pub position: usize,
+    pub ratio: f64
+}
+
+/// What the line breaker optimises for. The defaults are TeX's plain format settings.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Parameters {
+    /// The worst badness allowed for a line, where a line stretched to twice its natural spacing has a badness of 100.
+    pub tolerance: f64,
+    /// How many lines more (or, if negative, fewer) than the optimum to aim for.
+    pub looseness: i32,
+    /// Added to each line's badness before squaring, so fewer lines are preferred.
+    pub line_penalty: f64,
+    /// Demerits for two consecutive lines ending at flagged penalties, such as hyphens.
+    pub flagged_demerits: f64,
+    /// Demerits for a tight line next to a loose one, or the other way around.
+    pub fitness_demerits: f64,
+    /// The cost of a page break after the first line of a paragraph, leaving it alone at the foot of a page.
+    pub orphan_penalty: f64,
+    /// The cost of a page break before the last line of a paragraph, leaving it alone at the head of a page.
+    pub widow_penalty: f64
+}
+
+impl Default for Parameters {
+    fn default() -> Self {
+        Parameters {
+            tolerance: 200.0,
+            looseness: 0,
+            line_penalty: 10.0,
+            flagged_demerits: 3000.0,
+            fitness_demerits: 100.0,
+            orphan_penalty: 150.0,
+            widow_penalty: 150.0
+        }
+    }
+}
+
+impl Parameters {
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn with_looseness(mut self, looseness: i32) -> Self {
+        self.looseness = looseness;
+        self
+    }
+
+    pub fn with_orphan_penalty(mut self, penalty: f64) -> Self {
+        self.orphan_penalty = penalty;
+        self
+    }
+
+    pub fn with_widow_penalty(mut self, penalty: f64) -> Self {
+        self.widow_penalty = penalty;
+        self
+    }
+
+    /// The cost of a page break after the first `lines` of a paragraph that has `count` lines in all.
+    /// Paginated backends weigh this against the space a page break elsewhere would waste.
+    pub fn get_interline_penalty(&self, lines: usize, count: usize) -> f64 {
+        if lines == 0 || lines >= count {
+            return 0.0;
+        }
+        let mut penalty = 0.0;
+        if lines == 1 {
+            penalty += self.orphan_penalty;
+        }
+        if lines == count - 1 {
+            penalty += self.widow_penalty;
+        }
+        penalty
+    }
+}
+
+/// Running totals of the elements before some position.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Default)]
+struct Totals {
+    width: f64,
+    stretch: f64,
+    /// Glue with infinite stretch is counted apart, so the finite totals stay usable.
+    fills: usize,
+    shrink: f64
+}
+
+/// A feasible break, and the best way found of reaching it.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+struct Candidate {
+    position: usize,
+    line: usize,
+    fitness: usize,
+    /// The totals from the start of the paragraph to the first element of the line after this break.
+    after: Totals,
+    demerits: f64,
+    ratio: f64,
+    previous: Option<usize>
+}
+
+/// Chooses where to break `elements` into lines `measure` wide, minimising the total demerits of the paragraph.
+///
+/// The stream should end with a forced break, usually after glue that fills the last line. If no set of breaks keeps
+/// every line within the tolerance, lines are allowed to be as loose as they must, and a box wider than the measure
+/// overflows a line of its own; a paragraph can always be broken.
+pub fn break_lines(elements: &[Element], measure: f64, parameters: &Parameters) -> Vec<Break> {
+    find_breaks(elements, measure, parameters, false)
+        .unwrap_or_else(|| find_breaks(elements, measure, parameters, true).unwrap_or_default())
+}
+
+fn find_breaks(elements: &[Element], measure: f64, parameters: &Parameters, emergency: bool) -> Option<Vec<Break>> {
+    let mut totals = Vec::with_capacity(elements.len() + 1);
+    let mut sum = Totals::default();
+    totals.push(sum);
+    for element in elements {
+        match *element {
+            Element::Box { width } => sum.width += width,
+            Element::Glue { width, stretch, shrink } => {
+                sum.width += width;
+                if stretch.is_infinite() {
+                    sum.fills += 1;
+                } else {
+                    sum.stretch += stretch;
+                }
+                sum.shrink += shrink;
+            },
+            Element::Penalty { .. } => {}
+        }
+        totals.push(sum);
+    }
+
+    let mut nodes = vec![Candidate {
+        position: 0,
+        line: 0,
+        fitness: 1,
+        after: after_break(elements, &totals, 0, true),
+        demerits: 0.0,
+        ratio: 0.0,
+        previous: None
+    }];
+    let mut active = vec![0];
+    for (b, element) in elements.iter().enumerate() {
+        let (penalty, penalty_width, flagged) = match *element {
+            Element::Penalty { width, penalty, flagged } if penalty < INFINITE_PENALTY => (penalty, width, flagged),
+            Element::Glue { .. } if b > 0 && matches!(elements[b - 1], Element::Box { .. }) => (0.0, 0.0, false),
+            _ => continue
+        };
+        let forced = penalty <= -INFINITE_PENALTY;
+        let mut best: Vec<Candidate> = Vec::new();
+        let mut deactivated = Vec::new();
+        active.retain(|&a| {
+            let node = nodes[a];
+            let natural = totals[b].width - node.after.width + penalty_width;
+            let ratio = if natural < measure {
+                if totals[b].fills > node.after.fills {
+                    0.0
+                } else {
+                    let stretch = totals[b].stretch - node.after.stretch;
+                    if stretch > 0.0 { (measure - natural) / stretch } else { f64::INFINITY }
+                }
+            } else if natural > measure {
+                let shrink = totals[b].shrink - node.after.shrink;
+                if shrink > 0.0 { (measure - natural) / shrink } else { f64::NEG_INFINITY }
+            } else {
+                0.0
+            };
+            let badness = if ratio < -1.0 { f64::INFINITY } else { 100.0 * ratio.abs().powi(3) };
+            if ratio >= -1.0 && (badness <= parameters.tolerance || emergency) {
+                let badness = badness.min(INFINITE_PENALTY);
+                let mut demerits = (parameters.line_penalty + badness).powi(2);
+                if penalty >= 0.0 {
+                    demerits += penalty * penalty;
+                } else if !forced {
+                    demerits -= penalty * penalty;
+                }
+                if flagged && node.previous.is_some() && is_flagged(&elements[node.position]) {
+                    demerits += parameters.flagged_demerits;
+                }
+                let fitness = match ratio {
+                    r if r < -0.5 => 0,
+                    r if r <= 0.5 => 1,
+                    r if r <= 1.0 => 2,
+                    _ => 3
+                };
+                if (fitness as i32 - node.fitness as i32).abs() > 1 {
+                    demerits += parameters.fitness_demerits;
+                }
+                let candidate = Candidate {
+                    position: b,
+                    line: node.line + 1,
+                    fitness,
+                    after: Totals::default(),
+                    demerits: node.demerits + demerits,
+                    ratio,
+                    previous: Some(a)
+                };
+                // Only the best way of reaching this break is kept for each fitness class, and with a looseness
+                // also for each number of lines, since a worse paragraph of the wanted length may be needed.
+                let same = |other: &Candidate| other.fitness == fitness
+                    && (parameters.looseness == 0 || other.line == candidate.line);
+                match best.iter_mut().find(|other| same(other)) {
+                    Some(other) if other.demerits <= candidate.demerits => {},
+                    Some(other) => *other = candidate,
+                    None => best.push(candidate)
+                }
+            }
+            let keep = ratio >= -1.0 && !forced;
+            if !keep {
+                deactivated.push(a);
+            }
+            keep
+        });
+        if emergency && active.is_empty() && best.is_empty() {
+            // Nothing fits, so the best line so far overflows rather than leaving the paragraph unbroken.
+            if let Some(&a) = deactivated.iter().min_by(|&&x, &&y| nodes[x].demerits.partial_cmp(&nodes[y].demerits).unwrap()) {
+                best.push(Candidate {
+                    position: b,
+                    line: nodes[a].line + 1,
+                    fitness: 1,
+                    after: Totals::default(),
+                    demerits: nodes[a].demerits + INFINITE_PENALTY * INFINITE_PENALTY,
+                    ratio: 0.0,
+                    previous: Some(a)
+                });
+            }
+        }
+        for mut candidate in best {
+            candidate.after = after_break(elements, &totals, b, false);
+            nodes.push(candidate);
+            active.push(nodes.len() - 1);
+        }
+        if active.is_empty() {
+            return None;
+        }
+    }
+
+    let last = elements.len().checked_sub(1)?;
+    let finished: Vec<usize> = active.into_iter().filter(|&a| nodes[a].position == last && nodes[a].previous.is_some()).collect();
+    let best = *finished.iter().min_by(|&&x, &&y| nodes[x].demerits.partial_cmp(&nodes[y].demerits).unwrap())?;
+    let chosen = if parameters.looseness == 0 {
+        best
+    } else {
+        let target = nodes[best].line as i64 + i64::from(parameters.looseness);
+        *finished.iter()
+            .min_by(|&&x, &&y| {
+                let distance = |n: usize| (nodes[n].line as i64 - target).abs();
+                distance(x).cmp(&distance(y)).then(nodes[x].demerits.partial_cmp(&nodes[y].demerits).unwrap())
+            })
+            .unwrap()
+    };
+    let mut breaks = Vec::new();
+    let mut node = Some(chosen);
+    while let Some(n) = node.filter(|&n| nodes[n].previous.is_some()) {
+        breaks.push(Break { position: nodes[n].position, ratio: nodes[n].ratio });
+        node = nodes[n].previous;
+    }
+    breaks.reverse();
+    Some(breaks)
+}
+
+fn is_flagged(element: &Element) -> bool {
+    matches!(*element, Element::Penalty { flagged: true, .. })
+}
+
+/// The totals up to where the line after a break at `b` starts: glue and penalties after a break are discarded,
+/// up to the next box or forced break.
+fn after_break(elements: &[Element], totals: &[Totals], b: usize, start: bool) -> Totals {
+    let mut i = if start { b } else { b + 1 };
+    while let Some(element) = elements.get(i) {
+        match *element {
+            Element::Box { .. } => break,
+            Element::Penalty { penalty, .. } if penalty <= -INFINITE_PENALTY => break,
+            _ => i += 1
+        }
+    }
+    totals[i]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Words of the given widths, with glue between them and a finished last line.
+    fn paragraph(words: &[f64]) -> Vec<Element> {
+        let mut elements = Vec::new();
+        for (i, &width) in words.iter().enumerate() {
+            if i > 0 {
+                elements.push(Element::Glue { width: 1.0, stretch: 1.0, shrink: 0.5 });
+            }
+            elements.push(Element::Box { width });
+        }
+        elements.push(Element::Glue { width: 0.0, stretch: f64::INFINITY, shrink: 0.0 });
+        elements.push(Element::Penalty { width: 0.0, penalty: -INFINITE_PENALTY, flagged: false });
+        elements
+    }
+
+    /// The number of words on each line.
+    fn line_lengths(elements: &[Element], breaks: &[Break]) -> Vec<usize> {
+        let mut start = 0;
+        breaks.iter()
+            .map(|b| {
+                let words = elements[start..b.position].iter().filter(|e| matches!(**e, Element::Box { .. })).count();
+                start = b.position;
+                words
+            })
+            .collect()
+    }
+
+    #[test]
+    fn avoids_loose_lines() {
+        // Filling each line greedily gives 2, 2 and 1 words, leaving the first line hopelessly loose.
+        let elements = paragraph(&[5.0, 2.0, 4.0, 4.0, 5.0]);
+        let breaks = break_lines(&elements, 12.0, &Parameters::default());
+        assert_eq!(line_lengths(&elements, &breaks), vec![3, 2]);
+        assert_eq!(breaks[0].ratio, -1.0);
+        assert_eq!(breaks.last().unwrap(), &Break { position: elements.len() - 1, ratio: 0.0 });
+
+        let looser = break_lines(&elements, 12.0, &Parameters::default().with_looseness(1).with_tolerance(10000.0));
+        assert_eq!(looser.len(), 3);
+    }
+
+    #[test]
+    fn always_breaks() {
+        let mut elements = paragraph(&[3.0, 30.0, 3.0, 3.0]);
+        elements.insert(2, Element::Penalty { width: 0.0, penalty: -INFINITE_PENALTY, flagged: false });
+        let breaks = break_lines(&elements, 10.0, &Parameters::default());
+        assert_eq!(breaks.iter().map(|b| b.position).collect::<Vec<_>>(), vec![2, 4, elements.len() - 1]);
+    }
+
+    #[test]
+    fn widows_and_orphans() {
+        let parameters = Parameters::default().with_orphan_penalty(100.0).with_widow_penalty(50.0);
+        assert_eq!(parameters.get_interline_penalty(1, 4), 100.0);
+        assert_eq!(parameters.get_interline_penalty(2, 4), 0.0);
+        assert_eq!(parameters.get_interline_penalty(3, 4), 50.0);
+        assert_eq!(parameters.get_interline_penalty(1, 2), 150.0);
+        assert_eq!(parameters.get_interline_penalty(0, 4), 0.0);
+    }
+}
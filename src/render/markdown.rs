@@ -0,0 +1,216 @@
+use ::eval::Document;
+use ::eval::Node;
+use std::io;
+use super::latex_math::to_latex;
+
+/// Writes `doc` as CommonMark, with its header as a YAML front-matter block.
+///
+/// Math is written `$like this$`, and display math between lines of `$$`, which CommonMark leaves alone and most wikis
+/// then typeset. Equation numbers are given with `\tag`, as MathJax and KaTeX understand it.
+pub fn render<W: io::Write>(doc: &Document, writer: &mut W) -> io::Result<()> {
+    let mut first = true;
+    if let Some(front_matter) = front_matter(doc) {
+        write!(writer, "{}", front_matter)?;
+        first = false;
+    }
+    for block in &doc.blocks {
+        if !first {
+            writeln!(writer)?;
+        }
+        first = false;
+        writeln!(writer, "{}", block_text(block))?;
+    }
+    Ok(())
+}
+
+/// The YAML front matter for the document's header, or `None` if it has nothing to say.
+fn front_matter(doc: &Document) -> Option<String> {
+    let meta = &doc.meta;
+    let mut fields = String::new();
+    let scalars = [("doctype", &meta.doctype), ("title", &meta.title), ("date", &meta.date), ("lang", &meta.language)];
+    for &(key, value) in &scalars {
+        if let Some(ref value) = *value {
+            fields.push_str(&format!("{}: {}\n", key, yaml_string(value)));
+        }
+    }
+    if !meta.authors.is_empty() {
+        fields.push_str("author:\n");
+        for author in &meta.authors {
+            fields.push_str(&format!("  - {}\n", yaml_string(author)));
+        }
+    }
+    if fields.is_empty() {
+        None
+    } else {
+        Some(format!("---\n{}---\n", fields))
+    }
+}
+
+fn block_text(node: &Node) -> String {
+    match *node {
+        // The number is only digits and dots, which need no escaping after the `#`s.
+        Node::Heading { level, ref number, ref content, .. } => {
+            let number = number.as_ref().map(|number| format!("{} ", number)).unwrap_or_default();
+            format!("{} {}{}", "#".repeat(level), number, inline_text(content))
+        },
+        Node::List(ref items) => {
+            let items: Vec<String> = items.iter()
+                .map(|item| format!("- {}", inline_text(item).replace('\n', "\n  ")))
+                .collect();
+            items.join("\n")
+        },
+        Node::Paragraph(ref content) => inline_text(content),
+        Node::Equation { ref formula, number, .. } => {
+            let tag = number.map(|number| format!(" \\tag{{{}}}", number)).unwrap_or_default();
+            format!("$$\n{}{}\n$$", to_latex(formula), tag)
+        },
+        Node::Figure { ref content, ref caption, number, .. } => {
+            let caption = if caption.is_empty() { String::new() } else { format!(": {}", inline_text(caption)) };
+            format!("{}\n\n*Figure {}{}*", inline_text(content), number, caption)
+        },
+        Node::Contents { ref entries, .. } => {
+            let top = entries.iter().map(|entry| entry.level).min().unwrap_or(1);
+            let mut lines = vec!["**Contents**".to_string(), String::new()];
+            for entry in entries {
+                let number = entry.number.as_ref().map(|number| format!("{} ", number)).unwrap_or_default();
+                lines.push(format!("{}- {}{}", "  ".repeat(entry.level - top), number, inline_text(&entry.title)));
+            }
+            lines.join("\n").trim_end().to_string()
+        },
+        ref inline => inline_text(std::slice::from_ref(inline))
+    }
+}
+
+fn inline_text(nodes: &[Node]) -> String {
+    let mut out = String::new();
+    write_inlines(nodes, &mut out);
+    out
+}
+
+fn write_inlines(nodes: &[Node], out: &mut String) {
+    for node in nodes {
+        match *node {
+            Node::Text(ref text) | Node::Ref { number: ref text, .. } => {
+                let line_start = out.is_empty() || out.ends_with('\n');
+                out.push_str(&escape(text, line_start));
+            },
+            Node::Space => out.push(' '),
+            Node::Anchor(_) | Node::Contents { .. } => (),
+            Node::PageRef { ref title, .. } => out.push_str(&escape(title, false)),
+            // A backslash at the end of a line is CommonMark's hard line break.
+            Node::LineBreak => out.push_str("\\\n"),
+            Node::Math(ref math) | Node::Equation { formula: ref math, .. } => {
+                out.push('$');
+                out.push_str(&to_latex(math));
+                out.push('$');
+            },
+            Node::Emph(ref content) => {
+                out.push('*');
+                write_inlines(content, out);
+                out.push('*');
+            },
+            Node::Strong(ref content) => {
+                out.push_str("**");
+                write_inlines(content, out);
+                out.push_str("**");
+            },
+            Node::Paragraph(ref content) | Node::Heading { ref content, .. } | Node::Figure { ref content, .. } => {
+                write_inlines(content, out)
+            },
+            Node::List(ref items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(' ');
+                    }
+                    write_inlines(item, out);
+                }
+            }
+        }
+    }
+}
+
+/// Escapes the characters Markdown would otherwise read as syntax. At the start of a line,
+/// `line_start` also escapes what would begin a heading, quote, list item or rule there.
+pub fn escape(text: &str, line_start: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            // `&` would start an entity reference, such as `&copy;`.
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '!' | '$' | '|' | '~' | '&' => {
+                escaped.push('\\');
+                escaped.push(c);
+            },
+            _ => escaped.push(c)
+        }
+    }
+    if line_start {
+        let digits = escaped.chars().take_while(char::is_ascii_digit).count();
+        let marker = escaped[digits..].chars().next();
+        if digits == 0 && matches!(marker, Some('-') | Some('+') | Some('=')) {
+            escaped.insert(0, '\\');
+        } else if digits > 0 && matches!(marker, Some('.') | Some(')')) {
+            escaped.insert(digits, '\\');
+        }
+    }
+    escaped
+}
+
+/// `text` as a double-quoted YAML scalar, which needs no thought about what it contains.
+fn yaml_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c)
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use ::render::test::render;
+
+    #[test]
+    fn commonmark() {
+        let md = render("{litx :doctype mla :title \"A \\\"quoted\\\" title\" :authors {list \"A\" \"B\"}}\n\n\
+            {h1 \"Intro\"}\nSome {emph \"stress\"} and {strong \"weight\"}, *stars* and_under [links] &amp;\\n\
+            - not a list\\n3. not numbered {$ x^2 $}.\n\n{h3 \"List\"}\n{list \"a\" {emph \"b\"}}", super::render);
+        assert_eq!(md, "\
+---
+doctype: \"mla\"
+title: \"A \\\"quoted\\\" title\"
+author:
+  - \"A\"
+  - \"B\"
+---
+
+# 1 Intro
+
+Some *stress* and **weight**, \\*stars\\* and\\_under \\[links\\] \\&amp;\\
+\\- not a list\\
+3\\. not numbered $x^{2}$.
+
+### 1.1.1 List
+
+- a
+- *b*
+");
+    }
+
+    #[test]
+    fn display_math() {
+        assert_eq!(render("So {$$ a = b $$} and {equation {$ \\frac{$\\alpha + 1$}2 $}}.", super::render),
+            "So\n\n$$\na = b\n$$\n\nand\n\n$$\n\\frac{\\alpha + 1}{2} \\tag{1}\n$$\n\n.\n");
+    }
+
+    #[test]
+    fn no_header_no_front_matter() {
+        assert_eq!(render("Just text.", super::render), "Just text.\n");
+    }
+}
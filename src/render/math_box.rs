@@ -0,0 +1,261 @@
+use ::math::MathNode;
+
+/// The height of letters above the baseline and their depth below it, as fractions of the font size.
+const ASCENT: f64 = 0.7;
+const DESCENT: f64 = 0.22;
+/// The size of scripts, and of the parts of a fraction, relative to the text around them.
+const SCRIPT_SCALE: f64 = 0.7;
+/// The height of fraction bars and the middle of `+` and `=`, above the baseline.
+const AXIS: f64 = 0.25;
+const RULE_THICKNESS: f64 = 0.05;
+/// Space around an operator, as fractions of the font size: TeX's thin, medium and thick spaces.
+const THIN: f64 = 3.0 / 18.0;
+const MEDIUM: f64 = 4.0 / 18.0;
+const THICK: f64 = 5.0 / 18.0;
+
+/// Whether a glyph is set in italics, as variables are, or upright.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub enum Style {
+    Italic,
+    Upright
+}
+
+/// Text placed in a box. `x` and `y` are the start of its baseline from the box's origin, with `y` upwards.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Glyph {
+    pub x: f64,
+    pub y: f64,
+    pub size: f64,
+    pub style: Style,
+    pub text: String
+}
+
+/// A filled rectangle, such as a fraction bar, with its bottom left corner at `x` and `y`.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Rule {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub thickness: f64
+}
+
+/// Laid out math: how far it reaches right of its origin, above its baseline and below it, and what is in it.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(PartialEq)]
+pub struct MathBox {
+    pub width: f64,
+    pub height: f64,
+    pub depth: f64,
+    pub glyphs: Vec<Glyph>,
+    pub rules: Vec<Rule>
+}
+
+impl MathBox {
+    fn glyph(text: &str, style: Style, size: f64, measure: &dyn Fn(&str, Style, f64) -> f64) -> Self {
+        MathBox {
+            width: measure(text, style, size),
+            height: ASCENT * size,
+            depth: DESCENT * size,
+            glyphs: vec![Glyph { x: 0.0, y: 0.0, size, style, text: text.to_string() }],
+            rules: Vec::new()
+        }
+    }
+
+    /// Adds the contents of `other` with its origin at `x` and `y`, growing this box to hold it.
+    fn add(&mut self, other: MathBox, x: f64, y: f64) {
+        self.width = self.width.max(x + other.width);
+        self.height = self.height.max(y + other.height);
+        self.depth = self.depth.max(other.depth - y);
+        self.glyphs.extend(other.glyphs.into_iter().map(|glyph| Glyph { x: glyph.x + x, y: glyph.y + y, ..glyph }));
+        self.rules.extend(other.rules.into_iter().map(|rule| Rule { x: rule.x + x, y: rule.y + y, ..rule }));
+    }
+}
+
+/// How an operator is spaced, after TeX's classes of math atoms.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+enum Class {
+    Ordinary,
+    Function,
+    Binary,
+    Relation,
+    Open,
+    Close,
+    Punctuation
+}
+
+fn class_of(node: &MathNode) -> Class {
+    match *node {
+        MathNode::Operator(ref text) => match text.as_str() {
+            "+" | "\u{2212}" | "±" | "∓" | "×" | "÷" | "⋅" | "∗" | "∪" | "∩" | "∧" | "∨" => Class::Binary,
+            "=" | "<" | ">" | "≤" | "≥" | "≠" | "≈" | "≡" | "∼" | "∝" | "→" | "←" | "⇒" | "⇔" | "∈" | "∉" | "⊂" | "⊆" | ":" => {
+                Class::Relation
+            },
+            "(" | "[" | "{" | "⟨" => Class::Open,
+            ")" | "]" | "}" | "⟩" => Class::Close,
+            "," | ";" => Class::Punctuation,
+            _ => Class::Ordinary
+        },
+        MathNode::Function(_) => Class::Function,
+        MathNode::Scripts { ref base, .. } => class_of(base),
+        _ => Class::Ordinary
+    }
+}
+
+/// Lays out `nodes` at `size` points, measuring text with `measure`. Only spacing and positions are worked out here;
+/// a paginated backend chooses the fonts for each style and places the glyphs and rules itself.
+pub fn lay_out(nodes: &[MathNode], size: f64, measure: &dyn Fn(&str, Style, f64) -> f64) -> MathBox {
+    row(nodes, size, true, measure)
+}
+
+/// A row of terms. Operators are only spaced out at the outermost level, as TeX does not space them in scripts.
+fn row(nodes: &[MathNode], size: f64, spaced: bool, measure: &dyn Fn(&str, Style, f64) -> f64) -> MathBox {
+    let mut classes: Vec<Class> = nodes.iter().map(class_of).collect();
+    // A binary operator with nothing to its left, such as a leading minus sign, is unary and set tight.
+    for i in 0..classes.len() {
+        let previous = if i == 0 { None } else { Some(classes[i - 1]) };
+        if classes[i] == Class::Binary
+            && matches!(previous, None | Some(Class::Binary) | Some(Class::Relation) | Some(Class::Open) | Some(Class::Punctuation)) {
+            classes[i] = Class::Ordinary;
+        }
+    }
+    let mut line = MathBox::default();
+    let mut x = 0.0;
+    for (i, node) in nodes.iter().enumerate() {
+        if i > 0 && spaced {
+            x += size * match (classes[i - 1], classes[i]) {
+                (_, Class::Relation) | (Class::Relation, _) => THICK,
+                (_, Class::Binary) | (Class::Binary, _) => MEDIUM,
+                (Class::Punctuation, _) => THIN,
+                (Class::Function, next) if next != Class::Open => THIN,
+                _ => 0.0
+            };
+        }
+        let term = term(node, size, measure);
+        let width = term.width;
+        line.add(term, x, 0.0);
+        x += width;
+    }
+    line.width = x;
+    line
+}
+
+fn term(node: &MathNode, size: f64, measure: &dyn Fn(&str, Style, f64) -> f64) -> MathBox {
+    match *node {
+        MathNode::Identifier(ref text) => MathBox::glyph(text, Style::Italic, size, measure),
+        MathNode::Function(ref text) | MathNode::Number(ref text) | MathNode::Operator(ref text) | MathNode::Text(ref text) => {
+            MathBox::glyph(text, Style::Upright, size, measure)
+        },
+        MathNode::Row(ref nodes) => row(nodes, size, false, measure),
+        MathNode::Scripts { ref base, ref subscript, ref superscript } => {
+            let mut scripted = term(base, size, measure);
+            let x = scripted.width + 0.05 * size;
+            let script_size = size * SCRIPT_SCALE;
+            let superscript = superscript.as_ref().map(|script| term(script, script_size, measure));
+            let subscript = subscript.as_ref().map(|script| term(script, script_size, measure));
+            let mut rise = (scripted.height - 0.3 * size).max(0.4 * size);
+            let drop = (scripted.depth + 0.05 * size).max(0.2 * size);
+            if let (Some(ref sup), Some(ref sub)) = (&superscript, &subscript) {
+                // Keep a gap between the two scripts when they are stacked.
+                let gap = (rise - sup.depth) - (sub.height - drop);
+                rise += (0.1 * size - gap).max(0.0);
+            }
+            let mut width = scripted.width;
+            if let Some(sup) = superscript {
+                width = width.max(x + sup.width);
+                scripted.add(sup, x, rise);
+            }
+            if let Some(sub) = subscript {
+                width = width.max(x + sub.width);
+                scripted.add(sub, x, -drop);
+            }
+            scripted.width = width;
+            scripted
+        },
+        MathNode::Fraction { ref numerator, ref denominator } => {
+            let part_size = size * SCRIPT_SCALE;
+            let numerator = term(numerator, part_size, measure);
+            let denominator = term(denominator, part_size, measure);
+            let padding = 0.1 * size;
+            let width = numerator.width.max(denominator.width) + 2.0 * padding;
+            let thickness = RULE_THICKNESS * size;
+            let axis = AXIS * size;
+            let gap = 0.1 * size;
+            let mut fraction = MathBox::default();
+            let numerator_x = (width - numerator.width) / 2.0;
+            let numerator_y = axis + thickness / 2.0 + gap + numerator.depth;
+            fraction.add(numerator, numerator_x, numerator_y);
+            let denominator_x = (width - denominator.width) / 2.0;
+            let denominator_y = axis - thickness / 2.0 - gap - denominator.height;
+            fraction.add(denominator, denominator_x, denominator_y);
+            fraction.rules.push(Rule { x: padding / 2.0, y: axis - thickness / 2.0, width: width - padding, thickness });
+            fraction.width = width;
+            fraction
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::math::MathNode::*;
+    use super::*;
+
+    /// Every character half an em wide.
+    fn measure(text: &str, _: Style, size: f64) -> f64 {
+        text.chars().count() as f64 * size / 2.0
+    }
+
+    fn atom(kind: fn(String) -> ::math::MathNode, text: &str) -> ::math::MathNode {
+        kind(text.to_string())
+    }
+
+    fn round(value: f64) -> f64 {
+        (value * 1000.0).round() / 1000.0
+    }
+
+    #[test]
+    fn spaces_operators_and_raises_scripts() {
+        let nodes = vec![
+            atom(Operator, "\u{2212}"),
+            Scripts { base: Box::new(atom(Identifier, "x")), subscript: None, superscript: Some(Box::new(atom(Number, "2"))) },
+            atom(Operator, "="),
+            atom(Number, "4")
+        ];
+        let math = lay_out(&nodes, 18.0, &measure);
+        let positions: Vec<(&str, f64, f64, f64)> = math.glyphs.iter()
+            .map(|glyph| (glyph.text.as_str(), round(glyph.x), round(glyph.y), round(glyph.size)))
+            .collect();
+        // The leading minus is unary, so only the relation is spaced, by 5/18 of an em on each side.
+        assert_eq!(positions, vec![
+            ("\u{2212}", 0.0, 0.0, 18.0),
+            ("x", 9.0, 0.0, 18.0),
+            ("2", 18.9, 7.2, 12.6),
+            ("=", 30.2, 0.0, 18.0),
+            ("4", 44.2, 0.0, 18.0)
+        ]);
+        assert_eq!(round(math.width), 53.2);
+        assert_eq!(math.glyphs[1].style, Style::Italic);
+    }
+
+    #[test]
+    fn stacks_fractions_around_a_rule() {
+        let nodes = vec![Fraction { numerator: Box::new(atom(Number, "1")), denominator: Box::new(atom(Number, "22")) }];
+        let math = lay_out(&nodes, 10.0, &measure);
+        let (numerator, denominator) = (&math.glyphs[0], &math.glyphs[1]);
+        let rule = &math.rules[0];
+        assert!(numerator.y - DESCENT * 7.0 > rule.y + rule.thickness);
+        assert!(denominator.y + ASCENT * 7.0 < rule.y);
+        assert_eq!(math.width, 7.0 + 2.0);
+        assert_eq!((numerator.x, denominator.x), (2.75, 1.0));
+        assert!(math.height > ASCENT * 10.0 && math.depth > DESCENT * 10.0);
+    }
+}
@@ -0,0 +1,55 @@
+use ::math::Formula;
+use ::math::MathNode;
+use super::html::escape;
+
+/// `formula` as a MathML `<math>` element, with its source as the alternative text for anything that cannot show it.
+/// Display math is set as a block, centred on a line of its own.
+pub fn to_mathml(formula: &Formula, display: bool) -> String {
+    let block = if display { " display=\"block\"" } else { "" };
+    let mut mathml = format!("<math{} alttext=\"{}\">", block, escape(&formula.source));
+    for node in &formula.nodes {
+        write_node(node, &mut mathml);
+    }
+    mathml.push_str("</math>");
+    mathml
+}
+
+fn write_node(node: &MathNode, mathml: &mut String) {
+    match *node {
+        // Browsers set a single-letter `mi` in italics, and a longer one such as a function name upright.
+        MathNode::Identifier(ref text) | MathNode::Function(ref text) => write_leaf("mi", text, mathml),
+        MathNode::Number(ref text) => write_leaf("mn", text, mathml),
+        MathNode::Operator(ref text) => write_leaf("mo", text, mathml),
+        MathNode::Text(ref text) => write_leaf("mtext", text, mathml),
+        MathNode::Row(ref nodes) => {
+            mathml.push_str("<mrow>");
+            for node in nodes {
+                write_node(node, mathml);
+            }
+            mathml.push_str("</mrow>");
+        },
+        MathNode::Scripts { ref base, ref subscript, ref superscript } => {
+            let tag = match (subscript.is_some(), superscript.is_some()) {
+                (true, true) => "msubsup",
+                (true, false) => "msub",
+                _ => "msup"
+            };
+            mathml.push_str(&format!("<{}>", tag));
+            write_node(base, mathml);
+            for script in subscript.iter().chain(superscript) {
+                write_node(script, mathml);
+            }
+            mathml.push_str(&format!("</{}>", tag));
+        },
+        MathNode::Fraction { ref numerator, ref denominator } => {
+            mathml.push_str("<mfrac>");
+            write_node(numerator, mathml);
+            write_node(denominator, mathml);
+            mathml.push_str("</mfrac>");
+        }
+    }
+}
+
+fn write_leaf(tag: &str, text: &str, mathml: &mut String) {
+    mathml.push_str(&format!("<{}>{}</{}>", tag, escape(text), tag));
+}
@@ -0,0 +1,27 @@
+pub mod html;
+pub mod latex;
+pub mod latex_math;
+pub mod line_break;
+pub mod markdown;
+pub mod math_box;
+pub mod mathml;
+pub mod pdf;
+pub mod text;
+
+#[cfg(test)]
+mod test {
+    use ::eval::CommandRegistry;
+    use ::eval::Document;
+    use ::lex::Lexer;
+    use ::parse::parse;
+    use std::io;
+
+    /// Evaluates `source` with the built-in commands and returns what `render` writes for it.
+    pub fn render<F: FnOnce(&Document, &mut Vec<u8>) -> io::Result<()>>(source: &str, render: F) -> String {
+        let doc = ::ast::lower(&parse(Lexer::new(source)).unwrap());
+        let doc = ::eval::evaluate(&doc, &CommandRegistry::with_builtins()).unwrap();
+        let mut out = Vec::new();
+        render(&doc, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+}
@@ -0,0 +1,165 @@
+/// The four Times faces, and Symbol for Greek letters and math. They are among the fourteen standard fonts every
+/// PDF reader must provide, so a document can use them by name without carrying any font data of its own.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub enum Font {
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+    Symbol
+}
+
+pub const FONTS: [Font; 5] = [Font::Regular, Font::Bold, Font::Italic, Font::BoldItalic, Font::Symbol];
+
+/// Advance widths in thousandths of an em for the printable ASCII characters, from the Adobe font metrics.
+const REGULAR_WIDTHS: [u16; 95] = [
+    250, 333, 408, 500, 500, 833, 778, 180, 333, 333, 500, 564, 250, 333, 250, 278,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 278, 278, 564, 564, 564, 444,
+    921, 722, 667, 667, 722, 611, 556, 722, 722, 333, 389, 722, 611, 889, 722, 722,
+    556, 722, 667, 556, 611, 722, 722, 944, 722, 722, 611, 333, 278, 333, 469, 500,
+    333, 444, 500, 444, 500, 444, 333, 500, 500, 278, 278, 500, 278, 778, 500, 500,
+    500, 500, 333, 389, 278, 500, 500, 722, 500, 500, 444, 480, 200, 480, 541
+];
+
+const BOLD_WIDTHS: [u16; 95] = [
+    250, 333, 555, 500, 500, 1000, 833, 278, 333, 333, 500, 570, 250, 333, 250, 278,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 570, 570, 570, 500,
+    930, 722, 667, 722, 722, 667, 611, 778, 778, 389, 500, 778, 667, 944, 722, 778,
+    611, 778, 722, 556, 667, 722, 722, 1000, 722, 722, 667, 333, 278, 333, 581, 500,
+    333, 500, 556, 444, 556, 444, 333, 500, 556, 278, 333, 556, 278, 833, 556, 500,
+    556, 556, 444, 389, 333, 556, 500, 722, 500, 500, 444, 394, 220, 394, 520
+];
+
+const ITALIC_WIDTHS: [u16; 95] = [
+    250, 333, 420, 500, 500, 833, 778, 214, 333, 333, 500, 675, 250, 333, 250, 278,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 675, 675, 675, 500,
+    920, 611, 611, 667, 722, 611, 611, 722, 722, 333, 444, 667, 556, 833, 667, 722,
+    611, 722, 611, 500, 556, 722, 611, 833, 611, 556, 556, 389, 278, 389, 422, 500,
+    333, 500, 500, 444, 500, 444, 278, 500, 500, 278, 278, 444, 278, 722, 500, 500,
+    500, 500, 389, 389, 278, 500, 444, 667, 444, 444, 389, 400, 275, 400, 541
+];
+
+const BOLD_ITALIC_WIDTHS: [u16; 95] = [
+    250, 389, 555, 500, 500, 833, 778, 278, 333, 333, 500, 570, 250, 333, 250, 278,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 570, 570, 570, 500,
+    832, 667, 667, 667, 722, 667, 667, 722, 778, 389, 500, 667, 611, 889, 722, 722,
+    611, 722, 667, 556, 611, 722, 667, 889, 667, 611, 611, 333, 278, 333, 570, 500,
+    333, 500, 500, 444, 500, 444, 333, 500, 556, 278, 278, 500, 278, 778, 556, 500,
+    500, 500, 389, 389, 278, 556, 444, 667, 500, 444, 389, 348, 220, 348, 570
+];
+
+/// The characters the Symbol font has beyond ASCII which math uses, with their codes in its built-in encoding and
+/// their widths.
+const SYMBOL: [(char, u8, u16); 68] = [
+    ('α', 0x61, 631), ('β', 0x62, 549), ('χ', 0x63, 549), ('δ', 0x64, 494), ('ε', 0x65, 439), ('ϵ', 0x65, 439),
+    ('ϕ', 0x66, 521), ('γ', 0x67, 411), ('η', 0x68, 603), ('ι', 0x69, 329), ('φ', 0x6A, 603), ('κ', 0x6B, 549),
+    ('λ', 0x6C, 549), ('μ', 0x6D, 576), ('ν', 0x6E, 521), ('π', 0x70, 549), ('θ', 0x71, 521), ('ρ', 0x72, 549),
+    ('σ', 0x73, 603), ('τ', 0x74, 439), ('υ', 0x75, 576), ('ω', 0x77, 686), ('ξ', 0x78, 493), ('ψ', 0x79, 686),
+    ('ζ', 0x7A, 494), ('ϑ', 0x4A, 631), ('Γ', 0x47, 603), ('Δ', 0x44, 612), ('Θ', 0x51, 741), ('Λ', 0x4C, 686),
+    ('Ξ', 0x58, 645), ('Π', 0x50, 768), ('Σ', 0x53, 592), ('Υ', 0xA1, 620), ('Φ', 0x46, 763), ('Ψ', 0x59, 795),
+    ('Ω', 0x57, 768),
+    ('∞', 0xA5, 713), ('∂', 0xB6, 494), ('∇', 0xD1, 713), ('⋅', 0xD7, 250), ('∗', 0x2A, 500), ('\u{2212}', 0x2D, 549),
+    ('≤', 0xA3, 549), ('≥', 0xB3, 549), ('≠', 0xB9, 549), ('≈', 0xBB, 549), ('≡', 0xBA, 549), ('∼', 0x7E, 549),
+    ('∝', 0xB5, 713), ('→', 0xAE, 987), ('←', 0xAC, 987), ('⇒', 0xDE, 987), ('⇔', 0xDB, 1042), ('∈', 0xCE, 713),
+    ('∉', 0xCF, 713), ('⊂', 0xCC, 713), ('⊆', 0xCD, 713), ('∪', 0xC8, 768), ('∩', 0xC7, 768), ('∀', 0x22, 713),
+    ('∃', 0x24, 549), ('∧', 0xD9, 603), ('∨', 0xDA, 603), ('∑', 0xE5, 713), ('∏', 0xD5, 823), ('∫', 0xF2, 274),
+    ('′', 0xA2, 247)
+];
+
+/// The unaccented letter whose width stands in for each Latin-1 character from `À` to `ÿ`.
+const LATIN_1_BASES: &[u8; 64] = b"AAAAAAACEEEEIIIIDNOOOOO+OUUUUYPbaaaaaaaceeeeiiiidnooooo+ouuuuypy";
+
+impl Font {
+    pub fn get_base_font(self) -> &'static str {
+        match self {
+            Font::Regular => "Times-Roman",
+            Font::Bold => "Times-Bold",
+            Font::Italic => "Times-Italic",
+            Font::BoldItalic => "Times-BoldItalic",
+            Font::Symbol => "Symbol"
+        }
+    }
+
+    /// The resource name pages use to select the font.
+    pub fn get_resource_name(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+            Font::Italic => "F3",
+            Font::BoldItalic => "F4",
+            Font::Symbol => "F5"
+        }
+    }
+
+    pub fn with_bold(self) -> Self {
+        match self {
+            Font::Regular | Font::Bold => Font::Bold,
+            Font::Italic | Font::BoldItalic => Font::BoldItalic,
+            Font::Symbol => Font::Symbol
+        }
+    }
+
+    pub fn with_italic(self) -> Self {
+        match self {
+            Font::Regular | Font::Italic => Font::Italic,
+            Font::Bold | Font::BoldItalic => Font::BoldItalic,
+            Font::Symbol => Font::Symbol
+        }
+    }
+
+    /// Whether the Symbol font has every character of `text`.
+    pub fn has_symbols(text: &str) -> bool {
+        text.chars().all(|c| SYMBOL.iter().any(|&(symbol, _, _)| symbol == c))
+    }
+
+    /// The width of `text` set at `size` points.
+    pub fn get_width(self, text: &str, size: f64) -> f64 {
+        let units: u32 = text.chars().map(|c| u32::from(self.get_char_width(c))).sum();
+        f64::from(units) * size / 1000.0
+    }
+
+    fn get_char_width(self, c: char) -> u16 {
+        let widths = match self {
+            Font::Regular => &REGULAR_WIDTHS,
+            Font::Bold => &BOLD_WIDTHS,
+            Font::Italic => &ITALIC_WIDTHS,
+            Font::BoldItalic => &BOLD_ITALIC_WIDTHS,
+            Font::Symbol => return SYMBOL.iter().find(|&&(symbol, _, _)| symbol == c).map_or(500, |&(_, _, width)| width)
+        };
+        match win_ansi(c) {
+            code @ 32..=126 => widths[usize::from(code - 32)],
+            code @ 192..=255 => self.get_char_width(char::from(LATIN_1_BASES[usize::from(code - 192)])),
+            0x85 | 0x97 => 1000,
+            0x91 | 0x92 => 333,
+            0x93 | 0x94 => 444,
+            0x95 => 350,
+            _ => 500
+        }
+    }
+
+    /// The byte for `c` in the font's encoding: WinAnsiEncoding for the Times faces, and the Symbol font's own.
+    /// Characters the encoding lacks become `?`.
+    pub fn encode(self, c: char) -> u8 {
+        match self {
+            Font::Symbol => SYMBOL.iter().find(|&&(symbol, _, _)| symbol == c).map_or(b'?', |&(_, code, _)| code),
+            _ => win_ansi(c)
+        }
+    }
+}
+
+fn win_ansi(c: char) -> u8 {
+    match c {
+        ' '..='~' | '\u{a0}'..='\u{ff}' => c as u8,
+        '\u{2026}' => 0x85,
+        '\u{2018}' => 0x91,
+        '\u{2019}' => 0x92,
+        '\u{201c}' => 0x93,
+        '\u{201d}' => 0x94,
+        '\u{2022}' => 0x95,
+        '\u{2013}' => 0x96,
+        '\u{2014}' => 0x97,
+        _ => b'?'
+    }
+}
@@ -0,0 +1,842 @@
+use ::eval::ContentsEntry;
+use ::eval::Document;
+use ::eval::Node;
+use ::hyphenation::Patterns;
+use ::math::Formula;
+use ::render::math_box;
+use ::render::math_box::MathBox;
+use ::render::math_box::Style;
+use ::render::line_break;
+use ::render::line_break::Element;
+use ::render::line_break::INFINITE_PENALTY;
+use ::render::line_break::Parameters;
+use std::collections::HashMap;
+use super::Font;
+use super::PageSetup;
+
+/// The size of body text, in points.
+const BODY_SIZE: f64 = 11.0;
+/// The distance between baselines, as a multiple of the font size.
+const LEADING: f64 = 1.25;
+const HEADING_SIZES: [f64; 6] = [18.0, 14.0, 12.0, 11.0, 11.0, 11.0];
+const TITLE_SIZE: f64 = 20.0;
+const BYLINE_SIZE: f64 = 12.0;
+const PAGE_NUMBER_SIZE: f64 = 9.0;
+/// How far list items are indented, with their bullets hanging in the space.
+const LIST_INDENT: f64 = 18.0;
+/// The cost of breaking a line after a hyphen added to a word, as TeX's `\hyphenpenalty`.
+const HYPHEN_PENALTY: f64 = 50.0;
+/// The cost, in the units of penalties, of each line's worth of space a page break leaves empty at the foot of a page.
+const EMPTY_LINE_COST: f64 = 50.0;
+/// The most times a document is laid out while waiting for its page references to settle.
+const MAX_PASSES: usize = 5;
+/// What a page reference prints before the page of its label is known.
+const UNKNOWN_PAGE: &str = "??";
+/// The room kept at the right of a table of contents for page numbers.
+const PAGE_COLUMN: f64 = 30.0;
+
+/// A run of text placed on a page. `x` and `y` are the start of its baseline, in points from the bottom left corner.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Placed {
+    pub x: f64,
+    pub y: f64,
+    pub font: Font,
+    pub size: f64,
+    pub text: String
+}
+
+/// A filled rectangle, such as a fraction bar, with its bottom left corner at `x` and `y`.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64
+}
+
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(PartialEq)]
+pub struct Page {
+    pub items: Vec<Placed>,
+    pub rects: Vec<Rect>
+}
+
+/// All or part of a word: text in a single font, or a formula, which is never broken.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum Run {
+    Text {
+        font: Font,
+        text: String
+    },
+    Math(Formula)
+}
+
+impl Run {
+    pub fn get_width(&self, size: f64) -> f64 {
+        match *self {
+            Run::Text { font, ref text } => font.get_width(text, size),
+            Run::Math(ref formula) => lay_out_math(formula, size).width
+        }
+    }
+
+    /// The font of a run of text. Anything added to the end of a formula, such as a hyphen, is set in `Font::Regular`.
+    fn get_font(&self) -> Font {
+        match *self {
+            Run::Text { font, .. } => font,
+            Run::Math(_) => Font::Regular
+        }
+    }
+}
+
+/// Part of a paragraph once its styling has been resolved into fonts.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum Item {
+    Word(Vec<Run>),
+    Space,
+    Break
+}
+
+/// One line of a broken paragraph. `width` is its natural width, with single spaces between the words.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Line {
+    pub words: Vec<Vec<Run>>,
+    pub width: f64,
+    /// How far the spaces stretch to justify the line, or shrink if negative, as a fraction of how far they can.
+    /// The last line of a paragraph, and any line ended by a forced break, has a ratio of zero.
+    pub ratio: f64
+}
+
+/// Lays out `doc` onto pages: the title block, then each block in turn, starting a new page whenever the next line
+/// would run into the bottom margin. Every page is numbered at the foot.
+///
+/// Page references and tables of contents take the pages their labels and headings were on the last time round, so
+/// the document is laid out again until every label and heading stays on the same page.
+pub fn lay_out(doc: &Document, setup: &PageSetup, patterns: &Patterns) -> Vec<Page> {
+    let mut label_pages = HashMap::new();
+    let mut heading_pages = Vec::new();
+    let mut passes = 1;
+    loop {
+        let blocks = with_page_numbers(&doc.blocks, &label_pages);
+        let mut layout = Layout {
+            setup,
+            patterns,
+            pages: Vec::new(),
+            page: Page::default(),
+            cursor: setup.height - setup.margin_top,
+            parameters: Parameters::default(),
+            anchors: HashMap::new(),
+            headings: Vec::new(),
+            known_headings: &heading_pages
+        };
+        layout.title_block(doc);
+        for block in &blocks {
+            layout.block(block);
+        }
+        let anchors = std::mem::take(&mut layout.anchors);
+        let headings = std::mem::take(&mut layout.headings);
+        let pages = layout.finish();
+        if (anchors == label_pages && headings == heading_pages) || passes == MAX_PASSES {
+            return pages;
+        }
+        label_pages = anchors;
+        heading_pages = headings;
+        passes += 1;
+    }
+}
+
+struct Layout<'s> {
+    setup: &'s PageSetup,
+    patterns: &'s Patterns,
+    pages: Vec<Page>,
+    page: Page,
+    /// The top of the space left on the page, in points from the bottom edge.
+    cursor: f64,
+    parameters: Parameters,
+    /// The page number of each label laid out so far.
+    anchors: HashMap<String, usize>,
+    /// The page number of each heading laid out so far, in order.
+    headings: Vec<usize>,
+    /// The page number of each heading on the previous pass.
+    known_headings: &'s [usize]
+}
+
+impl <'s> Layout<'s> {
+    fn title_block(&mut self, doc: &Document) {
+        let meta = &doc.meta;
+        let mut lines = Vec::new();
+        if let Some(ref title) = meta.title {
+            lines.push((Font::Bold, TITLE_SIZE, title.clone()));
+        }
+        if !meta.authors.is_empty() {
+            lines.push((Font::Regular, BYLINE_SIZE, meta.authors.join(", ")));
+        }
+        if let Some(ref date) = meta.date {
+            lines.push((Font::Regular, BYLINE_SIZE, date.clone()));
+        }
+        for (font, size, text) in lines {
+            let items = words(&text, font);
+            for line in break_lines(&items, size, self.setup.get_text_width(), &self.parameters, self.patterns) {
+                let baseline = self.next_line(size);
+                let x = self.setup.margin_left + (self.setup.get_text_width() - line.width).max(0.0) / 2.0;
+                self.place_line(&line, x, baseline, size, false);
+            }
+        }
+        if !self.page.items.is_empty() {
+            self.cursor -= TITLE_SIZE;
+        }
+    }
+
+    /// Lays out a block, and notes the page it starts on as the page of every label in it, and of it if it is a heading.
+    fn block(&mut self, node: &Node) {
+        let start = (self.pages.len(), self.page.items.len());
+        self.block_content(node);
+        // The block starts on the page it started on, unless that page had to be finished before anything was placed.
+        let page = match self.pages.get(start.0) {
+            Some(page) if page.items.len() == start.1 => start.0 + 2,
+            _ => start.0 + 1
+        };
+        let mut labels = Vec::new();
+        anchors(std::slice::from_ref(node), &mut labels);
+        for label in labels {
+            self.anchors.insert(label, page);
+        }
+        if let Node::Heading { listed: true, .. } = *node {
+            self.headings.push(page);
+        }
+    }
+
+    fn block_content(&mut self, node: &Node) {
+        let width = self.setup.get_text_width();
+        match *node {
+            Node::Heading { level, ref number, ref content, .. } => {
+                let size = HEADING_SIZES[level - 1];
+                let lines = break_lines(&flatten(&Node::numbered(number.as_deref(), content), Font::Bold), size, width, &self.parameters, self.patterns);
+                self.skip(size * 0.8);
+                // Keep the heading with at least the first line of what follows it.
+                self.make_room(lines.len() as f64 * size * LEADING + BODY_SIZE * LEADING);
+                for line in &lines {
+                    let baseline = self.next_line(size);
+                    self.place_line(line, self.setup.margin_left, baseline, size, true);
+                }
+                self.skip(size * 0.4);
+            },
+            Node::List(ref items) => {
+                for item in items {
+                    let lines = break_lines(&flatten(item, Font::Regular), BODY_SIZE, width - LIST_INDENT, &self.parameters, self.patterns);
+                    let bullet = (self.setup.margin_left + LIST_INDENT / 3.0, "\u{2022}");
+                    self.place_lines(&lines, self.setup.margin_left + LIST_INDENT, Some(bullet));
+                    self.skip(BODY_SIZE * 0.2);
+                }
+                self.skip(BODY_SIZE * 0.4);
+            },
+            Node::Paragraph(ref content) => self.paragraph(content),
+            Node::Equation { ref formula, number, .. } => self.equation(formula, number),
+            Node::Figure { ref content, ref caption, number, .. } => self.figure(content, caption, number),
+            Node::Contents { ref entries, pages } => self.contents(entries, pages),
+            Node::Anchor(_) => (),
+            ref inline => self.paragraph(std::slice::from_ref(inline))
+        }
+    }
+
+    fn paragraph(&mut self, content: &[Node]) {
+        let width = self.setup.get_text_width();
+        let lines = break_lines(&flatten(content, Font::Regular), BODY_SIZE, width, &self.parameters, self.patterns);
+        self.place_lines(&lines, self.setup.margin_left, None);
+        self.skip(BODY_SIZE * 0.6);
+    }
+
+    /// Centres display math between the margins, with its number if any at the right margin.
+    fn equation(&mut self, formula: &Formula, number: Option<usize>) {
+        let math = lay_out_math(formula, BODY_SIZE);
+        // Room above and below the math as for a line of text, or more if it reaches further.
+        let height = math.height.max(BODY_SIZE * 0.8);
+        let depth = math.depth.max(BODY_SIZE * 0.2);
+        let gap = BODY_SIZE * (LEADING - 1.0);
+        self.make_room(height + depth + 2.0 * gap);
+        let baseline = self.cursor - gap - height;
+        let x = self.setup.margin_left + (self.setup.get_text_width() - math.width).max(0.0) / 2.0;
+        self.place_math(&math, x, baseline);
+        if let Some(number) = number {
+            let label = format!("({})", number);
+            let label_x = self.setup.width - self.setup.margin_right - Font::Regular.get_width(&label, BODY_SIZE);
+            self.place_text(label_x, baseline, Font::Regular, BODY_SIZE, &label);
+        }
+        self.cursor = baseline - depth - gap;
+        self.skip(BODY_SIZE * 0.6);
+    }
+
+    /// Centres the lines of a figure between the margins, with its caption below it.
+    fn figure(&mut self, content: &[Node], caption: &[Node], number: usize) {
+        let width = self.setup.get_text_width();
+        let lines = break_lines(&flatten(content, Font::Regular), BODY_SIZE, width, &self.parameters, self.patterns);
+        let label = if caption.is_empty() { format!("Figure {}", number) } else { format!("Figure {}:", number) };
+        let mut caption_items = words(&label, Font::Bold);
+        flatten_into(caption, Font::Regular, &mut caption_items);
+        let caption_lines = break_lines(&caption_items, BODY_SIZE, width, &self.parameters, self.patterns);
+        let gap = BODY_SIZE * 0.4;
+        // A figure is never split across pages.
+        self.make_room((lines.len() + caption_lines.len()) as f64 * BODY_SIZE * LEADING + gap);
+        for (i, line) in lines.iter().chain(&caption_lines).enumerate() {
+            if i == lines.len() && i > 0 {
+                self.cursor -= gap;
+            }
+            let baseline = self.next_line(BODY_SIZE);
+            let x = self.setup.margin_left + (width - line.width).max(0.0) / 2.0;
+            self.place_line(line, x, baseline, BODY_SIZE, false);
+        }
+        self.skip(BODY_SIZE * 0.6);
+    }
+
+    /// Lists the headings under a title, each indented by its level, with its page at the right margin if `pages` is set.
+    fn contents(&mut self, entries: &[ContentsEntry], pages: bool) {
+        let width = self.setup.get_text_width();
+        let size = HEADING_SIZES[1];
+        self.skip(size * 0.8);
+        let baseline = self.next_line(size);
+        self.place_text(self.setup.margin_left, baseline, Font::Bold, size, "Contents");
+        self.skip(size * 0.4);
+        for entry in entries {
+            let indent = LIST_INDENT * (entry.level - 1) as f64;
+            let mut items = entry.number.as_ref().map(|number| words(number, Font::Regular)).unwrap_or_default();
+            flatten_into(&entry.title, Font::Regular, &mut items);
+            let measure = width - indent - if pages { PAGE_COLUMN } else { 0.0 };
+            let lines = break_lines(&items, BODY_SIZE, measure, &self.parameters, self.patterns);
+            for (i, line) in lines.iter().enumerate() {
+                let baseline = self.next_line(BODY_SIZE);
+                self.place_line(line, self.setup.margin_left + indent, baseline, BODY_SIZE, false);
+                if pages && i + 1 == lines.len() {
+                    let page = self.known_headings.get(entry.heading)
+                        .map(|page| page.to_string())
+                        .unwrap_or_else(|| UNKNOWN_PAGE.to_string());
+                    let x = self.setup.width - self.setup.margin_right - Font::Regular.get_width(&page, BODY_SIZE);
+                    self.place_text(x, baseline, Font::Regular, BODY_SIZE, &page);
+                }
+            }
+        }
+        self.skip(BODY_SIZE * 0.6);
+    }
+
+    /// Places the lines of a body text paragraph starting at `x`, with `marker` before the first line if given.
+    ///
+    /// Where the paragraph has to be split across pages, the split goes where it costs least: a widow or orphan is
+    /// weighed against the lines of space left empty by breaking the page earlier.
+    fn place_lines(&mut self, lines: &[Line], x: f64, marker: Option<(f64, &str)>) {
+        let height = BODY_SIZE * LEADING;
+        let mut done = 0;
+        while done < lines.len() {
+            let room = ((self.cursor - self.setup.margin_bottom) / height + 1e-9).floor().max(0.0) as usize;
+            let mut end = lines.len();
+            if done + room < lines.len() {
+                let last = done + room;
+                // An empty page has to take at least one line, even one too tall for it.
+                let first = if self.page.items.is_empty() { done + 1 } else { done };
+                let cost = |j: usize| self.parameters.get_interline_penalty(j, lines.len()) + (last - j) as f64 * EMPTY_LINE_COST;
+                end = if first > last {
+                    first
+                } else {
+                    (first..=last).rev().min_by(|&a, &b| cost(a).partial_cmp(&cost(b)).unwrap()).unwrap()
+                };
+            }
+            for (i, line) in lines.iter().enumerate().take(end).skip(done) {
+                let baseline = self.next_line(BODY_SIZE);
+                if let (0, Some((marker_x, text))) = (i, marker) {
+                    self.place_text(marker_x, baseline, Font::Regular, BODY_SIZE, text);
+                }
+                self.place_line(line, x, baseline, BODY_SIZE, true);
+            }
+            if end < lines.len() {
+                self.new_page();
+            }
+            done = end;
+        }
+    }
+
+    /// Moves down by `amount`, except at the top of a page, where space would only push the text out of line.
+    fn skip(&mut self, amount: f64) {
+        if !self.page.items.is_empty() {
+            self.cursor -= amount;
+        }
+    }
+
+    /// Starts a new page unless `height` points still fit on this one, or this one is empty anyway.
+    fn make_room(&mut self, height: f64) {
+        if self.cursor - height < self.setup.margin_bottom && !self.page.items.is_empty() {
+            self.new_page();
+        }
+    }
+
+    fn new_page(&mut self) {
+        if !self.page.items.is_empty() {
+            self.pages.push(std::mem::take(&mut self.page));
+        }
+        self.cursor = self.setup.height - self.setup.margin_top;
+    }
+
+    /// Takes room for one line of text at `size`, returning its baseline.
+    fn next_line(&mut self, size: f64) -> f64 {
+        self.make_room(size * LEADING);
+        let baseline = self.cursor - size;
+        self.cursor -= size * LEADING;
+        baseline
+    }
+
+    /// Places `line` starting at `x`, stretching or shrinking its spaces as it was broken to if it should be justified.
+    fn place_line(&mut self, line: &Line, x: f64, baseline: f64, size: f64, justify: bool) {
+        let space = Font::Regular.get_width(" ", size);
+        let stretch = match line.ratio {
+            _ if !justify => 0.0,
+            ratio if ratio < 0.0 => ratio * space_glue(space).2,
+            ratio => ratio * space_glue(space).1
+        };
+        let mut x = x;
+        for word in &line.words {
+            for run in word {
+                match *run {
+                    Run::Text { font, ref text } => self.place_text(x, baseline, font, size, text),
+                    Run::Math(ref formula) => self.place_math(&lay_out_math(formula, size), x, baseline)
+                }
+                x += run.get_width(size);
+            }
+            x += space + stretch;
+        }
+    }
+
+    fn place_math(&mut self, math: &MathBox, x: f64, baseline: f64) {
+        for glyph in &math.glyphs {
+            self.place_text(x + glyph.x, baseline + glyph.y, math_font(&glyph.text, glyph.style), glyph.size, &glyph.text);
+        }
+        for rule in &math.rules {
+            self.page.rects.push(Rect { x: x + rule.x, y: baseline + rule.y, width: rule.width, height: rule.thickness });
+        }
+    }
+
+    fn place_text(&mut self, x: f64, y: f64, font: Font, size: f64, text: &str) {
+        self.page.items.push(Placed {
+            x,
+            y,
+            font,
+            size,
+            text: text.to_string()
+        });
+    }
+
+    fn finish(mut self) -> Vec<Page> {
+        if !self.page.items.is_empty() || self.pages.is_empty() {
+            self.pages.push(self.page);
+        }
+        let setup = self.setup;
+        for (i, page) in self.pages.iter_mut().enumerate() {
+            let number = (i + 1).to_string();
+            let width = Font::Regular.get_width(&number, PAGE_NUMBER_SIZE);
+            page.items.push(Placed {
+                x: (setup.width - width) / 2.0,
+                y: setup.margin_bottom / 2.0,
+                font: Font::Regular,
+                size: PAGE_NUMBER_SIZE,
+                text: number
+            });
+        }
+        self.pages
+    }
+}
+
+fn words(text: &str, font: Font) -> Vec<Item> {
+    let mut items = Vec::new();
+    for word in text.split_whitespace() {
+        items.push(Item::Word(vec![Run::Text { font, text: word.to_string() }]));
+        items.push(Item::Space);
+    }
+    items
+}
+
+/// Resolves the styling of `nodes` into fonts, starting from `font`. Text with no space between it stays in one word.
+pub fn flatten(nodes: &[Node], font: Font) -> Vec<Item> {
+    let mut items = Vec::new();
+    flatten_into(nodes, font, &mut items);
+    items
+}
+
+fn flatten_into(nodes: &[Node], font: Font, items: &mut Vec<Item>) {
+    for node in nodes {
+        match *node {
+            Node::Text(ref text) | Node::Ref { number: ref text, .. } => push_text(items, font, text),
+            // Page references have all been replaced by their pages by now.
+            Node::PageRef { .. } => push_text(items, font, UNKNOWN_PAGE),
+            Node::Anchor(_) | Node::Contents { .. } => (),
+            Node::Space => {
+                if let Some(&Item::Word(_)) = items.last() {
+                    items.push(Item::Space);
+                }
+            },
+            Node::LineBreak => items.push(Item::Break),
+            Node::Emph(ref content) => flatten_into(content, font.with_italic(), items),
+            Node::Strong(ref content) => flatten_into(content, font.with_bold(), items),
+            Node::Math(ref formula) | Node::Equation { ref formula, .. } => match items.last_mut() {
+                Some(&mut Item::Word(ref mut runs)) => runs.push(Run::Math(formula.clone())),
+                _ => items.push(Item::Word(vec![Run::Math(formula.clone())]))
+            },
+            Node::Paragraph(ref content) | Node::Heading { ref content, .. } | Node::Figure { ref content, .. } => {
+                flatten_into(content, font, items)
+            },
+            Node::List(ref entries) => {
+                for entry in entries {
+                    flatten_into(entry, font, items);
+                    items.push(Item::Space);
+                }
+            }
+        }
+    }
+}
+
+/// `nodes` with each page reference replaced by the page its label is on in `pages`.
+fn with_page_numbers(nodes: &[Node], pages: &HashMap<String, usize>) -> Vec<Node> {
+    nodes.iter()
+        .map(|node| match *node {
+            Node::PageRef { ref label, .. } => {
+                Node::Text(pages.get(label).map(|page| page.to_string()).unwrap_or_else(|| UNKNOWN_PAGE.to_string()))
+            },
+            Node::Emph(ref content) => Node::Emph(with_page_numbers(content, pages)),
+            Node::Strong(ref content) => Node::Strong(with_page_numbers(content, pages)),
+            Node::Paragraph(ref content) => Node::Paragraph(with_page_numbers(content, pages)),
+            Node::Heading { level, ref number, ref content, ref labels, listed } => Node::Heading {
+                level,
+                number: number.clone(),
+                content: with_page_numbers(content, pages),
+                labels: labels.clone(),
+                listed
+            },
+            Node::List(ref items) => Node::List(items.iter().map(|item| with_page_numbers(item, pages)).collect()),
+            Node::Figure { ref content, ref caption, number, ref labels } => Node::Figure {
+                content: with_page_numbers(content, pages),
+                caption: with_page_numbers(caption, pages),
+                number,
+                labels: labels.clone()
+            },
+            ref node => node.clone()
+        })
+        .collect()
+}
+
+/// Collects the labels of every anchor in `nodes`, and of the headings, figures and equations among them.
+fn anchors(nodes: &[Node], labels: &mut Vec<String>) {
+    for node in nodes {
+        match *node {
+            Node::Anchor(ref label) => labels.push(label.clone()),
+            Node::Equation { labels: ref own, .. } => labels.extend(own.iter().cloned()),
+            Node::Emph(ref content) | Node::Strong(ref content) | Node::Paragraph(ref content) => anchors(content, labels),
+            Node::Heading { ref content, labels: ref own, .. } => {
+                labels.extend(own.iter().cloned());
+                anchors(content, labels);
+            },
+            Node::List(ref items) => {
+                for item in items {
+                    anchors(item, labels);
+                }
+            },
+            Node::Figure { ref content, ref caption, labels: ref own, .. } => {
+                labels.extend(own.iter().cloned());
+                anchors(content, labels);
+                anchors(caption, labels);
+            },
+            _ => ()
+        }
+    }
+}
+
+fn push_text(items: &mut Vec<Item>, font: Font, text: &str) {
+    if let Some(&mut Item::Word(ref mut runs)) = items.last_mut() {
+        push_run(runs, font, text);
+        return;
+    }
+    items.push(Item::Word(vec![Run::Text { font, text: text.to_string() }]));
+}
+
+pub fn word_width(word: &[Run], size: f64) -> f64 {
+    word.iter().map(|run| run.get_width(size)).sum()
+}
+
+fn lay_out_math(formula: &Formula, size: f64) -> MathBox {
+    math_box::lay_out(&formula.nodes, size, &|text, style, size| math_font(text, style).get_width(text, size))
+}
+
+/// The font for text in a formula: Symbol for Greek letters and math symbols, and otherwise Times in `style`.
+fn math_font(text: &str, style: Style) -> Font {
+    match style {
+        _ if Font::has_symbols(text) => Font::Symbol,
+        Style::Italic => Font::Italic,
+        Style::Upright => Font::Regular
+    }
+}
+
+/// How far a space `space` wide may stretch and shrink, as its width, stretchability and shrinkability.
+fn space_glue(space: f64) -> (f64, f64, f64) {
+    (space, space / 2.0, space / 3.0)
+}
+
+/// Breaks a paragraph into lines `measure` points wide, choosing the breaks for the whole paragraph at once so that
+/// its spacing is as even as it can be. Words may also break where `patterns` allow, at a cost. A forced break ends a
+/// line with its natural spacing.
+pub fn break_lines(items: &[Item], size: f64, measure: f64, parameters: &Parameters, patterns: &Patterns) -> Vec<Line> {
+    let (width, stretch, shrink) = space_glue(Font::Regular.get_width(" ", size));
+    let mut elements = Vec::new();
+    // For each element which sets part of a word, which word it is and that part of it.
+    let mut parts = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        match *item {
+            Item::Word(ref word) => {
+                let pieces = hyphenate(word, patterns);
+                for (i, piece) in pieces.iter().enumerate() {
+                    if i > 0 {
+                        let hyphen = pieces[i - 1].last().unwrap().get_font().get_width("-", size);
+                        elements.push(Element::Penalty { width: hyphen, penalty: HYPHEN_PENALTY, flagged: true });
+                        parts.push(None);
+                    }
+                    elements.push(Element::Box { width: word_width(piece, size) });
+                    parts.push(Some((index, piece.clone())));
+                }
+            },
+            Item::Space => {
+                if let Some(&Element::Box { .. }) = elements.last() {
+                    elements.push(Element::Glue { width, stretch, shrink });
+                    parts.push(None);
+                }
+            },
+            Item::Break => end_line(&mut elements, &mut parts)
+        }
+    }
+    if elements.last().is_some_and(|element| !is_forced(element)) {
+        end_line(&mut elements, &mut parts);
+    }
+
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for end in line_break::break_lines(&elements, measure, parameters) {
+        let mut words: Vec<Vec<Run>> = Vec::new();
+        let mut last_word = None;
+        for &(index, ref runs) in parts[start..end.position].iter().flatten() {
+            if last_word == Some(index) {
+                words.last_mut().unwrap().extend(runs.iter().cloned());
+            } else {
+                words.push(runs.clone());
+                last_word = Some(index);
+            }
+        }
+        if let Element::Penalty { flagged: true, .. } = elements[end.position] {
+            if let Some(word) = words.last_mut() {
+                let font = word.last().unwrap().get_font();
+                push_run(word, font, "-");
+            }
+        }
+        let spaces = words.len().saturating_sub(1) as f64 * width;
+        lines.push(Line {
+            width: words.iter().map(|word| word_width(word, size)).sum::<f64>() + spaces,
+            words,
+            ratio: end.ratio
+        });
+        start = end.position + 1;
+    }
+    lines
+}
+
+/// Splits `word` where `patterns` allow a hyphen. Only a word made entirely of letters is hyphenated, apart from any
+/// punctuation at either end, and never one with math in it.
+fn hyphenate(word: &[Run], patterns: &Patterns) -> Vec<Vec<Run>> {
+    let mut text = String::new();
+    for run in word {
+        match *run {
+            Run::Text { text: ref part, .. } => text.push_str(part),
+            Run::Math(_) => return vec![word.to_vec()]
+        }
+    }
+    let start = text.find(char::is_alphabetic);
+    let end = text.rfind(char::is_alphabetic).map(|end| end + text[end..].chars().next().unwrap().len_utf8());
+    let breaks = match (start, end) {
+        (Some(start), Some(end)) if text[start..end].chars().all(char::is_alphabetic) => {
+            patterns.hyphenate(&text[start..end]).into_iter().map(|offset| start + offset).collect()
+        },
+        _ => Vec::new()
+    };
+
+    let mut pieces = vec![Vec::new()];
+    let mut breaks = breaks.into_iter().peekable();
+    let mut run_start = 0;
+    for run in word {
+        let (font, text) = match *run {
+            Run::Text { font, ref text } => (font, text),
+            Run::Math(_) => unreachable!()
+        };
+        let mut from = 0;
+        while let Some(at) = breaks.next_if(|&offset| offset < run_start + text.len()) {
+            let at = at - run_start;
+            if at > from {
+                push_run(pieces.last_mut().unwrap(), font, &text[from..at]);
+            }
+            pieces.push(Vec::new());
+            from = at;
+        }
+        if from < text.len() {
+            push_run(pieces.last_mut().unwrap(), font, &text[from..]);
+        }
+        run_start += text.len();
+    }
+    pieces
+}
+
+/// Adds `text` to the end of `word`, in the same run as the last text if that is in the same font.
+fn push_run(word: &mut Vec<Run>, font: Font, text: &str) {
+    match word.last_mut() {
+        Some(&mut Run::Text { font: last, text: ref mut run }) if last == font => run.push_str(text),
+        _ => word.push(Run::Text { font, text: text.to_string() })
+    }
+}
+
+/// Ends the current line with glue that fills it and a forced break. An empty line is given an empty box to hold it open.
+fn end_line(elements: &mut Vec<Element>, parts: &mut Vec<Option<(usize, Vec<Run>)>>) {
+    if let Some(&Element::Glue { .. }) = elements.last() {
+        elements.pop();
+        parts.pop();
+    }
+    if !matches!(elements.last(), Some(&Element::Box { .. })) {
+        elements.push(Element::Box { width: 0.0 });
+        parts.push(None);
+    }
+    elements.push(Element::Glue { width: 0.0, stretch: f64::INFINITY, shrink: 0.0 });
+    elements.push(Element::Penalty { width: 0.0, penalty: -INFINITE_PENALTY, flagged: false });
+    parts.push(None);
+    parts.push(None);
+}
+
+fn is_forced(element: &Element) -> bool {
+    matches!(*element, Element::Penalty { penalty, .. } if penalty <= -INFINITE_PENALTY)
+}
+
+#[cfg(test)]
+mod test {
+    use ::eval::CommandRegistry;
+    use ::lex::Lexer;
+    use ::parse::parse;
+    use super::*;
+
+    fn lay_out_source(source: &str, setup: &PageSetup) -> Vec<Page> {
+        let doc = ::ast::lower(&parse(Lexer::new(source)).unwrap());
+        let doc = ::eval::evaluate(&doc, &CommandRegistry::with_builtins()).unwrap();
+        lay_out(&doc, setup, &Patterns::default())
+    }
+
+    #[test]
+    fn fills_pages_within_margins() {
+        let setup = PageSetup::new(300.0, 200.0).with_margins(36.0);
+        let paragraph = "Lorem ipsum dolor sit amet, {emph \"consectetur\"} adipiscing elit, sed do eiusmod tempor. ";
+        let pages = lay_out_source(&format!("{{litx :title \"T\"}}\n\n{{h1 \"One\"}}\n{}\n\n{}", paragraph.repeat(4), paragraph), &setup);
+        assert!(pages.len() > 1);
+        for (i, page) in pages.iter().enumerate() {
+            let (number, text) = page.items.split_last().unwrap();
+            assert_eq!(number.text, (i + 1).to_string());
+            for item in text {
+                let right = item.x + item.font.get_width(&item.text, item.size);
+                assert!(item.x >= 36.0 && right <= 264.0 + 1e-6, "{:?} is outside the margins", item);
+                assert!(item.y >= 36.0 && item.y <= 164.0, "{:?} is outside the margins", item);
+            }
+        }
+        let first = &pages[0].items;
+        assert_eq!((first[0].font, first[0].text.as_str()), (Font::Bold, "T"));
+        assert!(first.iter().any(|item| item.font == Font::Italic && item.text == "consectetur"));
+        // Justified lines reach the right margin exactly.
+        let line_end = first.iter()
+            .filter(|item| item.size == BODY_SIZE)
+            .map(|item| item.x + item.font.get_width(&item.text, item.size))
+            .fold(0.0, f64::max);
+        assert!((line_end - 264.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn moves_orphans_to_the_next_page() {
+        // After the first paragraph there is room for one more line, which would leave the second paragraph's first
+        // line alone at the foot of the page.
+        let setup = PageSetup::new(300.0, 112.0).with_margins(36.0);
+        let second = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor. ".repeat(3);
+        let pages = lay_out_source(&format!("First.\n\n{}", second), &setup);
+        let texts: Vec<&str> = pages[0].items.iter().map(|item| item.text.as_str()).collect();
+        assert_eq!(texts, vec!["First.", "1"]);
+        assert_eq!(pages[1].items[0].text, "Lorem");
+
+        let items = flatten(&[Node::Text("a".into()), Node::LineBreak, Node::LineBreak, Node::Text("b".into())], Font::Regular);
+        let lines = break_lines(&items, BODY_SIZE, 100.0, &Parameters::default(), &Patterns::default());
+        assert_eq!(lines.iter().map(|line| line.words.len()).collect::<Vec<_>>(), vec![1, 0, 1]);
+    }
+
+    #[test]
+    fn centres_equations() {
+        let setup = PageSetup::new(300.0, 400.0).with_margins(36.0);
+        let pages = lay_out_source("Text.\n{equation {$ x = \\frac{$1$}{$2$} $}}", &setup);
+        let items = &pages[0].items;
+        let x = items.iter().find(|item| item.text == "x").unwrap();
+        let number = items.iter().find(|item| item.text == "(1)").unwrap();
+        assert!(x.x > 36.0 + 50.0 && x.y < items[0].y - BODY_SIZE * LEADING);
+        assert!((number.x + Font::Regular.get_width("(1)", BODY_SIZE) - 264.0).abs() < 1e-6);
+        assert_eq!(number.y, x.y);
+        // The fraction bar sits above the baseline, between the numerator and denominator.
+        let bar = &pages[0].rects[0];
+        let numerator = items.iter().find(|item| item.text == "1" && item.size < BODY_SIZE).unwrap();
+        assert!(bar.y > x.y && numerator.y > bar.y);
+    }
+
+    #[test]
+    fn resolves_page_references() {
+        let setup = PageSetup::new(300.0, 200.0).with_margins(36.0);
+        let paragraph = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor. ".repeat(6);
+        let source = format!("See page {{pageref end}}.\n\n{}\n\n{{h1 \"End\"}}{{label end}}\nLast.", paragraph);
+        let pages = lay_out_source(&source, &setup);
+        let page = pages.iter().position(|page| page.items.iter().any(|item| item.text == "End")).unwrap() + 1;
+        assert!(page > 1);
+        assert_eq!(pages[0].items[2].text, format!("{}.", page));
+    }
+
+    #[test]
+    fn numbers_pages_in_contents() {
+        let setup = PageSetup::new(300.0, 200.0).with_margins(36.0);
+        let paragraph = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor. ".repeat(6);
+        let source = format!("{{toc}}\n\n{{h1 \"Start\"}}\n{}\n\n{{h2 \"End\"}}\nLast.", paragraph);
+        let pages = lay_out_source(&source, &setup);
+        // The last page with the text on it, which for a heading is its own rather than the contents.
+        let page_of = |text: &str| pages.iter().rposition(|page| page.items.iter().any(|item| item.text == text)).unwrap() + 1;
+        assert!(page_of("End") > 1);
+        let texts: Vec<&str> = pages[0].items.iter().map(|item| item.text.as_str()).collect();
+        // After the contents comes the first heading, starting with its number.
+        assert_eq!(texts[..8], ["Contents", "1", "Start", "1", "1.1", "End", &page_of("End").to_string()[..], "1"]);
+        // The page numbers are set flush with the right margin.
+        let number = &pages[0].items[3];
+        assert!((number.x + Font::Regular.get_width("1", BODY_SIZE) - 264.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn hyphenates_to_fill_lines() {
+        let patterns = Patterns::parse("\\patterns{hy3ph he2n hena4 hen5at 1na n2at 1tio 2io o2n}");
+        let items = flatten(&Node::text("Many words use hyphenation, and hyphenation is useful."), Font::Regular);
+        let measure = word_width(&[Run::Text { font: Font::Regular, text: "Many words use hyphen-".to_string() }], BODY_SIZE);
+        let lines = break_lines(&items, BODY_SIZE, measure, &Parameters::default(), &patterns);
+        let text: Vec<String> = lines.iter()
+            .map(|line| line.words.iter()
+                .map(|word| word.iter().map(|run| match *run {
+                    Run::Text { ref text, .. } => text.as_str(),
+                    Run::Math(_) => ""
+                }).collect::<String>())
+                .collect::<Vec<_>>()
+                .join(" "))
+            .collect();
+        assert!(text.iter().any(|line| line.ends_with('-')), "{:?}", text);
+        assert_eq!(text.join(" ").replace("- ", ""), "Many words use hyphenation, and hyphenation is useful.");
+    }
+}
@@ -0,0 +1,85 @@
+mod font;
+mod layout;
+mod page_setup;
+mod serialize;
+
+use ::eval::Document;
+use ::hyphenation::Patterns;
+use std::io;
+use self::font::Font;
+pub use self::page_setup::PageSetup;
+pub use self::page_setup::parse_length;
+
+/// Writes `doc` as a PDF, laid out by litx itself onto pages of the given size and margins.
+///
+/// Text is set in Times, justified, with headings in bold, emphasis in italics and a number at the foot of every page.
+/// Math is laid out as a formula, with Greek letters and symbols from the Symbol font.
+/// Words are hyphenated by `patterns`, which should be those for the document's language, and by the document's own
+/// `{hyphenate ...}` exceptions. The title and authors from the `{litx ...}` header also go into the file's document
+/// information.
+pub fn render<W: io::Write>(doc: &Document, setup: &PageSetup, patterns: &Patterns, writer: &mut W) -> io::Result<()> {
+    let mut patterns = patterns.clone();
+    for word in &doc.hyphenations {
+        patterns.add_exception(word);
+    }
+    let pages = layout::lay_out(doc, setup, &patterns);
+    serialize::write_pdf(&pages, &doc.meta, setup, writer)
+}
+
+#[cfg(test)]
+mod test {
+    use ::eval::CommandRegistry;
+    use ::lex::Lexer;
+    use ::parse::parse;
+    use super::*;
+
+    #[test]
+    fn writes_valid_structure() {
+        let source = "{litx :title \"A (short) test\" :authors {list \"Ann\" \"Zo\u{eb}\"} :lang en}\n\n\
+            {h1 \"Start\"}\nSome text, {strong \"bold\"} and caf\u{e9}. Also {$ \\frac{$\\alpha$}2 $}.";
+        let doc = ::ast::lower(&parse(Lexer::new(source)).unwrap());
+        let doc = ::eval::evaluate(&doc, &CommandRegistry::with_builtins()).unwrap();
+        let mut out = Vec::new();
+        render(&doc, &PageSetup::default(), &Patterns::default(), &mut out).unwrap();
+        // Only the binary marker in the header is outside ASCII; replacing it keeps byte offsets and indices the same.
+        let pdf: String = out.iter().map(|&byte| if byte.is_ascii() { char::from(byte) } else { '?' }).collect();
+
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.contains("/Title (A \\(short\\) test)"));
+        assert!(pdf.contains("/Author <FEFF0041006E006E002C0020005A006F00EB>"));
+        assert!(pdf.contains("/Lang (en)"));
+        assert!(pdf.contains("/Count 1"));
+        assert!(pdf.contains("/MediaBox [0 0 595.28 841.89]"));
+        assert!(pdf.contains("/BaseFont /Times-Bold /Encoding /WinAnsiEncoding"));
+        assert!(pdf.contains("(caf\\351.) Tj"));
+        assert!(pdf.contains("/BaseFont /Symbol >>"));
+        assert!(pdf.contains("/F5 7.7 Tf\n"));
+        assert!(pdf.contains("(a) Tj"));
+        assert!(pdf.contains("(1) Tj\nET\n"));
+        assert!(pdf.contains(" re f"));
+
+        let startxref = pdf.rfind("startxref\n").unwrap();
+        let xref: usize = pdf[startxref + 10..].lines().next().unwrap().parse().unwrap();
+        assert!(pdf[xref..].starts_with("xref\n0 11\n"));
+        for (i, entry) in pdf[xref..].lines().skip(3).take(10).enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj\n", i + 1)));
+        }
+        for (at, _) in pdf.match_indices("/Length ") {
+            let length: usize = pdf[at + 8..].split(' ').next().unwrap().parse().unwrap();
+            let start = at + pdf[at..].find("stream\n").unwrap() + 7;
+            assert_eq!(&pdf[start + length..start + length + 10], "\nendstream");
+        }
+    }
+
+    #[test]
+    fn page_sizes() {
+        assert_eq!(PageSetup::size_from_name("Letter"), Some((612.0, 792.0)));
+        assert_eq!(PageSetup::size_from_name("6inx9in"), Some((432.0, 648.0)));
+        assert_eq!(PageSetup::size_from_name("6x"), None);
+        assert_eq!(parse_length("25.4mm"), Some(72.0));
+        assert_eq!(parse_length("1.5in"), Some(108.0));
+        assert_eq!(parse_length("2em"), None);
+        assert_eq!(PageSetup::new(400.0, 600.0).with_margins(50.0).get_text_width(), 300.0);
+    }
+}
@@ -0,0 +1,92 @@
+/// The size of each page and the margins around its text, all in points.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct PageSetup {
+    pub width: f64,
+    pub height: f64,
+    pub margin_top: f64,
+    pub margin_right: f64,
+    pub margin_bottom: f64,
+    pub margin_left: f64
+}
+
+/// Named paper sizes, in points, portrait.
+const SIZES: [(&str, f64, f64); 5] = [
+    ("a4", 595.28, 841.89),
+    ("a5", 419.53, 595.28),
+    ("b5", 498.9, 708.66),
+    ("letter", 612.0, 792.0),
+    ("legal", 612.0, 1008.0)
+];
+
+impl PageSetup {
+    /// A page `width` by `height` points, with one-inch margins.
+    pub fn new(width: f64, height: f64) -> Self {
+        PageSetup {
+            width,
+            height,
+            margin_top: 72.0,
+            margin_right: 72.0,
+            margin_bottom: 72.0,
+            margin_left: 72.0
+        }
+    }
+
+    pub fn with_size(mut self, width: f64, height: f64) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn with_margins(mut self, margin: f64) -> Self {
+        self.margin_top = margin;
+        self.margin_right = margin;
+        self.margin_bottom = margin;
+        self.margin_left = margin;
+        self
+    }
+
+    /// The width left for text between the side margins.
+    pub fn get_text_width(&self) -> f64 {
+        self.width - self.margin_left - self.margin_right
+    }
+
+    /// The height left for text between the top and bottom margins.
+    pub fn get_text_height(&self) -> f64 {
+        self.height - self.margin_top - self.margin_bottom
+    }
+
+    /// The size of a named paper, such as `a4` or `letter`, or of an explicit `WIDTHxHEIGHT` such as `6inx9in`.
+    pub fn size_from_name(name: &str) -> Option<(f64, f64)> {
+        let name = name.to_lowercase();
+        if let Some(&(_, width, height)) = SIZES.iter().find(|size| size.0 == name) {
+            return Some((width, height));
+        }
+        let mut dimensions = name.splitn(2, 'x');
+        let width = parse_length(dimensions.next()?)?;
+        let height = parse_length(dimensions.next()?)?;
+        Some((width, height))
+    }
+}
+
+impl Default for PageSetup {
+    fn default() -> Self {
+        PageSetup::new(SIZES[0].1, SIZES[0].2)
+    }
+}
+
+/// A length in points from text such as `72`, `72pt`, `2.54cm`, `25mm` or `1in`.
+pub fn parse_length(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let units = text.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+    let number: f64 = text[..text.len() - units.len()].parse().ok()?;
+    let scale = match units {
+        "" | "pt" => 1.0,
+        "in" => 72.0,
+        "cm" => 72.0 / 2.54,
+        "mm" => 72.0 / 25.4,
+        _ => return None
+    };
+    Some(number * scale)
+}
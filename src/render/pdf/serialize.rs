@@ -0,0 +1,129 @@
+use ::meta::DocumentMeta;
+use std::io;
+use super::font::Font;
+use super::font::FONTS;
+use super::layout::Page;
+use super::PageSetup;
+
+/// Writes laid-out pages as a PDF 1.4 file, with the title and authors in its information dictionary.
+///
+/// Objects are numbered in a fixed order: the catalog, the page tree, the information dictionary, the five fonts,
+/// and then each page followed by its content stream.
+pub fn write_pdf<W: io::Write>(pages: &[Page], meta: &DocumentMeta, setup: &PageSetup, writer: &mut W) -> io::Result<()> {
+    let first_page = 4 + FONTS.len();
+    let mut objects: Vec<Vec<u8>> = Vec::new();
+
+    let mut catalog = "<< /Type /Catalog /Pages 2 0 R".to_string();
+    if let Some(ref language) = meta.language {
+        catalog.push_str(&format!(" /Lang {}", text_string(language)));
+    }
+    catalog.push_str(" >>");
+    objects.push(catalog.into_bytes());
+
+    let kids: Vec<String> = (0..pages.len()).map(|i| format!("{} 0 R", first_page + 2 * i)).collect();
+    objects.push(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len()).into_bytes());
+
+    let mut info = format!("<< /Creator (litx) /Producer (litx {})", env!("CARGO_PKG_VERSION"));
+    if let Some(ref title) = meta.title {
+        info.push_str(&format!(" /Title {}", text_string(title)));
+    }
+    if !meta.authors.is_empty() {
+        info.push_str(&format!(" /Author {}", text_string(&meta.authors.join(", "))));
+    }
+    info.push_str(" >>");
+    objects.push(info.into_bytes());
+
+    for &font in &FONTS {
+        // Symbol keeps its own encoding, as WinAnsiEncoding has no Greek letters.
+        let encoding = if font == Font::Symbol { "" } else { " /Encoding /WinAnsiEncoding" };
+        objects.push(format!("<< /Type /Font /Subtype /Type1 /BaseFont /{}{} >>", font.get_base_font(), encoding).into_bytes());
+    }
+
+    let fonts: Vec<String> = FONTS.iter()
+        .enumerate()
+        .map(|(i, font)| format!("/{} {} 0 R", font.get_resource_name(), 4 + i))
+        .collect();
+    for (i, page) in pages.iter().enumerate() {
+        objects.push(format!("<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << {} >> >> /Contents {} 0 R >>",
+            number(setup.width),
+            number(setup.height),
+            fonts.join(" "),
+            first_page + 2 * i + 1).into_bytes());
+        let content = page_content(page);
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend(content);
+        stream.extend(b"\nendstream");
+        objects.push(stream);
+    }
+
+    let mut out = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.extend(format!("{} 0 obj\n", i + 1).into_bytes());
+        out.extend(object);
+        out.extend(b"\nendobj\n");
+    }
+    let xref = out.len();
+    out.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+    for offset in offsets {
+        out.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+    }
+    out.extend(format!("trailer\n<< /Size {} /Root 1 0 R /Info 3 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref).into_bytes());
+    writer.write_all(&out)
+}
+
+fn page_content(page: &Page) -> Vec<u8> {
+    let mut content = b"BT\n".to_vec();
+    let mut current = None;
+    for item in &page.items {
+        if current != Some((item.font, item.size)) {
+            content.extend(format!("/{} {} Tf\n", item.font.get_resource_name(), number(item.size)).into_bytes());
+            current = Some((item.font, item.size));
+        }
+        content.extend(format!("1 0 0 1 {} {} Tm ", number(item.x), number(item.y)).into_bytes());
+        content.extend(content_string(item.font, &item.text));
+        content.extend(b" Tj\n");
+    }
+    content.extend(b"ET");
+    for rect in &page.rects {
+        content.extend(format!("\n{} {} {} {} re f", number(rect.x), number(rect.y), number(rect.width), number(rect.height)).into_bytes());
+    }
+    content
+}
+
+/// A number with at most two decimal places, which is finer than any printer can show.
+fn number(value: f64) -> String {
+    let text = format!("{:.2}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// `text` as a literal string in the encoding of `font`.
+fn content_string(font: Font, text: &str) -> Vec<u8> {
+    let mut string = vec![b'('];
+    for byte in text.chars().map(|c| font.encode(c)) {
+        match byte {
+            b'(' | b')' | b'\\' => string.extend(&[b'\\', byte]),
+            32..=126 => string.push(byte),
+            _ => string.extend(format!("\\{:03o}", byte).into_bytes())
+        }
+    }
+    string.push(b')');
+    string
+}
+
+/// `text` as a PDF text string: a literal string if it is plain ASCII, and otherwise UTF-16 with a byte order mark.
+fn text_string(text: &str) -> String {
+    if text.chars().all(|c| (' '..='~').contains(&c)) {
+        let escaped: String = text.chars()
+            .flat_map(|c| match c {
+                '(' | ')' | '\\' => vec!['\\', c],
+                _ => vec![c]
+            })
+            .collect();
+        format!("({})", escaped)
+    } else {
+        let units: Vec<String> = text.encode_utf16().map(|unit| format!("{:04X}", unit)).collect();
+        format!("<FEFF{}>", units.concat())
+    }
+}
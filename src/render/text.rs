@@ -0,0 +1,241 @@
+use ::eval::Document;
+use ::eval::Node;
+use std::io;
+
+/// The line width used when nothing else is asked for, which suits email.
+pub const DEFAULT_WIDTH: usize = 72;
+
+/// Part of a paragraph once styling has been flattened into plain characters.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+enum Item {
+    Word(String),
+    Break
+}
+
+/// Writes `doc` as plain text, reflowing paragraphs to fit within `width` columns wherever the words allow.
+///
+/// Headings are underlined, emphasis and strong text are marked `_like this_` and `*like this*`,
+/// and math is written as its source. Display math is centred on its own line, with any number at the right margin.
+/// There are no pages, so `{pageref ...}` gives the title of what it refers to instead.
+pub fn render<W: io::Write>(doc: &Document, width: usize, writer: &mut W) -> io::Result<()> {
+    let meta = &doc.meta;
+    let mut first = true;
+    if let Some(ref title) = meta.title {
+        let rule = "=".repeat(title.chars().count());
+        writeln!(writer, "{}\n{}\n{}", rule, title, rule)?;
+        first = false;
+    }
+    if !meta.authors.is_empty() {
+        writeln!(writer, "{}", meta.authors.join(", "))?;
+        first = false;
+    }
+    if let Some(ref date) = meta.date {
+        writeln!(writer, "{}", date)?;
+        first = false;
+    }
+    for block in &doc.blocks {
+        if !first {
+            writeln!(writer)?;
+        }
+        first = false;
+        write_block(block, width, writer)?;
+    }
+    Ok(())
+}
+
+fn write_block<W: io::Write>(node: &Node, width: usize, writer: &mut W) -> io::Result<()> {
+    match *node {
+        Node::Heading { level, ref number, ref content, .. } => {
+            let underline = match level {
+                1 => '=',
+                2 => '-',
+                _ => '~'
+            };
+            for line in wrap(&flatten(&Node::numbered(number.as_deref(), content)), width, "", "") {
+                writeln!(writer, "{}", line)?;
+                writeln!(writer, "{}", underline.to_string().repeat(line.chars().count()))?;
+            }
+            Ok(())
+        },
+        Node::List(ref items) => {
+            for item in items {
+                for line in wrap(&flatten(item), width, "- ", "  ") {
+                    writeln!(writer, "{}", line)?;
+                }
+            }
+            Ok(())
+        },
+        Node::Paragraph(ref content) => write_lines(&flatten(content), width, writer),
+        Node::Equation { ref formula, number, .. } => {
+            let label = number.map(|number| format!("({})", number)).unwrap_or_default();
+            let columns = formula.source.chars().count();
+            let indent = width.saturating_sub(columns) / 2;
+            let mut line = format!("{}{}", " ".repeat(indent), formula.source);
+            if !label.is_empty() {
+                let gap = width.saturating_sub(indent + columns + label.len()).max(1);
+                line.push_str(&" ".repeat(gap));
+                line.push_str(&label);
+            }
+            writeln!(writer, "{}", line)
+        },
+        Node::Figure { ref content, ref caption, number, .. } => {
+            write_lines(&flatten(content), width, writer)?;
+            let label = if caption.is_empty() { format!("Figure {}", number) } else { format!("Figure {}: ", number) };
+            let mut items = flatten(&Node::text(&label));
+            items.extend(flatten(caption));
+            write_lines(&items, width, writer)
+        },
+        Node::Contents { ref entries, .. } => {
+            writeln!(writer, "Contents")?;
+            writeln!(writer, "--------")?;
+            for entry in entries {
+                let mut items = entry.number.as_ref().map(|number| flatten(&Node::text(number))).unwrap_or_default();
+                items.extend(flatten(&entry.title));
+                let indent = "  ".repeat(entry.level - 1);
+                for line in wrap(&items, width, &indent, &format!("{}  ", indent)) {
+                    writeln!(writer, "{}", line)?;
+                }
+            }
+            Ok(())
+        },
+        ref inline => write_lines(&flatten(std::slice::from_ref(inline)), width, writer)
+    }
+}
+
+fn write_lines<W: io::Write>(items: &[Item], width: usize, writer: &mut W) -> io::Result<()> {
+    for line in wrap(items, width, "", "") {
+        writeln!(writer, "{}", line)?;
+    }
+    Ok(())
+}
+
+fn flatten(nodes: &[Node]) -> Vec<Item> {
+    let mut items = Vec::new();
+    let mut word = String::new();
+    flatten_into(nodes, &mut items, &mut word);
+    end_word(&mut items, &mut word);
+    items
+}
+
+/// Adjacent text with no `Space` between it stays in one word, so punctuation never wraps away from what it follows.
+fn flatten_into(nodes: &[Node], items: &mut Vec<Item>, word: &mut String) {
+    for node in nodes {
+        match *node {
+            Node::Text(ref text) | Node::Ref { number: ref text, .. } => word.push_str(text),
+            Node::PageRef { ref title, .. } => flatten_into(&Node::text(title), items, word),
+            Node::Space => end_word(items, word),
+            Node::Anchor(_) | Node::Contents { .. } => (),
+            Node::LineBreak => {
+                end_word(items, word);
+                items.push(Item::Break);
+            },
+            // Math stays as one unbreakable word, spaces and all.
+            Node::Math(ref math) | Node::Equation { formula: ref math, .. } => word.push_str(&math.source),
+            Node::Emph(ref content) => {
+                word.push('_');
+                flatten_into(content, items, word);
+                word.push('_');
+            },
+            Node::Strong(ref content) => {
+                word.push('*');
+                flatten_into(content, items, word);
+                word.push('*');
+            },
+            Node::Paragraph(ref content) | Node::Heading { ref content, .. } | Node::Figure { ref content, .. } => {
+                flatten_into(content, items, word)
+            },
+            Node::List(ref entries) => {
+                for entry in entries {
+                    end_word(items, word);
+                    flatten_into(entry, items, word);
+                }
+            }
+        }
+    }
+}
+
+fn end_word(items: &mut Vec<Item>, word: &mut String) {
+    if !word.is_empty() {
+        items.push(Item::Word(word.clone()));
+        word.clear();
+    }
+}
+
+/// Greedily fills lines of at most `width` columns. A word longer than that gets a line to itself.
+/// `first` begins the first line and `rest` every line after it, so lists can hang their items.
+fn wrap(items: &[Item], width: usize, first: &str, rest: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = first.to_string();
+    let mut columns = first.chars().count();
+    let mut empty = true;
+    for item in items {
+        match *item {
+            Item::Word(ref word) => {
+                let length = word.chars().count();
+                if !empty && columns + 1 + length > width {
+                    lines.push(line);
+                    line = rest.to_string();
+                    columns = rest.chars().count();
+                    empty = true;
+                }
+                if !empty {
+                    line.push(' ');
+                    columns += 1;
+                }
+                line.push_str(word);
+                columns += length;
+                empty = false;
+            },
+            Item::Break => {
+                lines.push(line);
+                line = rest.to_string();
+                columns = rest.chars().count();
+                empty = true;
+            }
+        }
+    }
+    if !empty || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use ::render::test::render;
+
+    #[test]
+    fn reflows_paragraphs() {
+        let text = render("{litx :title \"Notes\" :author \"C\"}\n\n{h1 \"Intro\"}\n\
+            These lines {! not this !} were\nwritten   short, but {emph \"now\"} they are {strong \"joined\"}.\n\
+            Forced\\nbreak and {$ a + b $} math.\n\n{h2 \"Items\"}\n{list \"one two three four\" \"five\"}", |doc, out| super::render(doc, 24, out));
+        assert_eq!(text, "\
+=====
+Notes
+=====
+C
+
+1 Intro
+=======
+
+These lines were written
+short, but _now_ they
+are *joined*. Forced
+break and a + b math.
+
+1.1 Items
+---------
+
+- one two three four
+- five
+");
+    }
+
+    #[test]
+    fn centres_equations() {
+        let text = render("Before\n{$$ a = b $$}\n{equation {$ E = mc^2 $}}", |doc, out| super::render(doc, 24, out));
+        assert_eq!(text, "Before\n\n         a = b\n\n        E = mc^2     (1)\n");
+    }
+}
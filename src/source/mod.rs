@@ -0,0 +1,6 @@
+mod source_map;
+mod span;
+
+pub use self::source_map::SourceFile;
+pub use self::source_map::SourceMap;
+pub use self::span::Span;
@@ -0,0 +1,134 @@
+use super::Span;
+
+/// The text of one loaded file, with the byte offset at which each of its lines starts.
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct SourceFile {
+    name: String,
+    source: String,
+    line_starts: Vec<usize>
+}
+
+impl SourceFile {
+    pub fn new<S: Into<String>, T: Into<String>>(name: S, source: T) -> Self {
+        let source = source.into();
+        let line_starts = Some(0).into_iter()
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        SourceFile {
+            name: name.into(),
+            source,
+            line_starts
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn get_line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The zero-based line and byte column of `offset`, which may be at most the length of the source.
+    pub fn get_line_col(&self, offset: usize) -> (usize, usize) {
+        assert!(offset <= self.source.len(), "offset {} is past the end of {}", offset, self.name);
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1
+        };
+        (line, offset - self.line_starts[line])
+    }
+
+    /// The text of the zero-based `line`, without its line ending.
+    pub fn get_line(&self, line: usize) -> Option<&str> {
+        let start = *self.line_starts.get(line)?;
+        let end = self.line_starts.get(line + 1).map(|&next| next - 1).unwrap_or_else(|| self.source.len());
+        Some(self.source[start..end].trim_end_matches('\r'))
+    }
+}
+
+/// Every file loaded while building a document, so positions can be turned back into lines of text.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a file, replacing any previously loaded under the same name.
+    pub fn add(&mut self, file: SourceFile) -> &SourceFile {
+        self.files.retain(|existing| existing.name != file.name);
+        self.files.push(file);
+        self.files.last().unwrap()
+    }
+
+    pub fn add_file<S: Into<String>, T: Into<String>>(&mut self, name: S, source: T) -> &SourceFile {
+        self.add(SourceFile::new(name, source))
+    }
+
+    pub fn get_file(&self, name: &str) -> Option<&SourceFile> {
+        self.files.iter().find(|file| file.name == name)
+    }
+
+    pub fn get_files(&self) -> &[SourceFile] {
+        &self.files
+    }
+
+    pub fn into_files(self) -> Vec<SourceFile> {
+        self.files
+    }
+
+    /// The zero-based line and byte column of `offset` in the file called `name`.
+    pub fn get_line_col(&self, name: &str, offset: usize) -> Option<(usize, usize)> {
+        self.get_file(name).map(|file| file.get_line_col(offset))
+    }
+
+    /// The text `span` covers, if it is in one of the loaded files.
+    pub fn get_text(&self, span: &Span) -> Option<&str> {
+        self.get_file(span.file?)?.source.get(span.start..span.end)
+    }
+}
+
+impl Extend<SourceFile> for SourceMap {
+    fn extend<I: IntoIterator<Item = SourceFile>>(&mut self, files: I) {
+        for file in files {
+            self.add(file);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::lex::Lexer;
+    use super::SourceMap;
+
+    #[test]
+    fn agrees_with_lexer() {
+        let mut map = SourceMap::new();
+        map.add_file("a.litx", "one\ntwo\n");
+        map.add_file("b.litx", "{h1 \"x\"}\n\n  \u{e9}t\u{e9} {emph y}\r\nend");
+        for name in &["a.litx", "b.litx"] {
+            let file = map.get_file(name).unwrap();
+            for token in Lexer::new_with_filename(file.get_source(), name) {
+                let span = token.get_span();
+                assert_eq!(map.get_line_col(name, span.start), Some(span.start_line_col));
+                assert_eq!(map.get_line_col(name, span.end), Some(span.end_line_col));
+            }
+        }
+        let file = map.get_file("b.litx").unwrap();
+        assert_eq!(file.get_line_count(), 4);
+        assert_eq!(file.get_line(2), Some("  \u{e9}t\u{e9} {emph y}"));
+        assert_eq!(file.get_line(3), Some("end"));
+        assert_eq!(file.get_line(4), None);
+    }
+}
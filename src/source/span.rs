@@ -0,0 +1,71 @@
+/// A range of bytes in one source file, along with the line and column at each end.
+/// Lines and columns are zero-based, and columns count bytes from the start of the line.
+/// `end` and `end_line_col` point just past the last byte, so an empty span has both ends equal.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub struct Span<'b> {
+    pub file: Option<&'b str>,
+    pub start: usize,
+    pub end: usize,
+    pub start_line_col: (usize, usize),
+    pub end_line_col: (usize, usize)
+}
+
+impl <'b> Span<'b> {
+    /// An empty span at `index`.
+    pub fn empty(file: Option<&'b str>, index: usize, line_col: (usize, usize)) -> Self {
+        Span {
+            file,
+            start: index,
+            end: index,
+            start_line_col: line_col,
+            end_line_col: line_col
+        }
+    }
+
+    /// The span covering `content`, which starts at `index` and `line_col`.
+    pub fn covering(file: Option<&'b str>, index: usize, line_col: (usize, usize), content: &str) -> Self {
+        let (line, column) = line_col;
+        let end_line_col = match content.rfind('\n') {
+            Some(last) => (line + content.matches('\n').count(), content.len() - last - 1),
+            None => (line, column + content.len())
+        };
+        Span {
+            file,
+            start: index,
+            end: index + content.len(),
+            start_line_col: line_col,
+            end_line_col
+        }
+    }
+
+    /// The span from the start of `self` to the end of `other`, which should come later in the same file.
+    pub fn to(&self, other: &Span<'b>) -> Self {
+        Span {
+            file: self.file,
+            start: self.start,
+            end: other.end,
+            start_line_col: self.start_line_col,
+            end_line_col: other.end_line_col
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Span;
+
+    #[test]
+    fn covering() {
+        let span = Span::covering(Some("a.litx"), 10, (2, 4), "ab\n\ncd");
+        assert_eq!((span.end, span.end_line_col), (16, (4, 2)));
+        let span = Span::covering(None, 10, (2, 4), "abc");
+        assert_eq!((span.end, span.end_line_col), (13, (2, 7)));
+        assert!(Span::empty(None, 3, (0, 3)).is_empty());
+    }
+}
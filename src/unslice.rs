@@ -1,5 +1,11 @@
 use std;
 
+/// Joins two slices which are adjacent in memory back into a single slice.
+///
+/// # Safety
+/// Implementors must only report slices as adjacent when they point into the same allocation,
+/// so that `unslice_unchecked` can soundly rebuild the combined slice.
+#[allow(dead_code)]
 pub unsafe trait Unslice {
     fn unslice<'a>(&'a self, next: &'a Self) -> Option<&'a Self> {
         if self.is_adjacent(next) {
@@ -14,7 +20,7 @@ pub unsafe trait Unslice {
 
 unsafe impl Unslice for str {
     fn is_adjacent(&self, next: &Self) -> bool {
-        unsafe { self.as_ptr().offset(self.len() as isize) == next.as_ptr() }
+        unsafe { self.as_ptr().add(self.len()) == next.as_ptr() }
     }
 
     unsafe fn unslice_unchecked<'a>(&'a self, next: &'a Self) -> &'a Self {
@@ -25,7 +31,7 @@ unsafe impl Unslice for str {
 
 unsafe impl <T> Unslice for [T] {
     fn is_adjacent(&self, next: &Self) -> bool {
-        unsafe { self.as_ptr().offset(self.len() as isize) == next.as_ptr() }
+        unsafe { self.as_ptr().add(self.len()) == next.as_ptr() }
     }
 
     unsafe fn unslice_unchecked<'a>(&'a self, next: &'a Self) -> &'a Self {
@@ -34,9 +40,10 @@ unsafe impl <T> Unslice for [T] {
 }
 
 #[cfg(test)]
+#[allow(unused_variables)]
 mod test {
     use super::Unslice;
-    const SRC: &'static str = "FOO BAR";
+    const SRC: &str = "FOO BAR";
 
     fn slice() -> (&'static str, &'static str, &'static str) {
         (&SRC[0..3], &SRC[3..4], &SRC[4..7])